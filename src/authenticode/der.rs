@@ -0,0 +1,365 @@
+use std::{
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::error::{Result, PeError};
+
+pub const TAG_BOOLEAN: u8 = 0x01;
+pub const TAG_INTEGER: u8 = 0x02;
+pub const TAG_BIT_STRING: u8 = 0x03;
+pub const TAG_OCTET_STRING: u8 = 0x04;
+pub const TAG_NULL: u8 = 0x05;
+pub const TAG_OID: u8 = 0x06;
+pub const TAG_UTF8_STRING: u8 = 0x0c;
+pub const TAG_PRINTABLE_STRING: u8 = 0x13;
+pub const TAG_T61_STRING: u8 = 0x14;
+pub const TAG_IA5_STRING: u8 = 0x16;
+pub const TAG_UTC_TIME: u8 = 0x17;
+pub const TAG_GENERALIZED_TIME: u8 = 0x18;
+pub const TAG_BMP_STRING: u8 = 0x1e;
+pub const TAG_SEQUENCE: u8 = 0x30;
+pub const TAG_SET: u8 = 0x31;
+
+/// Returns the tag of the constructed, context specific field `[number]`
+pub const fn context(number: u8) -> u8 {
+    0xa0 | number
+}
+
+/// Returns the tag of the primitive, context specific field `[number]`, as
+/// used by implicitly tagged primitive types
+pub const fn context_primitive(number: u8) -> u8 {
+    0x80 | number
+}
+
+/// A DER encoded value. Only single byte tags are supported, which covers
+/// everything used by Authenticode and X.509.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tlv<'a> {
+    pub tag: u8,
+    /// The contents octets
+    pub value: &'a [u8],
+    /// The whole encoding, tag and length included
+    pub raw: &'a [u8],
+}
+
+impl<'a> Tlv<'a> {
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<(Self, &'a [u8])> {
+        let (&tag, rest) = bytes.split_first().ok_or(PeError::InvalidDer)?;
+        // Multi byte tags have all five low bits set
+        if tag & 0x1f == 0x1f {
+            return Err(PeError::InvalidDer);
+        }
+
+        let (&first, mut rest) = rest.split_first()
+            .ok_or(PeError::InvalidDer)?;
+        let len = match first {
+            0x00..=0x7f => first as usize,
+            // 0x80 is the indefinite form, which DER forbids
+            0x81..=0x84 => {
+                let count = (first & 0x7f) as usize;
+                if rest.len() < count {
+                    return Err(PeError::InvalidDer);
+                }
+                let (octets, remaining) = rest.split_at(count);
+                rest = remaining;
+                octets.iter().fold(0usize, |len, &b| len << 8 | b as usize)
+            },
+            _ => return Err(PeError::InvalidDer),
+        };
+        if rest.len() < len {
+            return Err(PeError::InvalidDer);
+        }
+
+        let header_len = bytes.len() - rest.len();
+        let (value, rest) = rest.split_at(len);
+        Ok((Self { tag, value, raw: &bytes[..header_len + len] }, rest))
+    }
+
+    /// Returns a reader over the values nested in this one
+    pub fn reader(&self) -> Reader<'a> {
+        Reader::new(self.value)
+    }
+
+    /// Fails unless this value has the tag `tag`
+    pub fn expect(self, tag: u8) -> Result<Self> {
+        match self.tag == tag {
+            true => Ok(self),
+            false => Err(PeError::InvalidDer),
+        }
+    }
+
+    /// Decodes an OBJECT IDENTIFIER
+    pub fn oid(&self) -> Result<Oid> {
+        if self.tag != TAG_OID {
+            return Err(PeError::InvalidDer);
+        }
+        Oid::from_bytes(self.value)
+    }
+
+    /// Returns the contents of an INTEGER with the leading zero, added to
+    /// keep positive numbers positive, stripped
+    pub fn unsigned_integer(&self) -> Result<&'a [u8]> {
+        if self.tag != TAG_INTEGER || self.value.is_empty() {
+            return Err(PeError::InvalidDer);
+        }
+        match self.value {
+            [0, rest @ ..] if !rest.is_empty() => Ok(rest),
+            value => Ok(value),
+        }
+    }
+
+    /// Decodes a small INTEGER, such as a version number
+    pub fn small_integer(&self) -> Result<u32> {
+        let value = self.unsigned_integer()?;
+        if value.len() > 4 {
+            return Err(PeError::InvalidDer);
+        }
+        Ok(value.iter().fold(0u32, |n, &b| n << 8 | b as u32))
+    }
+
+    /// Returns the bits of a BIT STRING, which must be a whole number of
+    /// bytes
+    pub fn bit_string(&self) -> Result<&'a [u8]> {
+        match (self.tag, self.value) {
+            (TAG_BIT_STRING, [0, bits @ ..]) => Ok(bits),
+            _ => Err(PeError::InvalidDer),
+        }
+    }
+
+    /// Decodes one of the string types found in X.509 names. BMPString is
+    /// UTF-16 big endian, the 8-bit types are decoded as Latin-1 unless they
+    /// are valid UTF-8.
+    pub fn string(&self) -> Result<String> {
+        match self.tag {
+            TAG_BMP_STRING => {
+                let units: Vec<u16> = self.value.chunks_exact(2)
+                    .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+                    .collect();
+                Ok(String::from_utf16_lossy(&units))
+            },
+            TAG_UTF8_STRING | TAG_PRINTABLE_STRING | TAG_T61_STRING
+                    | TAG_IA5_STRING | 0x12 | 0x1a | 0x1c => {
+                Ok(match std::str::from_utf8(self.value) {
+                    Ok(string) => string.to_string(),
+                    Err(_) => self.value.iter().map(|&b| b as char).collect(),
+                })
+            },
+            _ => Err(PeError::InvalidDer),
+        }
+    }
+
+    /// Decodes a UTCTime or a GeneralizedTime
+    pub fn time(&self) -> Result<Time> {
+        Time::from_tlv(self)
+    }
+}
+
+/// Reads a sequence of DER values one after the other
+#[derive(Debug, Clone)]
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Returns the tag of the next value without consuming it
+    pub fn peek_tag(&self) -> Option<u8> {
+        self.bytes.first().copied()
+    }
+
+    /// Reads the next value, whatever its tag
+    pub fn read_any(&mut self) -> Result<Tlv<'a>> {
+        let (tlv, rest) = Tlv::from_bytes(self.bytes)?;
+        self.bytes = rest;
+        Ok(tlv)
+    }
+
+    /// Reads the next value, which must have the tag `tag`
+    pub fn read(&mut self, tag: u8) -> Result<Tlv<'a>> {
+        self.read_any()?.expect(tag)
+    }
+
+    /// Reads the next value if it has the tag `tag`
+    pub fn read_optional(&mut self, tag: u8) -> Result<Option<Tlv<'a>>> {
+        match self.peek_tag() == Some(tag) {
+            true => self.read_any().map(Some),
+            false => Ok(None),
+        }
+    }
+
+    /// Consumes the rest of the values
+    pub fn read_all(&mut self) -> Result<Vec<Tlv<'a>>> {
+        let mut values = Vec::new();
+        while !self.is_empty() {
+            values.push(self.read_any()?);
+        }
+        Ok(values)
+    }
+}
+
+impl<'a> Iterator for Reader<'a> {
+    type Item = Result<Tlv<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.is_empty() {
+            true => None,
+            false => Some(self.read_any()),
+        }
+    }
+}
+
+/// An OBJECT IDENTIFIER, kept in its dotted form
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Oid(pub String);
+
+impl Oid {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut arcs: Vec<u64> = Vec::new();
+        let mut arc = 0u64;
+        for (index, &byte) in bytes.iter().enumerate() {
+            if arc > u64::MAX >> 7 {
+                return Err(PeError::InvalidDer);
+            }
+            arc = arc << 7 | (byte & 0x7f) as u64;
+            if byte & 0x80 != 0 {
+                if index == bytes.len() - 1 {
+                    return Err(PeError::InvalidDer);
+                }
+                continue;
+            }
+
+            if arcs.is_empty() {
+                // The first subidentifier packs the first two arcs
+                let first = (arc / 40).min(2);
+                arcs.push(first);
+                arcs.push(arc - first * 40);
+            } else {
+                arcs.push(arc);
+            }
+            arc = 0;
+        }
+        if arcs.is_empty() {
+            return Err(PeError::InvalidDer);
+        }
+
+        let arcs: Vec<String> = arcs.iter().map(u64::to_string).collect();
+        Ok(Self(arcs.join(".")))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Oid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl PartialEq<&str> for Oid {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+/// A point in time, in UTC, as found in certificates and signatures.
+/// Fields are ordered so that comparing two times compares them
+/// chronologically.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Time {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl Time {
+    /// Converts a count of seconds since the Unix epoch
+    pub fn from_unix(seconds: u64) -> Self {
+        let days = (seconds / 86400) as i64;
+        let seconds_of_day = seconds % 86400;
+
+        // Civil from days, counting in eras of 400 years from 0000-03-01
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let day_of_era = z.rem_euclid(146_097);
+        let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524
+            - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era
+            - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+        Self {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+            hour: (seconds_of_day / 3600) as u8,
+            minute: (seconds_of_day / 60 % 60) as u8,
+            second: (seconds_of_day % 60) as u8,
+        }
+    }
+
+    /// Returns the current time of the system clock
+    pub fn now() -> Self {
+        let seconds = SystemTime::now().duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        Self::from_unix(seconds)
+    }
+
+    fn from_tlv(tlv: &Tlv) -> Result<Self> {
+        let text = std::str::from_utf8(tlv.value)
+            .map_err(|_| PeError::InvalidDer)?;
+        // Fractions of seconds may follow in GeneralizedTime
+        let text = text.strip_suffix('Z').ok_or(PeError::InvalidDer)?;
+        let text = text.split('.').next().unwrap_or(text);
+        let digits = |range: std::ops::Range<usize>| -> Result<u16> {
+            text.get(range)
+                .filter(|digits| digits.bytes().all(|b| b.is_ascii_digit()))
+                .and_then(|digits| digits.parse().ok())
+                .ok_or(PeError::InvalidDer)
+        };
+
+        let (year, rest) = match tlv.tag {
+            TAG_UTC_TIME => {
+                // Two digit years pivot around 1950, per RFC 5280
+                let year = digits(0..2)?;
+                (if year < 50 { 2000 + year } else { 1900 + year }, 2)
+            },
+            TAG_GENERALIZED_TIME => (digits(0..4)?, 4),
+            _ => return Err(PeError::InvalidDer),
+        };
+        if text.len() != rest + 10 {
+            return Err(PeError::InvalidDer);
+        }
+
+        Ok(Self {
+            year,
+            month: digits(rest..rest + 2)? as u8,
+            day: digits(rest + 2..rest + 4)? as u8,
+            hour: digits(rest + 4..rest + 6)? as u8,
+            minute: digits(rest + 6..rest + 8)? as u8,
+            second: digits(rest + 8..rest + 10)? as u8,
+        })
+    }
+}
+
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC", self.year,
+            self.month, self.day, self.hour, self.minute, self.second)
+    }
+}
@@ -0,0 +1,126 @@
+pub mod der;
+pub mod page_hashes;
+pub mod pkcs7;
+pub mod verify;
+pub mod x509;
+
+use std::{iter, ops::Range};
+
+use crate::{
+    checksum::checksum_offset,
+    crypto::DigestAlgorithm,
+    error::Result,
+    headers::pe::data_directory::{DataDirectory, DataDirectoryType},
+    PE,
+};
+
+/// Returns the file ranges covered by the Authenticode digest, in the order
+/// they are hashed:
+///
+/// - the headers, up to `size_of_headers`, minus the `checksum` field and
+///   the security entry of the data directories
+/// - the raw data of each section, sorted by file offset
+/// - whatever follows the last section, minus the attribute certificate
+///   table
+///
+/// Feed these to any hasher to get the digest with another algorithm.
+pub fn authenticode_ranges(pe: &PE) -> Result<Vec<Range<usize>>> {
+    let file_len = pe.data().len();
+    let size_of_headers = size_of_headers(pe);
+    let sections = section_ranges(pe);
+    let end_of_sections = sections.iter()
+        .map(|section| section.end)
+        .fold(size_of_headers, usize::max);
+
+    let ranges = iter::once(0..size_of_headers)
+        .chain(sections)
+        .chain(iter::once(end_of_sections..file_len));
+
+    let certificate_table = pe.opt_header.data_directories.security()
+        .map(|security| {
+            let start = security.virtual_address as usize;
+            start..start.saturating_add(security.size as usize)
+        });
+    let excluded: Vec<_> = header_exclusions(pe).into_iter()
+        .chain(certificate_table)
+        .collect();
+
+    Ok(ranges
+        .flat_map(|range| subtract(range, &excluded))
+        .filter(|range| !range.is_empty())
+        .collect())
+}
+
+/// Returns `size_of_headers`, bounded by the length of the file
+pub(crate) fn size_of_headers(pe: &PE) -> usize {
+    (pe.opt_header.win_fields.size_of_headers() as usize)
+        .min(pe.data().len())
+}
+
+/// Returns the file ranges of the raw data of the sections, sorted by file
+/// offset and bounded by the length of the file
+pub(crate) fn section_ranges(pe: &PE) -> Vec<Range<usize>> {
+    let file_len = pe.data().len();
+    let mut sections: Vec<_> = pe.sections.iter()
+        .filter(|section| section.size_of_raw_data != 0)
+        .collect();
+    sections.sort_by_key(|section| section.pointer_to_raw_data);
+    sections.into_iter()
+        .map(|section| {
+            let start = (section.pointer_to_raw_data as usize).min(file_len);
+            let end = start.saturating_add(section.size_of_raw_data as usize)
+                .min(file_len);
+            start..end
+        })
+        .collect()
+}
+
+/// Returns the ranges of the headers that signatures leave out: the
+/// `checksum` field and the security entry of the data directories
+pub(crate) fn header_exclusions(pe: &PE) -> Vec<Range<usize>> {
+    let checksum = checksum_offset(pe);
+
+    let directories = &pe.opt_header.data_directories;
+    let security_index = DataDirectoryType::Security as usize;
+    let security_entry = (directories.len() > security_index).then(|| {
+        let directories_offset = checksum
+            + match pe.opt_header.win_fields.is_pe64() {
+                true => 48,
+                false => 32,
+            };
+        let entry = directories_offset + security_index * DataDirectory::len();
+        entry..entry + DataDirectory::len()
+    });
+
+    iter::once(checksum..checksum + 4).chain(security_entry).collect()
+}
+
+/// Computes the Authenticode digest of `pe`, the one stored in the
+/// `SpcIndirectDataContent` of its signature
+pub fn authenticode_digest(pe: &PE, algorithm: DigestAlgorithm)
+        -> Result<Vec<u8>> {
+    let mut hasher = algorithm.hasher();
+    for range in authenticode_ranges(pe)? {
+        hasher.update(&pe.data()[range]);
+    }
+    Ok(hasher.finalize())
+}
+
+/// Removes the `excluded` ranges from `range`
+pub(crate) fn subtract(range: Range<usize>, excluded: &[Range<usize>])
+        -> Vec<Range<usize>> {
+    let mut pieces = vec![range];
+    for hole in excluded {
+        pieces = pieces.into_iter()
+            .flat_map(|piece| {
+                if hole.end <= piece.start || hole.start >= piece.end {
+                    return vec![piece];
+                }
+                vec![piece.start..hole.start.max(piece.start),
+                    hole.end.min(piece.end)..piece.end]
+            })
+            .filter(|piece| !piece.is_empty())
+            .collect();
+    }
+    pieces
+}
@@ -0,0 +1,173 @@
+use std::{collections::BTreeMap, ops::Range};
+
+use crate::{
+    authenticode::{
+        der::*,
+        header_exclusions, section_ranges, size_of_headers, subtract,
+    },
+    crypto::DigestAlgorithm,
+    error::{PeError, Result},
+    parsing::{take_bytes, take_u32},
+    PE,
+};
+
+/// `SPC_PE_IMAGE_PAGE_HASHES_V1`, page hashes made with SHA-1
+pub const PAGE_HASHES_V1: &str = "1.3.6.1.4.1.311.2.3.1";
+/// `SPC_PE_IMAGE_PAGE_HASHES_V2`, page hashes made with SHA-256
+pub const PAGE_HASHES_V2: &str = "1.3.6.1.4.1.311.2.3.2";
+
+/// The class id of the `SpcSerializedObject` holding the page hashes,
+/// `a6b586d5-b4a1-2466-ae05-a217da8e60d6`
+const SERIALIZED_OBJECT_CLASS_ID: [u8; 16] = [
+    0xa6, 0xb5, 0x86, 0xd5, 0xb4, 0xa1, 0x24, 0x66,
+    0xae, 0x05, 0xa2, 0x17, 0xda, 0x8e, 0x60, 0xd6,
+];
+
+/// The size of the pages that are hashed
+pub const PAGE_SIZE: usize = 0x1000;
+
+/// The digest of one page
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageHash {
+    /// The file offset of the page
+    pub offset: u32,
+    /// The digest of the page, all zeroes for the entry that ends the table
+    pub digest: Vec<u8>,
+}
+
+/// A table of page hashes. The first page is the headers, then come the
+/// raw data of the sections, split in pages, and an entry with a zero
+/// digest at the end of the last section.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageHashes {
+    pub algorithm: DigestAlgorithm,
+    pub pages: Vec<PageHash>,
+}
+
+impl PageHashes {
+    /// Finds the page hashes in the DER encoding of an `SpcPeImageData`,
+    /// where they are serialized in the `moniker` of the `file` link
+    pub fn from_image_data(bytes: &[u8]) -> Result<Option<Self>> {
+        let (data, _) = Tlv::from_bytes(bytes)?;
+        let mut reader = data.expect(TAG_SEQUENCE)?.reader();
+        // The flags are unused
+        reader.read_optional(TAG_BIT_STRING)?;
+        let Some(file) = reader.read_optional(context(0))? else {
+            return Ok(None)
+        };
+        let link = file.reader().read_any()?;
+        if link.tag != context(1) {
+            return Ok(None);
+        }
+
+        let mut object = link.reader();
+        let class_id = object.read(TAG_OCTET_STRING)?.value;
+        if class_id != SERIALIZED_OBJECT_CLASS_ID {
+            return Ok(None);
+        }
+        let (attributes, _) =
+            Tlv::from_bytes(object.read(TAG_OCTET_STRING)?.value)?;
+        for attribute in attributes.expect(TAG_SET)?.reader() {
+            let mut reader = attribute?.expect(TAG_SEQUENCE)?.reader();
+            let algorithm = match reader.read(TAG_OID)?.oid()?.as_str() {
+                PAGE_HASHES_V1 => DigestAlgorithm::Sha1,
+                PAGE_HASHES_V2 => DigestAlgorithm::Sha256,
+                _ => continue,
+            };
+            let table = reader.read(TAG_SET)?.reader()
+                .read(TAG_OCTET_STRING)?;
+            return Self::from_table(algorithm, table.value).map(Some);
+        }
+        Ok(None)
+    }
+
+    /// Parses a table of file offsets, each followed by a digest made with
+    /// `algorithm`
+    pub fn from_table(algorithm: DigestAlgorithm, mut bytes: &[u8])
+            -> Result<Self> {
+        let entry_len = 4 + algorithm.digest_len();
+        if !bytes.len().is_multiple_of(entry_len) {
+            return Err(PeError::InvalidDer);
+        }
+        let mut pages = Vec::with_capacity(bytes.len() / entry_len);
+        while !bytes.is_empty() {
+            let (offset, rest) = take_u32(bytes)?;
+            let (digest, rest) = take_bytes(rest, algorithm.digest_len())?;
+            pages.push(PageHash { offset, digest: digest.to_vec() });
+            bytes = rest;
+        }
+        Ok(Self { algorithm, pages })
+    }
+
+    /// Recomputes the page hashes of `pe` with `algorithm`
+    pub fn compute(pe: &PE, algorithm: DigestAlgorithm) -> Self {
+        let data = pe.data();
+        let zeroes = [0u8; PAGE_SIZE];
+
+        // The headers make one page, without the fields that signatures
+        // leave out but padded as if they were there
+        let size_of_headers = size_of_headers(pe);
+        let mut hasher = algorithm.hasher();
+        for range in subtract(0..size_of_headers, &header_exclusions(pe)) {
+            hasher.update(&data[range]);
+        }
+        hasher.update(&zeroes[..PAGE_SIZE.saturating_sub(size_of_headers)]);
+        let header = PageHash { offset: 0, digest: hasher.finalize() };
+        let mut pages = vec![header];
+
+        let sections = section_ranges(pe);
+        for section in &sections {
+            let pages_of_section = data[section.clone()].chunks(PAGE_SIZE);
+            for (index, page) in pages_of_section.enumerate() {
+                let offset = section.start + index * PAGE_SIZE;
+                let mut hasher = algorithm.hasher();
+                hasher.update(page);
+                hasher.update(&zeroes[page.len()..]);
+                pages.push(PageHash {
+                    offset: offset as u32,
+                    digest: hasher.finalize(),
+                });
+            }
+        }
+
+        let end = sections.iter().map(|section| section.end).max()
+            .unwrap_or(size_of_headers);
+        pages.push(PageHash {
+            offset: end as u32,
+            digest: vec![0; algorithm.digest_len()],
+        });
+        Self { algorithm, pages }
+    }
+
+    /// Returns the file range each page covers: from its offset up to the
+    /// next page, at most `PAGE_SIZE` bytes. The entry that ends the table
+    /// covers nothing.
+    pub fn ranges(&self) -> Vec<Range<usize>> {
+        let offsets: Vec<usize> = self.pages.iter()
+            .map(|page| page.offset as usize)
+            .collect();
+        offsets.iter().enumerate()
+            .map(|(index, &start)| {
+                let next = offsets.get(index + 1).copied().unwrap_or(start);
+                start..next.clamp(start, start + PAGE_SIZE)
+            })
+            .collect()
+    }
+
+    /// Compares these page hashes, usually the signed ones, with the ones
+    /// of `pe` and returns the file ranges of the pages that differ. Pages
+    /// that `pe` does not have at the same offset are reported as well.
+    pub fn modified_pages(&self, pe: &PE) -> Vec<Range<usize>> {
+        let computed: BTreeMap<u32, Vec<u8>> =
+            Self::compute(pe, self.algorithm).pages.into_iter()
+                .map(|page| (page.offset, page.digest))
+                .collect();
+        self.pages.iter()
+            .zip(self.ranges())
+            .filter(|(page, _)| {
+                computed.get(&page.offset) != Some(&page.digest)
+            })
+            .map(|(_, range)| range)
+            .collect()
+    }
+}
@@ -0,0 +1,375 @@
+use crate::{
+    authenticode::{
+        der::*,
+        page_hashes::PageHashes,
+        x509::{AlgorithmIdentifier, Name, X509Certificate},
+    },
+    crypto::DigestAlgorithm,
+    error::{Result, PeError},
+};
+
+/// `signedData` content type
+pub const SIGNED_DATA: &str = "1.2.840.113549.1.7.2";
+/// `SPC_INDIRECT_DATA_OBJID`, the content of Authenticode signatures
+pub const SPC_INDIRECT_DATA: &str = "1.3.6.1.4.1.311.2.1.4";
+/// `SPC_PE_IMAGE_DATAOBJ`, the data type of signatures over PE images
+pub const SPC_PE_IMAGE_DATA: &str = "1.3.6.1.4.1.311.2.1.15";
+/// `id-ct-TSTInfo`, the content of RFC 3161 timestamp tokens
+pub const TST_INFO: &str = "1.2.840.113549.1.9.16.1.4";
+/// `contentType` attribute
+pub const CONTENT_TYPE: &str = "1.2.840.113549.1.9.3";
+/// `messageDigest` attribute
+pub const MESSAGE_DIGEST: &str = "1.2.840.113549.1.9.4";
+/// `signingTime` attribute
+pub const SIGNING_TIME: &str = "1.2.840.113549.1.9.5";
+/// `countersignature` attribute, a PKCS#9 timestamp
+pub const COUNTERSIGNATURE: &str = "1.2.840.113549.1.9.6";
+/// `SPC_RFC3161_OBJID`, an RFC 3161 timestamp token
+pub const RFC3161_TIMESTAMP: &str = "1.3.6.1.4.1.311.3.3.1";
+/// `SPC_NESTED_SIGNATURE_OBJID`, an additional signature of the image
+pub const NESTED_SIGNATURE: &str = "1.3.6.1.4.1.311.2.4.1";
+
+/// Signatures nested deeper than this are rejected
+const MAX_NESTING: usize = 4;
+
+impl DigestAlgorithm {
+    /// Returns the algorithm identified by `oid`, if it is supported
+    pub fn from_oid(oid: &Oid) -> Option<Self> {
+        match oid.as_str() {
+            "1.3.14.3.2.26" => Some(Self::Sha1),
+            "2.16.840.1.101.3.4.2.1" => Some(Self::Sha256),
+            _ => None,
+        }
+    }
+}
+
+/// The `SpcIndirectDataContent` of an Authenticode signature: what was
+/// signed and the digest of the image
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndirectData {
+    /// The type of `data`, `SPC_PE_IMAGE_DATAOBJ` for images
+    pub data_type: Oid,
+    /// The DER encoding of the `SpcPeImageData`, if present
+    pub data: Option<Vec<u8>>,
+    pub digest_algorithm: AlgorithmIdentifier,
+    /// The Authenticode digest of the image
+    pub digest: Vec<u8>,
+}
+
+impl IndirectData {
+    pub fn from_tlv(tlv: Tlv) -> Result<Self> {
+        let mut reader = tlv.expect(TAG_SEQUENCE)?.reader();
+        let mut data = reader.read(TAG_SEQUENCE)?.reader();
+        let data_type = data.read(TAG_OID)?.oid()?;
+        let data = data.read_all()?.first().map(|value| value.raw.to_vec());
+
+        let mut digest_info = reader.read(TAG_SEQUENCE)?.reader();
+        let digest_algorithm =
+            AlgorithmIdentifier::from_tlv(digest_info.read(TAG_SEQUENCE)?)?;
+        let digest = digest_info.read(TAG_OCTET_STRING)?.value.to_vec();
+
+        Ok(Self { data_type, data, digest_algorithm, digest })
+    }
+
+    /// Returns the digest algorithm, if it is supported
+    pub fn algorithm(&self) -> Option<DigestAlgorithm> {
+        DigestAlgorithm::from_oid(&self.digest_algorithm.oid)
+    }
+
+    /// Returns the page hashes serialized in the `SpcPeImageData`, if any
+    pub fn page_hashes(&self) -> Result<Option<PageHashes>> {
+        match (&self.data, self.data_type == SPC_PE_IMAGE_DATA) {
+            (Some(data), true) => PageHashes::from_image_data(data),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// The `TSTInfo` of an RFC 3161 timestamp token
+#[derive(Debug, Clone, PartialEq)]
+pub struct TstInfo {
+    pub policy: Oid,
+    pub hash_algorithm: AlgorithmIdentifier,
+    /// The digest of the timestamped data, the signature of the signer
+    pub hashed_message: Vec<u8>,
+    pub serial: Vec<u8>,
+    /// When the timestamp was issued
+    pub gen_time: Time,
+}
+
+impl TstInfo {
+    pub fn from_der(bytes: &[u8]) -> Result<Self> {
+        let (tlv, _) = Tlv::from_bytes(bytes)?;
+        let mut reader = tlv.expect(TAG_SEQUENCE)?.reader();
+        reader.read(TAG_INTEGER)?;
+        let policy = reader.read(TAG_OID)?.oid()?;
+        let mut imprint = reader.read(TAG_SEQUENCE)?.reader();
+        let hash_algorithm =
+            AlgorithmIdentifier::from_tlv(imprint.read(TAG_SEQUENCE)?)?;
+        let hashed_message = imprint.read(TAG_OCTET_STRING)?.value.to_vec();
+        let serial = reader.read(TAG_INTEGER)?.unsigned_integer()?.to_vec();
+        let gen_time = reader.read(TAG_GENERALIZED_TIME)?.time()?;
+
+        Ok(Self { policy, hash_algorithm, hashed_message, serial, gen_time })
+    }
+}
+
+/// An RFC 3161 timestamp: a `SignedData` from the timestamping authority
+/// over a `TSTInfo`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timestamp {
+    pub signed_data: SignedData,
+    pub info: TstInfo,
+}
+
+/// A `SignerInfo`, along with what its unauthenticated attributes carry
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignerInfo {
+    pub version: u32,
+    /// Issuer of the signing certificate
+    pub issuer: Option<Name>,
+    /// Serial number of the signing certificate
+    pub serial: Option<Vec<u8>>,
+    /// Subject key identifier of the signing certificate, used instead of
+    /// the issuer and serial number by version 3 signers
+    pub subject_key_id: Option<Vec<u8>>,
+    pub digest_algorithm: AlgorithmIdentifier,
+    /// The DER encoding of the authenticated attributes, with the implicit
+    /// `[0]` tag. The signature covers them with a SET tag instead.
+    pub authenticated_attributes: Option<Vec<u8>>,
+    /// The `contentType` authenticated attribute
+    pub content_type: Option<Oid>,
+    /// The `messageDigest` authenticated attribute, the digest of the
+    /// content
+    pub message_digest: Option<Vec<u8>>,
+    /// The `signingTime` authenticated attribute, set by PKCS#9
+    /// countersignatures
+    pub signing_time: Option<Time>,
+    pub signature_algorithm: AlgorithmIdentifier,
+    /// The encrypted digest
+    pub signature: Vec<u8>,
+    /// PKCS#9 countersignatures, whose `messageDigest` is the digest of
+    /// `signature`
+    pub countersignatures: Vec<SignerInfo>,
+    /// RFC 3161 timestamps over `signature`
+    pub timestamps: Vec<Timestamp>,
+    /// Additional signatures of the image
+    pub nested_signatures: Vec<SignedData>,
+}
+
+impl SignerInfo {
+    fn from_tlv(tlv: Tlv, depth: usize) -> Result<Self> {
+        if depth > MAX_NESTING {
+            return Err(PeError::InvalidDer);
+        }
+
+        let mut reader = tlv.expect(TAG_SEQUENCE)?.reader();
+        let version = reader.read(TAG_INTEGER)?.small_integer()?;
+
+        let identifier = reader.read_any()?;
+        let (issuer, serial, subject_key_id) = match identifier.tag {
+            TAG_SEQUENCE => {
+                let mut reader = identifier.reader();
+                let issuer = Name::from_tlv(reader.read(TAG_SEQUENCE)?)?;
+                let serial = reader.read(TAG_INTEGER)?.unsigned_integer()?;
+                (Some(issuer), Some(serial.to_vec()), None)
+            },
+            tag if tag == context_primitive(0) => {
+                (None, None, Some(identifier.value.to_vec()))
+            },
+            _ => return Err(PeError::InvalidDer),
+        };
+
+        let digest_algorithm =
+            AlgorithmIdentifier::from_tlv(reader.read(TAG_SEQUENCE)?)?;
+
+        let mut signer = Self {
+            version, issuer, serial, subject_key_id, digest_algorithm,
+            authenticated_attributes: None,
+            content_type: None,
+            message_digest: None,
+            signing_time: None,
+            signature_algorithm: AlgorithmIdentifier {
+                oid: Oid(String::new()),
+                parameters: None,
+            },
+            signature: Vec::new(),
+            countersignatures: Vec::new(),
+            timestamps: Vec::new(),
+            nested_signatures: Vec::new(),
+        };
+
+        if let Some(attributes) = reader.read_optional(context(0))? {
+            signer.authenticated_attributes = Some(attributes.raw.to_vec());
+            for (oid, values) in attributes_of(attributes)? {
+                let Some(value) = values.first() else { continue };
+                match oid.as_str() {
+                    CONTENT_TYPE => signer.content_type = Some(value.oid()?),
+                    MESSAGE_DIGEST => {
+                        let digest = value.expect(TAG_OCTET_STRING)?;
+                        signer.message_digest = Some(digest.value.to_vec());
+                    },
+                    SIGNING_TIME => signer.signing_time = Some(value.time()?),
+                    _ => {},
+                }
+            }
+        }
+
+        signer.signature_algorithm =
+            AlgorithmIdentifier::from_tlv(reader.read(TAG_SEQUENCE)?)?;
+        signer.signature = reader.read(TAG_OCTET_STRING)?.value.to_vec();
+
+        if let Some(attributes) = reader.read_optional(context(1))? {
+            for (oid, values) in attributes_of(attributes)? {
+                for value in values {
+                    match oid.as_str() {
+                        COUNTERSIGNATURE => {
+                            signer.countersignatures
+                                .push(Self::from_tlv(value, depth + 1)?);
+                        },
+                        RFC3161_TIMESTAMP => {
+                            let signed_data =
+                                SignedData::from_tlv(value, depth + 1)?;
+                            if signed_data.content_type != TST_INFO {
+                                return Err(PeError::InvalidDer);
+                            }
+                            let info = TstInfo::from_der(&signed_data.content)?;
+                            signer.timestamps
+                                .push(Timestamp { signed_data, info });
+                        },
+                        NESTED_SIGNATURE => {
+                            signer.nested_signatures
+                                .push(SignedData::from_tlv(value, depth + 1)?);
+                        },
+                        _ => {},
+                    }
+                }
+            }
+        }
+
+        Ok(signer)
+    }
+
+    /// Returns the certificate that made this signature among
+    /// `certificates`
+    pub fn find_certificate<'a>(&self, certificates: &'a [X509Certificate])
+            -> Option<&'a X509Certificate> {
+        certificates.iter().find(|certificate| {
+            match (&self.issuer, &self.serial, &self.subject_key_id) {
+                (Some(issuer), Some(serial), _) => {
+                    certificate.issuer.raw == issuer.raw
+                        && &certificate.serial == serial
+                },
+                (_, _, Some(key_id)) => {
+                    certificate.subject_key_id() == Some(&key_id[..])
+                },
+                _ => false,
+            }
+        })
+    }
+}
+
+/// Splits a SET of attributes into their type and values
+fn attributes_of(attributes: Tlv) -> Result<Vec<(Oid, Vec<Tlv>)>> {
+    attributes.reader()
+        .map(|attribute| {
+            let mut reader = attribute?.expect(TAG_SEQUENCE)?.reader();
+            let oid = reader.read(TAG_OID)?.oid()?;
+            let values = reader.read(TAG_SET)?.reader().read_all()?;
+            Ok((oid, values))
+        })
+        .collect()
+}
+
+/// A PKCS#7 `SignedData`, as found in Authenticode signatures and RFC 3161
+/// timestamp tokens
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignedData {
+    pub version: u32,
+    pub digest_algorithms: Vec<AlgorithmIdentifier>,
+    /// The type of the signed content
+    pub content_type: Oid,
+    /// The signed content. For Authenticode, this is the
+    /// `SpcIndirectDataContent` SEQUENCE; for timestamps, what the OCTET
+    /// STRING wraps.
+    pub content: Vec<u8>,
+    /// The decoded content of Authenticode signatures
+    pub indirect_data: Option<IndirectData>,
+    pub certificates: Vec<X509Certificate>,
+    pub signers: Vec<SignerInfo>,
+}
+
+impl SignedData {
+    /// Parses a DER encoded `ContentInfo` wrapping a `SignedData`, such as
+    /// the content of an Authenticode `WIN_CERTIFICATE`
+    pub fn from_der(bytes: &[u8]) -> Result<Self> {
+        let (tlv, _) = Tlv::from_bytes(bytes)?;
+        Self::from_tlv(tlv, 0)
+    }
+
+    fn from_tlv(tlv: Tlv, depth: usize) -> Result<Self> {
+        if depth > MAX_NESTING {
+            return Err(PeError::InvalidDer);
+        }
+
+        let mut content_info = tlv.expect(TAG_SEQUENCE)?.reader();
+        if content_info.read(TAG_OID)?.oid()? != SIGNED_DATA {
+            return Err(PeError::InvalidDer);
+        }
+        let signed_data = content_info.read(context(0))?.reader()
+            .read(TAG_SEQUENCE)?;
+
+        let mut reader = signed_data.reader();
+        let version = reader.read(TAG_INTEGER)?.small_integer()?;
+        let digest_algorithms = reader.read(TAG_SET)?.reader()
+            .map(|algorithm| AlgorithmIdentifier::from_tlv(algorithm?))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut encapsulated = reader.read(TAG_SEQUENCE)?.reader();
+        let content_type = encapsulated.read(TAG_OID)?.oid()?;
+        let content = match encapsulated.read_optional(context(0))? {
+            Some(content) => content.reader().read_any()?,
+            None => return Err(PeError::InvalidDer),
+        };
+        let (content, indirect_data) = match content_type.as_str() {
+            SPC_INDIRECT_DATA => {
+                (content.raw, Some(IndirectData::from_tlv(content)?))
+            },
+            _ if content.tag == TAG_OCTET_STRING => (content.value, None),
+            _ => (content.raw, None),
+        };
+
+        let mut certificates = Vec::new();
+        if let Some(set) = reader.read_optional(context(0))? {
+            for certificate in set.reader() {
+                let certificate = certificate?;
+                // Attribute certificates and other choices are skipped
+                if certificate.tag == TAG_SEQUENCE {
+                    certificates.push(X509Certificate::from_tlv(certificate)?);
+                }
+            }
+        }
+        // Revocation lists are not used
+        reader.read_optional(context(1))?;
+
+        let signers = reader.read(TAG_SET)?.reader()
+            .map(|signer| SignerInfo::from_tlv(signer?, depth))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            version, digest_algorithms, content_type,
+            content: content.to_vec(), indirect_data, certificates, signers
+        })
+    }
+
+    /// Returns this signature followed by the nested ones, depth first
+    pub fn all_signatures(&self) -> Vec<&SignedData> {
+        let mut signatures = vec![self];
+        for signer in &self.signers {
+            for nested in &signer.nested_signatures {
+                signatures.extend(nested.all_signatures());
+            }
+        }
+        signatures
+    }
+}
@@ -0,0 +1,642 @@
+use std::{fmt, fs, path::Path};
+
+use crate::{
+    authenticode::{
+        der::{Oid, Time, Tlv, TAG_SET},
+        pkcs7::{SignedData, SignerInfo, SPC_INDIRECT_DATA, SPC_PE_IMAGE_DATA},
+        x509::{Name, X509Certificate, RSA_ENCRYPTION},
+    },
+    crypto::DigestAlgorithm,
+    error::{PeError, Result},
+    PE,
+};
+
+/// `sha1WithRSAEncryption`
+pub const SHA1_WITH_RSA: &str = "1.2.840.113549.1.1.5";
+/// `sha256WithRSAEncryption`
+pub const SHA256_WITH_RSA: &str = "1.2.840.113549.1.1.11";
+
+/// `id-kp-codeSigning`, the purpose required of signers
+pub const CODE_SIGNING: &str = "1.3.6.1.5.5.7.3.3";
+/// `id-kp-timeStamping`, the purpose required of timestamp authorities
+pub const TIME_STAMPING: &str = "1.3.6.1.5.5.7.3.8";
+
+/// Chains longer than this, leaf and anchor included, are rejected
+const MAX_CHAIN_LEN: usize = 8;
+
+/// The certificates signatures are checked against. Roots are trust
+/// anchors: a chain is trusted once it reaches one of them. Intermediates
+/// only help build chains, like the certificates embedded in signatures.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrustStore {
+    pub roots: Vec<X509Certificate>,
+    pub intermediates: Vec<X509Certificate>,
+}
+
+impl TrustStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads every file of the directory `path` as a DER or PEM encoded
+    /// certificate. Self-issued certificates become roots, the others
+    /// intermediates; subdirectories are skipped.
+    pub fn from_dir(path: impl AsRef<Path>) -> Result<Self> {
+        let mut store = Self::new();
+        let mut paths = fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        paths.sort();
+        for path in paths.iter().filter(|path| path.is_file()) {
+            store.add_file(&fs::read(path)?)?;
+        }
+        Ok(store)
+    }
+
+    /// Adds the certificates of a file, which is PEM if it starts with a
+    /// `-----BEGIN` line and DER otherwise
+    pub fn add_file(&mut self, bytes: &[u8]) -> Result<()> {
+        let text = std::str::from_utf8(bytes).ok()
+            .filter(|text| text.trim_start().starts_with("-----BEGIN"));
+        match text {
+            Some(text) => self.add_pem(text),
+            None => self.add_der(bytes),
+        }
+    }
+
+    /// Adds a DER encoded certificate, as a root if it is self-issued
+    pub fn add_der(&mut self, bytes: &[u8]) -> Result<()> {
+        let certificate = X509Certificate::from_der(bytes)?;
+        match certificate.is_self_issued() {
+            true => self.add_root(certificate),
+            false => self.add_intermediate(certificate),
+        }
+        Ok(())
+    }
+
+    /// Adds every `CERTIFICATE` block of a PEM file, as roots if they are
+    /// self-issued
+    pub fn add_pem(&mut self, text: &str) -> Result<()> {
+        let blocks = pem_certificates(text)?;
+        if blocks.is_empty() {
+            return Err(PeError::InvalidPem);
+        }
+        for block in blocks {
+            self.add_der(&block)?;
+        }
+        Ok(())
+    }
+
+    /// Trusts `certificate` as an anchor, whether it is self-issued or not
+    pub fn add_root(&mut self, certificate: X509Certificate) {
+        if !self.roots.contains(&certificate) {
+            self.roots.push(certificate);
+        }
+    }
+
+    pub fn add_intermediate(&mut self, certificate: X509Certificate) {
+        if !self.intermediates.contains(&certificate) {
+            self.intermediates.push(certificate);
+        }
+    }
+
+    fn is_root(&self, certificate: &X509Certificate) -> bool {
+        self.roots.iter().any(|root| root.raw == certificate.raw)
+    }
+}
+
+/// Why a signature, or the chain of its signer, is not valid
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerificationFailure {
+    /// The signed content is not an `SpcIndirectDataContent` over a PE image
+    NotAuthenticode,
+    /// The `SignedData` has no `SignerInfo`, or more than one
+    SignerCount(usize),
+    /// The digest algorithm is not supported
+    UnsupportedDigest(Oid),
+    /// The signed digest is not the one of the image
+    ImageDigestMismatch,
+    /// The signer has no authenticated attributes, or they lack the
+    /// `contentType` or `messageDigest` attribute
+    MissingAuthenticatedAttributes,
+    /// The `contentType` attribute does not match the signed content
+    ContentTypeMismatch,
+    /// The `messageDigest` attribute is not the digest of the signed content
+    MessageDigestMismatch,
+    /// The certificate named by the signer is not in the signature
+    SignerCertificateNotFound,
+    /// The extended key usage of the signer's certificate does not allow
+    /// `usage`
+    KeyUsageNotAllowed { subject: Name, usage: Oid },
+    /// The signature or public key algorithm is not RSA with a supported
+    /// digest
+    UnsupportedSignatureAlgorithm(Oid),
+    /// The signer's signature over its authenticated attributes is wrong
+    BadSignature { subject: Name },
+    /// No certificate with a matching name and key issued `subject`
+    IssuerNotFound { subject: Name, issuer: Name },
+    /// A certificate matched `subject` by name, but its key did not verify
+    /// the signature of `subject`
+    BadCertificateSignature { subject: Name },
+    /// A certificate issued others but is not a CA
+    NotCa { subject: Name },
+    /// The chain ends with a self-issued certificate that is not a root of
+    /// the trust store
+    UntrustedRoot { subject: Name },
+    /// A certificate is not valid at the time the chain is checked at
+    CertificateNotValidAt { subject: Name, time: Time },
+    /// The chain is longer than the supported maximum or loops
+    ChainTooLong,
+    /// A timestamp does not cover the signature it is attached to
+    TimestampMismatch,
+}
+
+impl fmt::Display for VerificationFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotAuthenticode => {
+                write!(f, "the signed content is not a PE image digest")
+            },
+            Self::SignerCount(count) => {
+                write!(f, "expected one signer, found {}", count)
+            },
+            Self::UnsupportedDigest(oid) => {
+                write!(f, "unsupported digest algorithm {}", oid)
+            },
+            Self::ImageDigestMismatch => {
+                write!(f, "the image digest does not match the signed one")
+            },
+            Self::MissingAuthenticatedAttributes => {
+                write!(f, "the signer lacks authenticated attributes")
+            },
+            Self::ContentTypeMismatch => {
+                write!(f, "the contentType attribute does not match")
+            },
+            Self::MessageDigestMismatch => {
+                write!(f, "the messageDigest attribute does not match")
+            },
+            Self::SignerCertificateNotFound => {
+                write!(f, "the signer certificate is missing")
+            },
+            Self::KeyUsageNotAllowed { subject, usage } => {
+                write!(f, "{} may not be used for {}", subject, usage)
+            },
+            Self::UnsupportedSignatureAlgorithm(oid) => {
+                write!(f, "unsupported signature algorithm {}", oid)
+            },
+            Self::BadSignature { subject } => {
+                write!(f, "bad signature by {}", subject)
+            },
+            Self::IssuerNotFound { subject, issuer } => {
+                write!(f, "issuer {} of {} not found", issuer, subject)
+            },
+            Self::BadCertificateSignature { subject } => {
+                write!(f, "bad signature on the certificate of {}", subject)
+            },
+            Self::NotCa { subject } => write!(f, "{} is not a CA", subject),
+            Self::UntrustedRoot { subject } => {
+                write!(f, "root {} is not trusted", subject)
+            },
+            Self::CertificateNotValidAt { subject, time } => {
+                write!(f, "the certificate of {} is not valid at {}",
+                    subject, time)
+            },
+            Self::ChainTooLong => write!(f, "the chain is too long"),
+            Self::TimestampMismatch => {
+                write!(f, "the timestamp does not cover the signature")
+            },
+        }
+    }
+}
+
+/// The outcome of checking a timestamp or a legacy countersignature
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimestampVerification {
+    /// The time vouched for by the timestamp authority, if it says
+    pub time: Option<Time>,
+    /// The subject of the timestamp authority's certificate
+    pub subject: Option<Name>,
+    /// The chain of the authority, from its certificate up to the last one
+    /// found
+    pub chain: Vec<X509Certificate>,
+    pub failures: Vec<VerificationFailure>,
+}
+
+impl TimestampVerification {
+    pub fn is_valid(&self) -> bool {
+        self.time.is_some() && self.failures.is_empty()
+    }
+}
+
+/// The outcome of checking one signature, the primary one or a nested one
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignatureVerification {
+    pub digest_algorithm: Option<DigestAlgorithm>,
+    /// The subject of the signer's certificate
+    pub subject: Option<Name>,
+    /// The chain of the signer, from its certificate up to the trust anchor
+    /// or the last certificate found
+    pub chain: Vec<X509Certificate>,
+    pub timestamps: Vec<TimestampVerification>,
+    /// The time the chain was checked at: that of the first valid
+    /// timestamp, or the current time if there is none
+    pub time: Time,
+    pub failures: Vec<VerificationFailure>,
+}
+
+impl SignatureVerification {
+    pub fn is_valid(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// The outcome of checking every signature of an image
+#[derive(Debug, Clone, PartialEq)]
+pub struct Verification {
+    /// The primary signatures followed by the nested ones
+    pub signatures: Vec<SignatureVerification>,
+}
+
+impl Verification {
+    /// Returns whether at least one signature is valid, the way Windows
+    /// accepts an image whose SHA-1 or SHA-256 signature is valid
+    pub fn is_valid(&self) -> bool {
+        self.signatures.iter().any(SignatureVerification::is_valid)
+    }
+}
+
+/// Checks every Authenticode signature of `pe` against `store`
+pub fn verify_signatures(pe: &PE, store: &TrustStore) -> Result<Verification> {
+    let mut signatures = Vec::new();
+    for signed_data in pe.signatures()? {
+        for signed_data in signed_data.all_signatures() {
+            signatures.push(verify_signature(pe, signed_data, store)?);
+        }
+    }
+    Ok(Verification { signatures })
+}
+
+/// Checks one Authenticode signature and its timestamps
+pub fn verify_signature(pe: &PE, signed_data: &SignedData,
+        store: &TrustStore) -> Result<SignatureVerification> {
+    let mut failures = Vec::new();
+
+    let indirect_data = signed_data.indirect_data.as_ref()
+        .filter(|data| data.data_type == SPC_PE_IMAGE_DATA);
+    let digest_algorithm = indirect_data.and_then(|data| data.algorithm());
+    match (indirect_data, digest_algorithm) {
+        (None, _) => failures.push(VerificationFailure::NotAuthenticode),
+        (Some(data), None) => {
+            let oid = data.digest_algorithm.oid.clone();
+            failures.push(VerificationFailure::UnsupportedDigest(oid));
+        },
+        (Some(data), Some(algorithm)) => {
+            if pe.authenticode_digest(algorithm)? != data.digest {
+                failures.push(VerificationFailure::ImageDigestMismatch);
+            }
+        },
+    }
+
+    let signer = match signed_data.signers.as_slice() {
+        [signer] => signer,
+        signers => {
+            failures.push(VerificationFailure::SignerCount(signers.len()));
+            return Ok(SignatureVerification {
+                digest_algorithm, subject: None, chain: Vec::new(),
+                timestamps: Vec::new(), time: Time::now(), failures
+            });
+        },
+    };
+
+    let timestamps = verify_timestamps(signer, &signed_data.certificates,
+        store);
+    let time = timestamps.iter()
+        .find(|timestamp| timestamp.is_valid())
+        .and_then(|timestamp| timestamp.time)
+        .unwrap_or_else(Time::now);
+
+    // The messageDigest covers the content of `SpcIndirectDataContent`,
+    // without its tag and length
+    let Ok((content, _)) = Tlv::from_bytes(&signed_data.content) else {
+        if !failures.contains(&VerificationFailure::NotAuthenticode) {
+            failures.push(VerificationFailure::NotAuthenticode);
+        }
+        return Ok(SignatureVerification {
+            digest_algorithm, subject: None, chain: Vec::new(), timestamps,
+            time, failures
+        });
+    };
+    let checked = verify_signer(signer, Some(SPC_INDIRECT_DATA),
+        content.value, CODE_SIGNING, &signed_data.certificates, store, time);
+    failures.extend(checked.failures);
+
+    Ok(SignatureVerification {
+        digest_algorithm, subject: checked.subject, chain: checked.chain,
+        timestamps, time, failures
+    })
+}
+
+/// Checks the RFC 3161 timestamps and the legacy countersignatures of
+/// `signer`, whose certificates are `certificates`
+fn verify_timestamps(signer: &SignerInfo, certificates: &[X509Certificate],
+        store: &TrustStore) -> Vec<TimestampVerification> {
+    let mut verifications = Vec::new();
+
+    for timestamp in &signer.timestamps {
+        let time = timestamp.info.gen_time;
+        let mut failures = Vec::new();
+        let imprint = DigestAlgorithm::from_oid(
+            &timestamp.info.hash_algorithm.oid);
+        match imprint {
+            Some(algorithm) => {
+                if algorithm.digest(&signer.signature)
+                        != timestamp.info.hashed_message {
+                    failures.push(VerificationFailure::TimestampMismatch);
+                }
+            },
+            None => {
+                let oid = timestamp.info.hash_algorithm.oid.clone();
+                failures.push(VerificationFailure::UnsupportedDigest(oid));
+            },
+        }
+
+        let signed_data = &timestamp.signed_data;
+        let checked = match signed_data.signers.as_slice() {
+            [tsa] => verify_signer(tsa, Some(signed_data.content_type.as_str()),
+                &signed_data.content, TIME_STAMPING, &signed_data.certificates,
+                store, time),
+            signers => Checked::failed(
+                VerificationFailure::SignerCount(signers.len())),
+        };
+        failures.extend(checked.failures);
+        verifications.push(TimestampVerification {
+            time: Some(time), subject: checked.subject, chain: checked.chain,
+            failures
+        });
+    }
+
+    // Legacy countersignatures sign the signature itself, with the
+    // certificates of the outer signature
+    for countersignature in &signer.countersignatures {
+        let time = countersignature.signing_time;
+        let checked = verify_signer(countersignature, None, &signer.signature,
+            TIME_STAMPING, certificates, store, time.unwrap_or_else(Time::now));
+        verifications.push(TimestampVerification {
+            time, subject: checked.subject, chain: checked.chain,
+            failures: checked.failures
+        });
+    }
+
+    verifications
+}
+
+/// What checking a signer gave
+struct Checked {
+    subject: Option<Name>,
+    chain: Vec<X509Certificate>,
+    failures: Vec<VerificationFailure>,
+}
+
+impl Checked {
+    fn failed(failure: VerificationFailure) -> Self {
+        Self { subject: None, chain: Vec::new(), failures: vec![failure] }
+    }
+}
+
+/// Checks that `signer` signed `content`, whose type is `content_type` when
+/// the attributes must name it, that its certificate may be used for
+/// `usage` and that it chains to `store` at `time`
+fn verify_signer(signer: &SignerInfo, content_type: Option<&str>,
+        content: &[u8], usage: &str, certificates: &[X509Certificate],
+        store: &TrustStore, time: Time) -> Checked {
+    let Some(certificate) = signer.find_certificate(certificates) else {
+        return Checked::failed(VerificationFailure::SignerCertificateNotFound)
+    };
+    let subject = Some(certificate.subject.clone());
+    let mut failures = Vec::new();
+    if !certificate.allows_usage(usage) {
+        failures.push(VerificationFailure::KeyUsageNotAllowed {
+            subject: certificate.subject.clone(),
+            usage: Oid(usage.to_string()),
+        });
+    }
+
+    let algorithm = DigestAlgorithm::from_oid(&signer.digest_algorithm.oid);
+    let Some(algorithm) = algorithm else {
+        let oid = signer.digest_algorithm.oid.clone();
+        failures.push(VerificationFailure::UnsupportedDigest(oid));
+        return Checked { subject, chain: Vec::new(), failures };
+    };
+
+    match (&signer.authenticated_attributes, &signer.message_digest) {
+        (Some(attributes), Some(message_digest)) => {
+            if let Some(content_type) = content_type {
+                if signer.content_type.as_ref()
+                        .is_none_or(|oid| oid != &content_type) {
+                    failures.push(VerificationFailure::ContentTypeMismatch);
+                }
+            }
+            if &algorithm.digest(content) != message_digest {
+                failures.push(VerificationFailure::MessageDigestMismatch);
+            }
+
+            // The signature covers the attributes encoded as a SET, not
+            // with the implicit [0] tag they are stored with
+            let mut signed = attributes.clone();
+            signed[0] = TAG_SET;
+            let verified = signature_digest(&signer.signature_algorithm.oid,
+                    algorithm)
+                .and_then(|algorithm| {
+                    verify_rsa(certificate, algorithm, &signed,
+                        &signer.signature)
+                });
+            match verified {
+                Ok(true) => {},
+                Ok(false) => {
+                    failures.push(VerificationFailure::BadSignature {
+                        subject: certificate.subject.clone()
+                    });
+                },
+                Err(failure) => failures.push(failure),
+            }
+        },
+        _ => failures.push(VerificationFailure::MissingAuthenticatedAttributes),
+    }
+
+    let (chain, chain_failures) = build_chain(certificate, certificates,
+        store, time);
+    failures.extend(chain_failures);
+    Checked { subject, chain, failures }
+}
+
+/// Returns the digest of the signature algorithm `oid`. Bare
+/// `rsaEncryption` uses the digest of the signer, `digest`.
+fn signature_digest(oid: &Oid, digest: DigestAlgorithm)
+        -> std::result::Result<DigestAlgorithm, VerificationFailure> {
+    match oid.as_str() {
+        RSA_ENCRYPTION => Ok(digest),
+        SHA1_WITH_RSA => Ok(DigestAlgorithm::Sha1),
+        SHA256_WITH_RSA => Ok(DigestAlgorithm::Sha256),
+        _ => Err(VerificationFailure::UnsupportedSignatureAlgorithm(
+            oid.clone())),
+    }
+}
+
+/// Checks an RSA `signature` over `data` with the key of `certificate`.
+/// Fails if the key is not an RSA key.
+fn verify_rsa(certificate: &X509Certificate, algorithm: DigestAlgorithm,
+        data: &[u8], signature: &[u8])
+        -> std::result::Result<bool, VerificationFailure> {
+    let key = certificate.rsa_public_key().map_err(|_| {
+        VerificationFailure::UnsupportedSignatureAlgorithm(
+            certificate.public_key_algorithm.oid.clone())
+    })?;
+    Ok(key.verify_pkcs1v15(algorithm, &algorithm.digest(data), signature))
+}
+
+/// Checks that `issuer` signed `certificate`
+fn issued_by(certificate: &X509Certificate, issuer: &X509Certificate)
+        -> std::result::Result<bool, VerificationFailure> {
+    let oid = &certificate.signature_algorithm.oid;
+    let algorithm = match oid.as_str() {
+        SHA1_WITH_RSA => DigestAlgorithm::Sha1,
+        SHA256_WITH_RSA => DigestAlgorithm::Sha256,
+        _ => {
+            return Err(VerificationFailure::UnsupportedSignatureAlgorithm(
+                oid.clone()))
+        },
+    };
+    verify_rsa(issuer, algorithm, &certificate.tbs, &certificate.signature)
+}
+
+/// Builds the chain of `leaf` up to a root of `store`, with the
+/// intermediates of the store and the certificates of the signature, and
+/// checks every link and validity period
+fn build_chain(leaf: &X509Certificate, certificates: &[X509Certificate],
+        store: &TrustStore, time: Time)
+        -> (Vec<X509Certificate>, Vec<VerificationFailure>) {
+    let mut chain = vec![leaf.clone()];
+    let mut failures = Vec::new();
+
+    loop {
+        let current = &chain[chain.len() - 1];
+        if !(current.not_before..=current.not_after).contains(&time) {
+            failures.push(VerificationFailure::CertificateNotValidAt {
+                subject: current.subject.clone(), time
+            });
+        }
+        if store.is_root(current) {
+            break;
+        }
+        if chain.len() == MAX_CHAIN_LEN {
+            failures.push(VerificationFailure::ChainTooLong);
+            break;
+        }
+
+        // Roots are tried first so that chains end as soon as possible
+        let mut candidates = store.roots.iter()
+            .chain(&store.intermediates)
+            .chain(certificates)
+            .filter(|candidate| candidate.subject.raw == current.issuer.raw);
+        let mut issuer = None;
+        let mut failure = None;
+        for candidate in &mut candidates {
+            match issued_by(current, candidate) {
+                Ok(true) => {
+                    issuer = Some(candidate);
+                    break;
+                },
+                Ok(false) => {
+                    let subject = current.subject.clone();
+                    failure =
+                        Some(VerificationFailure::BadCertificateSignature {
+                            subject
+                        });
+                },
+                Err(unsupported) => failure = Some(unsupported),
+            }
+        }
+
+        let Some(issuer) = issuer else {
+            failures.push(match failure {
+                Some(failure) => failure,
+                None if current.is_self_issued() => {
+                    VerificationFailure::UntrustedRoot {
+                        subject: current.subject.clone()
+                    }
+                },
+                None => VerificationFailure::IssuerNotFound {
+                    subject: current.subject.clone(),
+                    issuer: current.issuer.clone(),
+                },
+            });
+            break;
+        };
+
+        // A self-issued certificate that is not a root ends the chain
+        if issuer.raw == current.raw {
+            failures.push(VerificationFailure::UntrustedRoot {
+                subject: current.subject.clone()
+            });
+            break;
+        }
+        if chain.iter().any(|certificate| certificate.raw == issuer.raw) {
+            failures.push(VerificationFailure::ChainTooLong);
+            break;
+        }
+        // Version 1 certificates predate basicConstraints
+        if issuer.version >= 2 && !issuer.is_ca() {
+            failures.push(VerificationFailure::NotCa {
+                subject: issuer.subject.clone()
+            });
+        }
+        chain.push(issuer.clone());
+    }
+
+    (chain, failures)
+}
+
+/// Returns the contents of the `CERTIFICATE` blocks of a PEM file
+fn pem_certificates(text: &str) -> Result<Vec<Vec<u8>>> {
+    let mut blocks = Vec::new();
+    let mut body: Option<String> = None;
+    for line in text.lines().map(str::trim) {
+        match (&mut body, line) {
+            (None, "-----BEGIN CERTIFICATE-----") => body = Some(String::new()),
+            (Some(_), "-----END CERTIFICATE-----") => {
+                blocks.push(decode_base64(&body.take().unwrap_or_default())?);
+            },
+            (Some(body), line) => body.push_str(line),
+            (None, _) => {},
+        }
+    }
+    match body {
+        Some(_) => Err(PeError::InvalidPem),
+        None => Ok(blocks),
+    }
+}
+
+/// Decodes standard base64, with or without padding
+fn decode_base64(text: &str) -> Result<Vec<u8>> {
+    let text = text.trim_end_matches('=');
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in text.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return Err(PeError::InvalidPem),
+        };
+        buffer = buffer << 6 | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Ok(bytes)
+}
@@ -0,0 +1,278 @@
+use std::fmt;
+
+use crate::{
+    authenticode::der::*,
+    crypto::rsa::RsaPublicKey,
+    error::{PeError, Result},
+};
+
+/// `basicConstraints` extension
+pub const BASIC_CONSTRAINTS: &str = "2.5.29.19";
+/// `subjectKeyIdentifier` extension
+pub const SUBJECT_KEY_IDENTIFIER: &str = "2.5.29.14";
+/// `extKeyUsage` extension
+pub const EXTENDED_KEY_USAGE: &str = "2.5.29.37";
+/// `anyExtendedKeyUsage`, which allows every purpose
+pub const ANY_EXTENDED_KEY_USAGE: &str = "2.5.29.37.0";
+/// `rsaEncryption` public keys
+pub const RSA_ENCRYPTION: &str = "1.2.840.113549.1.1.1";
+
+/// An `AlgorithmIdentifier`, with its parameters left encoded
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlgorithmIdentifier {
+    pub oid: Oid,
+    /// The DER encoding of the parameters, absent or NULL for most
+    /// algorithms
+    pub parameters: Option<Vec<u8>>,
+}
+
+impl AlgorithmIdentifier {
+    pub fn from_tlv(tlv: Tlv) -> Result<Self> {
+        let mut reader = tlv.expect(TAG_SEQUENCE)?.reader();
+        let oid = reader.read(TAG_OID)?.oid()?;
+        let parameters = match reader.read_any() {
+            Ok(parameters) if parameters.tag != TAG_NULL => {
+                Some(parameters.raw.to_vec())
+            },
+            _ => None,
+        };
+        Ok(Self { oid, parameters })
+    }
+}
+
+/// A distinguished name, such as the subject or the issuer of a certificate
+#[derive(Debug, Clone, PartialEq)]
+pub struct Name {
+    /// The attributes, as type and value, in the order they are encoded
+    pub attributes: Vec<(Oid, String)>,
+    /// The DER encoding, which is what chains are matched on
+    pub raw: Vec<u8>,
+}
+
+impl Name {
+    pub fn from_tlv(tlv: Tlv) -> Result<Self> {
+        let mut attributes = Vec::new();
+        for set in tlv.expect(TAG_SEQUENCE)?.reader() {
+            for attribute in set?.expect(TAG_SET)?.reader() {
+                let mut reader = attribute?.expect(TAG_SEQUENCE)?.reader();
+                let oid = reader.read(TAG_OID)?.oid()?;
+                let value = reader.read_any()?;
+                // Attribute values that are not strings are shown encoded
+                let value = value.string().unwrap_or_else(|_| {
+                    value.raw.iter().map(|b| format!("{:02x}", b)).collect()
+                });
+                attributes.push((oid, value));
+            }
+        }
+        Ok(Self { attributes, raw: tlv.raw.to_vec() })
+    }
+
+    /// Returns the first value of the attribute `oid`
+    pub fn get(&self, oid: &str) -> Option<&str> {
+        self.attributes.iter()
+            .find(|(key, _)| key == &oid)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the common name, `CN`
+    pub fn common_name(&self) -> Option<&str> {
+        self.get("2.5.4.3")
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, (oid, value)) in self.attributes.iter().enumerate() {
+            if index != 0 {
+                write!(f, ", ")?;
+            }
+            let key = match oid.as_str() {
+                "2.5.4.3" => "CN",
+                "2.5.4.5" => "serialNumber",
+                "2.5.4.6" => "C",
+                "2.5.4.7" => "L",
+                "2.5.4.8" => "ST",
+                "2.5.4.10" => "O",
+                "2.5.4.11" => "OU",
+                "1.2.840.113549.1.9.1" => "emailAddress",
+                oid => oid,
+            };
+            write!(f, "{}={}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// A certificate extension, with its value left encoded
+#[derive(Debug, Clone, PartialEq)]
+pub struct Extension {
+    pub oid: Oid,
+    pub critical: bool,
+    /// The DER encoding held by the `extnValue` OCTET STRING
+    pub value: Vec<u8>,
+}
+
+/// An X.509 certificate
+#[derive(Debug, Clone, PartialEq)]
+pub struct X509Certificate {
+    /// The whole encoding of the certificate
+    pub raw: Vec<u8>,
+    /// The encoding of the `tbsCertificate`, the part covered by the
+    /// signature
+    pub tbs: Vec<u8>,
+    /// The version, 0 for v1 up to 2 for v3
+    pub version: u32,
+    /// The serial number, big endian
+    pub serial: Vec<u8>,
+    pub issuer: Name,
+    pub subject: Name,
+    pub not_before: Time,
+    pub not_after: Time,
+    pub public_key_algorithm: AlgorithmIdentifier,
+    /// The `subjectPublicKey` bits, an `RSAPublicKey` for RSA keys
+    pub public_key: Vec<u8>,
+    pub extensions: Vec<Extension>,
+    pub signature_algorithm: AlgorithmIdentifier,
+    pub signature: Vec<u8>,
+}
+
+impl X509Certificate {
+    pub fn from_der(bytes: &[u8]) -> Result<Self> {
+        let (tlv, _) = Tlv::from_bytes(bytes)?;
+        Self::from_tlv(tlv)
+    }
+
+    pub fn from_tlv(tlv: Tlv) -> Result<Self> {
+        let mut reader = tlv.expect(TAG_SEQUENCE)?.reader();
+        let tbs = reader.read(TAG_SEQUENCE)?;
+        let signature_algorithm =
+            AlgorithmIdentifier::from_tlv(reader.read(TAG_SEQUENCE)?)?;
+        let signature = reader.read(TAG_BIT_STRING)?.bit_string()?.to_vec();
+
+        let mut fields = tbs.reader();
+        let version = match fields.read_optional(context(0))? {
+            Some(version) => {
+                version.reader().read(TAG_INTEGER)?.small_integer()?
+            },
+            None => 0,
+        };
+        let serial = fields.read(TAG_INTEGER)?.unsigned_integer()?.to_vec();
+        // The signature algorithm is repeated inside the signed part
+        fields.read(TAG_SEQUENCE)?;
+        let issuer = Name::from_tlv(fields.read(TAG_SEQUENCE)?)?;
+        let mut validity = fields.read(TAG_SEQUENCE)?.reader();
+        let not_before = validity.read_any()?.time()?;
+        let not_after = validity.read_any()?.time()?;
+        let subject = Name::from_tlv(fields.read(TAG_SEQUENCE)?)?;
+        let mut key_info = fields.read(TAG_SEQUENCE)?.reader();
+        let public_key_algorithm =
+            AlgorithmIdentifier::from_tlv(key_info.read(TAG_SEQUENCE)?)?;
+        let public_key = key_info.read(TAG_BIT_STRING)?.bit_string()?.to_vec();
+
+        // The unique identifiers, [1] and [2], are skipped
+        let mut extensions = Vec::new();
+        for field in fields {
+            let field = field?;
+            if field.tag != context(3) {
+                continue;
+            }
+            for extension in field.reader().read(TAG_SEQUENCE)?.reader() {
+                let mut reader = extension?.expect(TAG_SEQUENCE)?.reader();
+                let oid = reader.read(TAG_OID)?.oid()?;
+                let critical = match reader.read_optional(TAG_BOOLEAN)? {
+                    Some(critical) => critical.value.first() == Some(&0xff),
+                    None => false,
+                };
+                let value = reader.read(TAG_OCTET_STRING)?.value.to_vec();
+                extensions.push(Extension { oid, critical, value });
+            }
+        }
+
+        Ok(Self {
+            raw: tlv.raw.to_vec(), tbs: tbs.raw.to_vec(), version, serial,
+            issuer, subject, not_before, not_after, public_key_algorithm,
+            public_key, extensions, signature_algorithm, signature
+        })
+    }
+
+    pub fn extension(&self, oid: &str) -> Option<&Extension> {
+        self.extensions.iter().find(|extension| extension.oid == oid)
+    }
+
+    /// Returns whether the certificate may issue other certificates, from
+    /// its `basicConstraints` extension
+    pub fn is_ca(&self) -> bool {
+        let Some(extension) = self.extension(BASIC_CONSTRAINTS) else {
+            return false
+        };
+        let Ok((constraints, _)) = Tlv::from_bytes(&extension.value) else {
+            return false
+        };
+        matches!(constraints.reader().read_optional(TAG_BOOLEAN),
+            Ok(Some(ca)) if ca.value.first() == Some(&0xff))
+    }
+
+    /// Returns the key identifier of the `subjectKeyIdentifier` extension
+    pub fn subject_key_id(&self) -> Option<&[u8]> {
+        let extension = self.extension(SUBJECT_KEY_IDENTIFIER)?;
+        let (key_id, _) = Tlv::from_bytes(&extension.value).ok()?;
+        key_id.expect(TAG_OCTET_STRING).ok().map(|key_id| key_id.value)
+    }
+
+    /// Returns the purposes of the `extKeyUsage` extension, `None` if the
+    /// certificate has none and may be used for any purpose. A malformed
+    /// extension allows no purpose.
+    pub fn extended_key_usage(&self) -> Option<Vec<Oid>> {
+        let extension = self.extension(EXTENDED_KEY_USAGE)?;
+        let usages = Tlv::from_bytes(&extension.value)
+            .and_then(|(usages, _)| usages.expect(TAG_SEQUENCE))
+            .and_then(|usages| {
+                usages.reader()
+                    .map(|usage| usage?.oid())
+                    .collect::<Result<Vec<_>>>()
+            });
+        Some(usages.unwrap_or_default())
+    }
+
+    /// Returns whether the certificate may be used for `usage`, an
+    /// extended key usage purpose
+    pub fn allows_usage(&self, usage: &str) -> bool {
+        self.extended_key_usage().is_none_or(|usages| {
+            usages.iter().any(|oid| *oid == usage
+                || *oid == ANY_EXTENDED_KEY_USAGE)
+        })
+    }
+
+    /// Returns whether the certificate is signed by its own key's owner
+    pub fn is_self_issued(&self) -> bool {
+        self.issuer.raw == self.subject.raw
+    }
+
+    /// Decodes the public key, which must be an RSA key
+    pub fn rsa_public_key(&self) -> Result<RsaPublicKey> {
+        if self.public_key_algorithm.oid != RSA_ENCRYPTION {
+            return Err(PeError::InvalidDer);
+        }
+        let (key, _) = Tlv::from_bytes(&self.public_key)?;
+        let mut reader = key.expect(TAG_SEQUENCE)?.reader();
+        let modulus = reader.read(TAG_INTEGER)?.unsigned_integer()?.to_vec();
+        let exponent = reader.read(TAG_INTEGER)?.unsigned_integer()?.to_vec();
+        Ok(RsaPublicKey { modulus, exponent })
+    }
+
+    /// Returns the serial number as uppercase hex
+    pub fn serial_hex(&self) -> String {
+        self.serial.iter().map(|b| format!("{:02X}", b)).collect()
+    }
+}
+
+/// Parses every certificate of a DER blob, which must be a concatenation of
+/// certificates
+pub fn parse_certificates(bytes: &[u8]) -> Result<Vec<X509Certificate>> {
+    let mut reader = Reader::new(bytes);
+    let mut certificates = Vec::new();
+    while !reader.is_empty() {
+        certificates.push(X509Certificate::from_tlv(reader.read_any()?)?);
+    }
+    Ok(certificates)
+}
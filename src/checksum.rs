@@ -0,0 +1,31 @@
+use crate::{headers::pe::file_header::FileHeader, PE};
+
+/// Offset of the `checksum` field from the start of the optional header,
+/// the same for PE32 and PE32+
+pub(crate) const CHECKSUM_OFFSET: usize = 64;
+
+/// Returns the file offset of the `checksum` field of the optional header
+pub(crate) fn checksum_offset(pe: &PE) -> usize {
+    pe.dos_header.e_lfanew as usize + FileHeader::len() + CHECKSUM_OFFSET
+}
+
+/// Computes the image checksum the way `CheckSumMappedFile` does: the file
+/// is summed as little endian 16-bit words, with the carries folded back in
+/// and the `checksum` field read as zero, and the file length is added to
+/// the result. An odd last byte counts as a word of its own.
+pub fn compute_checksum(pe: &PE) -> u32 {
+    let data = pe.data();
+    let field = checksum_offset(pe)..checksum_offset(pe) + 4;
+
+    let mut sum = 0u32;
+    for (index, pair) in data.chunks(2).enumerate() {
+        let word = pair.iter().enumerate()
+            .filter(|(byte, _)| !field.contains(&(index * 2 + byte)))
+            .fold(0, |word, (byte, &value)| {
+                word | (value as u32) << (byte * 8)
+            });
+        sum += word;
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    sum.wrapping_add(data.len() as u32)
+}
@@ -0,0 +1,170 @@
+use std::cmp::Ordering;
+
+/// An arbitrary precision unsigned integer, just enough of one for RSA
+/// signature checks. Limbs are little endian and the top one is never zero.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct BigUint {
+    limbs: Vec<u32>,
+}
+
+impl BigUint {
+    pub(crate) fn from_be_bytes(bytes: &[u8]) -> Self {
+        let limbs = bytes.rchunks(4)
+            .map(|chunk| {
+                chunk.iter().fold(0u32, |limb, &byte| limb << 8 | byte as u32)
+            })
+            .collect();
+        Self::normalized(limbs)
+    }
+
+    /// Encodes the number as `len` big endian bytes, or `None` if it does
+    /// not fit
+    pub(crate) fn to_be_bytes(&self, len: usize) -> Option<Vec<u8>> {
+        let bytes: Vec<u8> = self.limbs.iter().rev()
+            .flat_map(|limb| limb.to_be_bytes())
+            .skip_while(|byte| *byte == 0)
+            .collect();
+        if bytes.len() > len {
+            return None;
+        }
+        let mut padded = vec![0; len - bytes.len()];
+        padded.extend(bytes);
+        Some(padded)
+    }
+
+    pub(crate) fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    fn normalized(mut limbs: Vec<u32>) -> Self {
+        while limbs.last() == Some(&0) {
+            limbs.pop();
+        }
+        Self { limbs }
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        let mut limbs = vec![0u32; self.limbs.len() + other.limbs.len()];
+        for (i, &a) in self.limbs.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, &b) in other.limbs.iter().enumerate() {
+                let t = a as u64 * b as u64 + limbs[i + j] as u64 + carry;
+                limbs[i + j] = t as u32;
+                carry = t >> 32;
+            }
+            limbs[i + other.limbs.len()] = carry as u32;
+        }
+        Self::normalized(limbs)
+    }
+
+    /// Computes `self % modulus` with Knuth's algorithm D. `modulus` must
+    /// not be zero.
+    fn rem(&self, modulus: &Self) -> Self {
+        if self.cmp(modulus) == Ordering::Less {
+            return self.clone();
+        }
+        let n = modulus.limbs.len();
+        if n == 1 {
+            let divisor = modulus.limbs[0] as u64;
+            let rem = self.limbs.iter().rev()
+                .fold(0u64, |rem, &limb| (rem << 32 | limb as u64) % divisor);
+            return Self::normalized(vec![rem as u32]);
+        }
+
+        // Shift both so that the top limb of the divisor has its high bit set
+        let shift = modulus.limbs[n - 1].leading_zeros();
+        let v = shift_left(&modulus.limbs, shift, n);
+        let mut u = shift_left(&self.limbs, shift, self.limbs.len() + 1);
+        let base = 1u64 << 32;
+
+        for j in (0..u.len() - n).rev() {
+            let top = (u[j + n] as u64) << 32 | u[j + n - 1] as u64;
+            let mut qhat = top / v[n - 1] as u64;
+            let mut rhat = top % v[n - 1] as u64;
+            while qhat >= base
+                    || qhat * v[n - 2] as u64
+                        > (rhat << 32 | u[j + n - 2] as u64) {
+                qhat -= 1;
+                rhat += v[n - 1] as u64;
+                if rhat >= base {
+                    break;
+                }
+            }
+
+            // Subtract qhat times the divisor
+            let mut borrow = 0i64;
+            for i in 0..n {
+                let product = qhat * v[i] as u64;
+                let low = (product & 0xffff_ffff) as i64;
+                let t = u[i + j] as i64 - borrow - low;
+                u[i + j] = t as u32;
+                borrow = (product >> 32) as i64 - (t >> 32);
+            }
+            let t = u[j + n] as i64 - borrow;
+            u[j + n] = t as u32;
+
+            // qhat was one too large, add the divisor back
+            if t < 0 {
+                let mut carry = 0u64;
+                for i in 0..n {
+                    let t = u[i + j] as u64 + v[i] as u64 + carry;
+                    u[i + j] = t as u32;
+                    carry = t >> 32;
+                }
+                u[j + n] = u[j + n].wrapping_add(carry as u32);
+            }
+        }
+
+        // Undo the shift on the remainder
+        let mut limbs = vec![0u32; n];
+        for i in 0..n {
+            limbs[i] = match shift {
+                0 => u[i],
+                _ => u[i] >> shift | u[i + 1] << (32 - shift),
+            };
+        }
+        Self::normalized(limbs)
+    }
+
+    /// Computes `self ^ exponent % modulus`, the exponent being big endian
+    /// bytes. `modulus` must not be zero.
+    pub(crate) fn mod_pow(&self, exponent: &[u8], modulus: &Self) -> Self {
+        let base = self.rem(modulus);
+        let mut result = Self::normalized(vec![1]).rem(modulus);
+        for byte in exponent {
+            for bit in (0..8).rev() {
+                result = result.mul(&result).rem(modulus);
+                if byte >> bit & 1 == 1 {
+                    result = result.mul(&base).rem(modulus);
+                }
+            }
+        }
+        result
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.limbs.len().cmp(&other.limbs.len())
+            .then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+}
+
+/// Shifts `limbs` left by `shift` bits, which is less than 32, into a
+/// buffer of `len` limbs
+fn shift_left(limbs: &[u32], shift: u32, len: usize) -> Vec<u32> {
+    let mut shifted = vec![0u32; len];
+    for (i, &limb) in limbs.iter().enumerate() {
+        shifted[i] |= limb << shift;
+        if shift != 0 && i + 1 < len {
+            shifted[i + 1] = limb >> (32 - shift);
+        }
+    }
+    shifted
+}
@@ -0,0 +1,133 @@
+pub(crate) mod bignum;
+pub mod md5;
+pub mod rsa;
+pub mod sha1;
+pub mod sha256;
+
+use crate::crypto::{sha1::Sha1, sha256::Sha256};
+
+/// A digest algorithm used by Authenticode signatures. The implementations
+/// live in the crate to keep it free of dependencies and make no attempt at
+/// being constant time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DigestAlgorithm {
+    Sha1,
+    Sha256,
+}
+
+impl DigestAlgorithm {
+    /// Size of the digest, in bytes
+    pub fn digest_len(&self) -> usize {
+        match self {
+            Self::Sha1 => 20,
+            Self::Sha256 => 32,
+        }
+    }
+
+    /// The DER encoding of the `DigestInfo` that precedes the digest in
+    /// PKCS#1 v1.5 signatures
+    pub fn digest_info_prefix(&self) -> &'static [u8] {
+        match self {
+            Self::Sha1 => &[
+                0x30, 0x21, 0x30, 0x09, 0x06, 0x05, 0x2b, 0x0e, 0x03, 0x02,
+                0x1a, 0x05, 0x00, 0x04, 0x14,
+            ],
+            Self::Sha256 => &[
+                0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01,
+                0x65, 0x03, 0x04, 0x02, 0x01, 0x05, 0x00, 0x04, 0x20,
+            ],
+        }
+    }
+
+    pub fn hasher(&self) -> Hasher {
+        match self {
+            Self::Sha1 => Hasher::Sha1(Sha1::new()),
+            Self::Sha256 => Hasher::Sha256(Sha256::new()),
+        }
+    }
+
+    /// Hashes `data` in one go
+    pub fn digest(&self, data: &[u8]) -> Vec<u8> {
+        let mut hasher = self.hasher();
+        hasher.update(data);
+        hasher.finalize()
+    }
+}
+
+/// A running digest of one of the supported algorithms
+#[derive(Debug, Clone)]
+pub enum Hasher {
+    Sha1(Sha1),
+    Sha256(Sha256),
+}
+
+impl Hasher {
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Sha1(hasher) => hasher.update(data),
+            Self::Sha256(hasher) => hasher.update(data),
+        }
+    }
+
+    pub fn finalize(self) -> Vec<u8> {
+        match self {
+            Self::Sha1(hasher) => hasher.finalize().to_vec(),
+            Self::Sha256(hasher) => hasher.finalize().to_vec(),
+        }
+    }
+}
+
+/// Splits the input of a Merkle–Damgård hash into 64 byte blocks and pads
+/// the last one
+#[derive(Debug, Clone)]
+pub(crate) struct BlockBuffer {
+    block: [u8; 64],
+    len: usize,
+    /// Number of bytes hashed so far
+    total: u64,
+}
+
+impl Default for BlockBuffer {
+    fn default() -> Self {
+        Self { block: [0; 64], len: 0, total: 0 }
+    }
+}
+
+impl BlockBuffer {
+    pub(crate) fn update(&mut self, mut data: &[u8],
+            mut compress: impl FnMut(&[u8; 64])) {
+        self.total = self.total.wrapping_add(data.len() as u64);
+        while !data.is_empty() {
+            let count = (64 - self.len).min(data.len());
+            self.block[self.len..self.len + count]
+                .copy_from_slice(&data[..count]);
+            self.len += count;
+            data = &data[count..];
+
+            if self.len == 64 {
+                compress(&self.block);
+                self.len = 0;
+            }
+        }
+    }
+
+    /// Appends the 0x80 marker, the zero padding and the message length in
+    /// bits, in the byte order the algorithm wants
+    pub(crate) fn finalize(&mut self, big_endian: bool,
+            mut compress: impl FnMut(&[u8; 64])) {
+        let bits = self.total.wrapping_mul(8);
+
+        self.block[self.len] = 0x80;
+        self.block[self.len + 1..].fill(0);
+        if self.len >= 56 {
+            compress(&self.block);
+            self.block.fill(0);
+        }
+        let length = match big_endian {
+            true => bits.to_be_bytes(),
+            false => bits.to_le_bytes(),
+        };
+        self.block[56..].copy_from_slice(&length);
+        compress(&self.block);
+    }
+}
@@ -0,0 +1,60 @@
+use crate::crypto::{bignum::BigUint, DigestAlgorithm};
+
+/// Moduli larger than this are rejected, to bound the work done on
+/// untrusted input
+const MAX_MODULUS_BITS: usize = 8192;
+/// Exponents longer than this, leading zeroes aside, are rejected for the
+/// same reason: the work grows with their length. Public exponents are
+/// 65537, or at least small, in practice.
+const MAX_EXPONENT_LEN: usize = 4;
+
+/// An RSA public key
+#[derive(Debug, Clone, PartialEq)]
+pub struct RsaPublicKey {
+    /// The modulus, big endian, without leading zeroes
+    pub modulus: Vec<u8>,
+    /// The public exponent, big endian
+    pub exponent: Vec<u8>,
+}
+
+impl RsaPublicKey {
+    /// Checks an RSASSA-PKCS1-v1_5 `signature` over a message whose digest
+    /// is `digest`
+    pub fn verify_pkcs1v15(&self, algorithm: DigestAlgorithm, digest: &[u8],
+            signature: &[u8]) -> bool {
+        let len = self.modulus.len();
+        let exponent = match self.exponent.iter().position(|byte| *byte != 0) {
+            Some(start) => &self.exponent[start..],
+            None => return false,
+        };
+        if len == 0 || len * 8 > MAX_MODULUS_BITS || signature.len() != len
+                || exponent.len() > MAX_EXPONENT_LEN
+                || digest.len() != algorithm.digest_len() {
+            return false;
+        }
+        let modulus = BigUint::from_be_bytes(&self.modulus);
+        let signature = BigUint::from_be_bytes(signature);
+        if modulus.is_zero() || signature >= modulus {
+            return false;
+        }
+
+        let Some(encoded) = signature.mod_pow(exponent, &modulus)
+                .to_be_bytes(len) else {
+            return false
+        };
+
+        // 00 01 FF .. FF 00 DigestInfo
+        let prefix = algorithm.digest_info_prefix();
+        let info_len = prefix.len() + digest.len();
+        if len < info_len + 11 {
+            return false;
+        }
+        let padding_len = len - info_len - 3;
+        encoded[0] == 0
+            && encoded[1] == 1
+            && encoded[2..2 + padding_len].iter().all(|byte| *byte == 0xff)
+            && encoded[2 + padding_len] == 0
+            && encoded[3 + padding_len..len - digest.len()] == *prefix
+            && encoded[len - digest.len()..] == *digest
+    }
+}
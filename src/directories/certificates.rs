@@ -0,0 +1,140 @@
+use crate::{
+    error::{Result, PeError},
+    parsing::*,
+    PE,
+};
+
+/// Upper bound on the number of entries read from the certificate table.
+const MAX_CERTIFICATES: usize = 0x100;
+
+/// The revision of the `WIN_CERTIFICATE` structure
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CertificateRevision {
+    /// `WIN_CERT_REVISION_1_0`, legacy version.
+    Revision1,
+    /// `WIN_CERT_REVISION_2_0`, the current version.
+    Revision2,
+    Other(u16),
+}
+
+impl From<u16> for CertificateRevision {
+    fn from(value: u16) -> CertificateRevision {
+        match value {
+            0x0100 => CertificateRevision::Revision1,
+            0x0200 => CertificateRevision::Revision2,
+            value => CertificateRevision::Other(value),
+        }
+    }
+}
+
+/// The kind of content held by a `WIN_CERTIFICATE`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CertificateType {
+    /// `WIN_CERT_TYPE_X509`, an X.509 certificate. Not supported.
+    X509,
+    /// `WIN_CERT_TYPE_PKCS_SIGNED_DATA`, a PKCS#7 `SignedData` structure.
+    PkcsSignedData,
+    /// `WIN_CERT_TYPE_RESERVED_1`, reserved.
+    Reserved1,
+    /// `WIN_CERT_TYPE_TS_STACK_SIGNED`, terminal server protocol stack
+    /// certificate signing. Not supported.
+    TsStackSigned,
+    Other(u16),
+}
+
+impl From<u16> for CertificateType {
+    fn from(value: u16) -> CertificateType {
+        match value {
+            0x0001 => CertificateType::X509,
+            0x0002 => CertificateType::PkcsSignedData,
+            0x0003 => CertificateType::Reserved1,
+            0x0004 => CertificateType::TsStackSigned,
+            value => CertificateType::Other(value),
+        }
+    }
+}
+
+/// An entry of the attribute certificate table, the `WIN_CERTIFICATE`
+/// structure
+#[derive(Debug, Clone, PartialEq)]
+pub struct Certificate<'pe> {
+    /// File offset of the entry.
+    pub offset: u32,
+    /// Specifies the length of the entry, header included, without the
+    /// padding to 8 bytes.
+    pub length: u32,
+    /// Contains the certificate version number, see `CertificateRevision`.
+    pub revision: u16,
+    /// Specifies the type of content in `certificate`, see
+    /// `CertificateType`.
+    pub certificate_type: u16,
+    /// Contains a certificate, such as an Authenticode signature.
+    pub certificate: &'pe [u8],
+}
+
+impl<'pe> Certificate<'pe> {
+    pub fn from_bytes(bytes: &'pe [u8], offset: u32)
+            -> Result<(Self, &'pe [u8])> {
+        let (length, bytes) = take_u32(bytes)?;
+        let (revision, bytes) = take_u16(bytes)?;
+        let (certificate_type, bytes) = take_u16(bytes)?;
+        let content_len = (length as usize).checked_sub(Self::len())
+            .ok_or(PeError::InvalidCertificate(offset))?;
+        let (certificate, bytes) = take_bytes(bytes, content_len)
+            .map_err(|_| PeError::InvalidCertificate(offset))?;
+
+        Ok((Self {
+            offset, length, revision, certificate_type, certificate
+        }, bytes))
+    }
+
+    /// Size of the header preceding the content
+    pub fn len() -> usize {
+        8usize
+    }
+
+    pub fn revision(&self) -> CertificateRevision {
+        self.revision.into()
+    }
+
+    pub fn kind(&self) -> CertificateType {
+        self.certificate_type.into()
+    }
+
+    /// Returns the DER encoded PKCS#7 `SignedData`, if this is an
+    /// Authenticode signature
+    pub fn signed_data(&self) -> Option<&'pe [u8]> {
+        (self.kind() == CertificateType::PkcsSignedData)
+            .then_some(self.certificate)
+    }
+}
+
+/// Parses the attribute certificate table. Unlike every other directory,
+/// its address is a file offset: the table is not mapped in memory. Each
+/// entry starts on an 8 byte boundary.
+pub fn parse_certificates<'pe>(pe: &PE<'pe>) -> Result<Vec<Certificate<'pe>>> {
+    let directory = match pe.opt_header.data_directories.security() {
+        Some(directory) => *directory,
+        None => return Ok(Vec::new()),
+    };
+
+    let start = directory.virtual_address as usize;
+    let end = start.checked_add(directory.size as usize)
+        .filter(|end| *end <= pe.data().len())
+        .ok_or(PeError::InvalidCertificate(directory.virtual_address))?;
+    let table = &pe.data()[start..end];
+
+    let mut certificates = Vec::new();
+    let mut offset = 0;
+    // A trailing header-less remnant is padding, not an entry
+    while offset + Certificate::len() <= table.len() {
+        if certificates.len() >= MAX_CERTIFICATES {
+            return Err(PeError::InvalidCertificate((start + offset) as u32));
+        }
+        let (certificate, _) = Certificate::from_bytes(&table[offset..],
+            (start + offset) as u32)?;
+        offset += (certificate.length as usize).next_multiple_of(8);
+        certificates.push(certificate);
+    }
+    Ok(certificates)
+}
@@ -0,0 +1,416 @@
+use std::fmt;
+
+use crate::{
+    error::{PeError, Result},
+    parsing::*,
+    PE,
+};
+
+/// Upper bound on the number of debug directory entries.
+const MAX_ENTRIES: usize = 0x1000;
+
+/// The kind of debug information an entry points to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DebugType {
+    /// An unknown value that is ignored by all tools.
+    Unknown,
+    /// The COFF debug information (line numbers, symbol table, and string
+    /// table).
+    Coff,
+    /// The Visual C++ debug information, i.e. the location of the PDB.
+    CodeView,
+    /// The frame pointer omission (FPO) information.
+    Fpo,
+    /// The location of a DBG file.
+    Misc,
+    /// A copy of the .pdata section.
+    Exception,
+    /// Reserved.
+    Fixup,
+    /// The mapping from an RVA in the image to an RVA in the source image.
+    OmapToSrc,
+    /// The mapping from an RVA in the source image to an RVA in the image.
+    OmapFromSrc,
+    /// Reserved for Borland.
+    Borland,
+    /// Reserved.
+    Reserved10,
+    /// Reserved.
+    Clsid,
+    /// Counters of the Visual C++ security features used when compiling.
+    VcFeature,
+    /// Profile guided optimization information.
+    Pogo,
+    /// Incremental link time code generation information.
+    Iltcg,
+    /// Uses Intel MPX.
+    Mpx,
+    /// PE determinism or reproducibility.
+    Repro,
+    /// An embedded portable PDB.
+    EmbeddedPortablePdb,
+    /// A checksum of the PDB file.
+    PdbChecksum,
+    /// Extended DLL characteristics bits.
+    ExDllCharacteristics,
+    /// A value that is not documented
+    Other(u32),
+}
+
+impl From<u32> for DebugType {
+    fn from(value: u32) -> DebugType {
+        match value {
+            0 => DebugType::Unknown,
+            1 => DebugType::Coff,
+            2 => DebugType::CodeView,
+            3 => DebugType::Fpo,
+            4 => DebugType::Misc,
+            5 => DebugType::Exception,
+            6 => DebugType::Fixup,
+            7 => DebugType::OmapToSrc,
+            8 => DebugType::OmapFromSrc,
+            9 => DebugType::Borland,
+            10 => DebugType::Reserved10,
+            11 => DebugType::Clsid,
+            12 => DebugType::VcFeature,
+            13 => DebugType::Pogo,
+            14 => DebugType::Iltcg,
+            15 => DebugType::Mpx,
+            16 => DebugType::Repro,
+            17 => DebugType::EmbeddedPortablePdb,
+            19 => DebugType::PdbChecksum,
+            20 => DebugType::ExDllCharacteristics,
+            value => DebugType::Other(value),
+        }
+    }
+}
+
+/// An `IMAGE_DEBUG_DIRECTORY` entry
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DebugDirectory {
+    /// Reserved, must be zero.
+    pub characteristics: u32,
+    /// The time and date that the debug data was created.
+    pub time_date_stamp: u32,
+    /// The major version number of the debug data format.
+    pub major_version: u16,
+    /// The minor version number of the debug data format.
+    pub minor_version: u16,
+    /// The format of debugging information.
+    pub debug_type: DebugType,
+    /// The size of the debug data (not including the debug directory
+    /// itself).
+    pub size_of_data: u32,
+    /// The address of the debug data when loaded, relative to the image
+    /// base.
+    pub address_of_raw_data: u32,
+    /// The file pointer to the debug data.
+    pub pointer_to_raw_data: u32,
+}
+
+impl DebugDirectory {
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8])> {
+        let (characteristics, bytes) = take_u32(bytes)?;
+        let (time_date_stamp, bytes) = take_u32(bytes)?;
+        let (major_version, bytes) = take_u16(bytes)?;
+        let (minor_version, bytes) = take_u16(bytes)?;
+        let (debug_type, bytes) = take_u32(bytes)?;
+        let (size_of_data, bytes) = take_u32(bytes)?;
+        let (address_of_raw_data, bytes) = take_u32(bytes)?;
+        let (pointer_to_raw_data, bytes) = take_u32(bytes)?;
+
+        Ok((Self {
+            characteristics, time_date_stamp, major_version, minor_version,
+            debug_type: debug_type.into(), size_of_data, address_of_raw_data,
+            pointer_to_raw_data
+        }, bytes))
+    }
+
+    pub fn len() -> usize {
+        28usize
+    }
+
+    /// Returns the debug data the entry points to. The file pointer is
+    /// preferred since debug data is not always mapped in memory. Entries
+    /// with neither a file pointer nor an address have no data, which is
+    /// only valid if their size is zero.
+    pub fn data(&self, pe: &PE) -> Result<Vec<u8>> {
+        let size = self.size_of_data as usize;
+        let start = self.pointer_to_raw_data as usize;
+        if self.pointer_to_raw_data != 0 {
+            let end = start.saturating_add(size);
+            if let Some(data) = pe.data().get(start..end) {
+                return Ok(data.to_vec());
+            }
+        }
+
+        // RVA 0 is the start of the headers, not debug data
+        match (self.address_of_raw_data, size) {
+            (0, 0) => Ok(Vec::new()),
+            (0, _) => Err(PeError::UnmappedRva(0)),
+            (rva, _) => Ok(pe.read_at_rva(rva, size)?.into_owned()),
+        }
+    }
+}
+
+/// A globally unique identifier, stored in its mixed endian form
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Guid {
+    pub data1: u32,
+    pub data2: u16,
+    pub data3: u16,
+    pub data4: [u8; 8],
+}
+
+impl Guid {
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8])> {
+        let (data1, bytes) = take_u32(bytes)?;
+        let (data2, bytes) = take_u16(bytes)?;
+        let (data3, bytes) = take_u16(bytes)?;
+        let (data4, bytes) = take_bytes(bytes, 8)?;
+
+        Ok((Self { data1, data2, data3, data4: data4.try_into()? }, bytes))
+    }
+
+    /// Returns the GUID as 32 uppercase hex digits without separators, the
+    /// form used by symbol servers
+    pub fn to_simple_string(&self) -> String {
+        let mut string = format!("{:08X}{:04X}{:04X}",
+            self.data1, self.data2, self.data3);
+        for byte in self.data4 {
+            string.push_str(&format!("{:02X}", byte));
+        }
+        string
+    }
+}
+
+impl fmt::Display for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:08X}-{:04X}-{:04X}-{:02X}{:02X}-", self.data1,
+            self.data2, self.data3, self.data4[0], self.data4[1])?;
+        for byte in &self.data4[2..] {
+            write!(f, "{:02X}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// The location of the PDB, as stored in a CodeView entry
+#[derive(Debug, Clone, PartialEq)]
+pub enum CodeView {
+    /// The PDB 7.0 format
+    Rsds {
+        /// Unique identifier of the PDB
+        guid: Guid,
+        /// Incremented each time the PDB is written
+        age: u32,
+        /// Path of the PDB at link time
+        path: String,
+    },
+    /// The PDB 2.0 format
+    Nb10 {
+        /// Always zero, the CodeView data lives in a separate file
+        offset: u32,
+        /// The time the PDB was created, in seconds since the Epoch
+        signature: u32,
+        /// Incremented each time the PDB is written
+        age: u32,
+        /// Path of the PDB at link time
+        path: String,
+    },
+}
+
+impl CodeView {
+    /// Parses CodeView debug data. Returns `None` for signatures other than
+    /// `RSDS` and `NB10`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Option<Self>> {
+        let (signature, bytes) = take_bytes(bytes, 4)?;
+        match signature {
+            b"RSDS" => {
+                let (guid, bytes) = Guid::from_bytes(bytes)?;
+                let (age, bytes) = take_u32(bytes)?;
+                Ok(Some(Self::Rsds { guid, age, path: cstr(bytes) }))
+            },
+            b"NB10" => {
+                let (offset, bytes) = take_u32(bytes)?;
+                let (signature, bytes) = take_u32(bytes)?;
+                let (age, bytes) = take_u32(bytes)?;
+                let path = cstr(bytes);
+                Ok(Some(Self::Nb10 { offset, signature, age, path }))
+            },
+            _ => Ok(None),
+        }
+    }
+}
+
+/// A profile guided optimization entry, which records the RVA and size of
+/// each contribution to the image
+#[derive(Debug, Clone, PartialEq)]
+pub struct PogoEntry {
+    pub rva: u32,
+    pub size: u32,
+    pub name: String,
+}
+
+/// The POGO debug data
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pogo {
+    /// Identifies the producer, e.g. `LTCG`, `PGU\0` or `GCTL`
+    pub signature: u32,
+    pub entries: Vec<PogoEntry>,
+}
+
+impl Pogo {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let (signature, mut bytes) = take_u32(bytes)?;
+
+        let mut entries = Vec::new();
+        while bytes.len() >= 8 {
+            let (rva, rest) = take_u32(bytes)?;
+            let (size, rest) = take_u32(rest)?;
+            let name_len = rest.iter().position(|&b| b == 0)
+                .unwrap_or(rest.len());
+            let name = String::from_utf8_lossy(&rest[..name_len]).into_owned();
+            entries.push(PogoEntry { rva, size, name });
+
+            // Names are null terminated and padded to a 4 byte boundary
+            let padded = (name_len + 4) & !3;
+            bytes = rest.get(padded..).unwrap_or_default();
+        }
+
+        Ok(Self { signature, entries })
+    }
+}
+
+/// Counters of the security features used by the compiler
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VcFeature {
+    /// Objects compiled by compilers before Visual C++ 11.00
+    pub pre_vc11: u32,
+    /// Objects compiled as C or C++
+    pub c_cpp: u32,
+    /// Objects compiled with `/GS`
+    pub gs: u32,
+    /// Objects compiled with `/sdl`
+    pub sdl: u32,
+    /// Objects compiled with `/guard:N`
+    pub guard_n: u32,
+}
+
+impl VcFeature {
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8])> {
+        let (pre_vc11, bytes) = take_u32(bytes)?;
+        let (c_cpp, bytes) = take_u32(bytes)?;
+        let (gs, bytes) = take_u32(bytes)?;
+        let (sdl, bytes) = take_u32(bytes)?;
+        let (guard_n, bytes) = take_u32(bytes)?;
+
+        Ok((Self { pre_vc11, c_cpp, gs, sdl, guard_n }, bytes))
+    }
+}
+
+/// Decoded debug data
+#[derive(Debug, Clone, PartialEq)]
+pub enum DebugPayload {
+    CodeView(CodeView),
+    Pogo(Pogo),
+    VcFeature(VcFeature),
+    /// The hash that replaces the timestamps of a reproducible build. Empty
+    /// when the linker only used the timestamp fields.
+    Repro(Vec<u8>),
+    /// The extended DLL characteristics flags
+    ExDllCharacteristics(u32),
+    /// The data of a decoded type could not be read, or is truncated or
+    /// malformed
+    Malformed,
+}
+
+impl DebugPayload {
+    /// Returns whether the payloads of `debug_type` are decoded
+    pub fn is_decoded(debug_type: DebugType) -> bool {
+        matches!(debug_type, DebugType::CodeView | DebugType::Pogo
+            | DebugType::VcFeature | DebugType::Repro
+            | DebugType::ExDllCharacteristics)
+    }
+
+    /// Decodes `data` according to `debug_type`. Returns `None` for types
+    /// that are not decoded.
+    pub fn from_bytes(debug_type: DebugType, data: &[u8])
+            -> Result<Option<Self>> {
+        let payload = match debug_type {
+            DebugType::CodeView => CodeView::from_bytes(data)?
+                .map(Self::CodeView),
+            DebugType::Pogo => Some(Self::Pogo(Pogo::from_bytes(data)?)),
+            DebugType::VcFeature => {
+                Some(Self::VcFeature(VcFeature::from_bytes(data)?.0))
+            },
+            DebugType::Repro if data.is_empty() => {
+                Some(Self::Repro(Vec::new()))
+            },
+            DebugType::Repro => {
+                let (len, bytes) = take_u32(data)?;
+                let (hash, _) = take_bytes(bytes, len as usize)?;
+                Some(Self::Repro(hash.to_vec()))
+            },
+            DebugType::ExDllCharacteristics => {
+                Some(Self::ExDllCharacteristics(take_u32(data)?.0))
+            },
+            _ => None,
+        };
+
+        Ok(payload)
+    }
+}
+
+/// A debug directory entry with its decoded payload
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugEntry {
+    pub directory: DebugDirectory,
+    /// `None` for types that are not decoded
+    pub payload: Option<DebugPayload>,
+}
+
+/// The identity of the PDB matching an image
+#[derive(Debug, Clone, PartialEq)]
+pub struct PdbInfo {
+    pub guid: Guid,
+    pub age: u32,
+    pub path: String,
+}
+
+/// Walks the debug directory of `pe` and decodes the known payloads. Each
+/// payload is decoded on its own, one that cannot be is `Malformed`.
+pub fn parse_debug_entries(pe: &PE) -> Result<Vec<DebugEntry>> {
+    let range = match pe.opt_header.data_directories.debug() {
+        Some(directory) => *directory,
+        None => return Ok(Vec::new()),
+    };
+
+    let count = (range.size as usize / DebugDirectory::len())
+        .min(MAX_ENTRIES);
+    let data = pe.read_at_rva(range.virtual_address,
+        count * DebugDirectory::len())?;
+
+    let mut entries = Vec::with_capacity(count);
+    let mut bytes = &data[..];
+    for _ in 0..count {
+        let (directory, rest) = DebugDirectory::from_bytes(bytes)?;
+        let debug_type = directory.debug_type;
+        let payload = match DebugPayload::is_decoded(debug_type) {
+            true => directory.data(pe)
+                .and_then(|data| DebugPayload::from_bytes(debug_type, &data))
+                .unwrap_or(Some(DebugPayload::Malformed)),
+            false => None,
+        };
+        entries.push(DebugEntry { directory, payload });
+        bytes = rest;
+    }
+
+    Ok(entries)
+}
+
+/// Returns the null terminated UTF-8 string at the start of `bytes`
+fn cstr(bytes: &[u8]) -> String {
+    let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..len]).into_owned()
+}
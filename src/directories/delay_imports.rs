@@ -0,0 +1,148 @@
+use crate::{
+    directories::imports::{parse_thunks, thunk_size, ImportedFunction},
+    error::Result,
+    parsing::*,
+    PE,
+};
+
+/// Upper bound on the number of delay-load descriptors read from the
+/// directory.
+const MAX_DESCRIPTORS: usize = 0x4000;
+
+/// Set in `attributes` when the descriptor holds RVAs. Older linkers emitted
+/// virtual addresses instead and leave this bit clear.
+const DLATTR_RVA: u32 = 0x1;
+
+/// An `ImgDelayDescr`, as stored in the delay import directory. The address
+/// fields are RVAs when bit 0 of `attributes` is set and virtual addresses
+/// otherwise.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DelayImportDescriptor {
+    /// Attributes of the descriptor
+    pub attributes: u32,
+    /// The address of the name of the DLL to be loaded
+    pub dll_name: u32,
+    /// The address of the module handle of the DLL, filled by the helper
+    pub module_handle: u32,
+    /// The address of the delay-load import address table
+    pub iat: u32,
+    /// The address of the delay-load import name table
+    pub int: u32,
+    /// The address of the optional bound IAT
+    pub bound_iat: u32,
+    /// The address of the optional copy of the original IAT, used to unload
+    /// the DLL
+    pub unload_iat: u32,
+    /// The timestamp of the DLL the image was bound against, zero if not bound
+    pub time_date_stamp: u32,
+}
+
+impl DelayImportDescriptor {
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8])> {
+        let (attributes, bytes) = take_u32(bytes)?;
+        let (dll_name, bytes) = take_u32(bytes)?;
+        let (module_handle, bytes) = take_u32(bytes)?;
+        let (iat, bytes) = take_u32(bytes)?;
+        let (int, bytes) = take_u32(bytes)?;
+        let (bound_iat, bytes) = take_u32(bytes)?;
+        let (unload_iat, bytes) = take_u32(bytes)?;
+        let (time_date_stamp, bytes) = take_u32(bytes)?;
+
+        Ok((Self {
+            attributes, dll_name, module_handle, iat, int, bound_iat,
+            unload_iat, time_date_stamp
+        }, bytes))
+    }
+
+    pub fn len() -> usize {
+        32usize
+    }
+
+    /// Returns true if the address fields are RVAs rather than VAs
+    pub fn is_rva_based(&self) -> bool {
+        self.attributes & DLATTR_RVA != 0
+    }
+
+    fn is_null(&self) -> bool {
+        self.dll_name == 0 && self.iat == 0 && self.int == 0
+    }
+}
+
+/// A DLL loaded on first use, with every address converted to an RVA
+#[derive(Debug, Clone, PartialEq)]
+pub struct DelayImportedModule {
+    /// The name of the DLL
+    pub name: String,
+    /// The raw descriptor for the DLL
+    pub descriptor: DelayImportDescriptor,
+    /// The RVA of the module handle of the DLL
+    pub module_handle_rva: u32,
+    /// The RVA of the delay-load import address table
+    pub iat_rva: u32,
+    /// The RVA of the delay-load import name table
+    pub int_rva: u32,
+    /// The RVA of the bound import address table, if present
+    pub bound_iat_rva: Option<u32>,
+    /// The RVA of the unload import address table, if present
+    pub unload_iat_rva: Option<u32>,
+    /// The functions imported from the DLL
+    pub functions: Vec<ImportedFunction>,
+}
+
+/// Walks the delay import directory of `pe`
+pub fn parse_delay_imports(pe: &PE) -> Result<Vec<DelayImportedModule>> {
+    let directory = match pe.opt_header.data_directories.delay_import() {
+        Some(directory) => *directory,
+        None => return Ok(Vec::new()),
+    };
+
+    let thunk_size = thunk_size(pe);
+    let mut modules = Vec::new();
+    let mut rva = directory.virtual_address;
+    while modules.len() < MAX_DESCRIPTORS {
+        let data = pe.read_at_rva(rva, DelayImportDescriptor::len())?;
+        let (descriptor, _) = DelayImportDescriptor::from_bytes(&data)?;
+        if descriptor.is_null() {
+            break;
+        }
+
+        let va_based = !descriptor.is_rva_based();
+        let to_rva = |address: u32| -> Result<u32> {
+            if va_based {
+                pe.va_to_rva(address as u64)
+            } else {
+                Ok(address)
+            }
+        };
+        let optional_rva = |address: u32| -> Result<Option<u32>> {
+            match address {
+                0 => Ok(None),
+                address => to_rva(address).map(Some),
+            }
+        };
+
+        let name = pe.read_cstr_at_rva(to_rva(descriptor.dll_name)?)?;
+        let module_handle_rva = to_rva(descriptor.module_handle)?;
+        let iat_rva = to_rva(descriptor.iat)?;
+        let int_rva = to_rva(descriptor.int)?;
+        let bound_iat_rva = optional_rva(descriptor.bound_iat)?;
+        let unload_iat_rva = optional_rva(descriptor.unload_iat)?;
+
+        let functions = parse_thunks(pe, int_rva, va_based)?
+            .into_iter()
+            .enumerate()
+            .map(|(index, name)| ImportedFunction {
+                name,
+                iat_rva: iat_rva.wrapping_add(index as u32 * thunk_size),
+            })
+            .collect();
+
+        modules.push(DelayImportedModule {
+            name, descriptor, module_handle_rva, iat_rva, int_rva,
+            bound_iat_rva, unload_iat_rva, functions
+        });
+        rva = rva.wrapping_add(DelayImportDescriptor::len() as u32);
+    }
+
+    Ok(modules)
+}
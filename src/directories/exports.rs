@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+
+use crate::{
+    error::{Result, PeError},
+    parsing::*,
+    PE,
+};
+
+/// The `IMAGE_EXPORT_DIRECTORY` table, which describes the rest of the export
+/// symbol information.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExportDirectory {
+    /// Reserved, must be 0.
+    pub characteristics: u32,
+    /// The time and date that the export data was created.
+    pub time_date_stamp: u32,
+    /// The major version number.
+    pub major_version: u16,
+    /// The minor version number.
+    pub minor_version: u16,
+    /// The RVA of the ASCII string that contains the name of the DLL.
+    pub name: u32,
+    /// The starting ordinal number for exports in this image. Usually 1.
+    pub ordinal_base: u32,
+    /// The number of entries in the export address table.
+    pub number_of_functions: u32,
+    /// The number of entries in the name pointer table. This is also the
+    /// number of entries in the ordinal table.
+    pub number_of_names: u32,
+    /// The RVA of the export address table.
+    pub address_of_functions: u32,
+    /// The RVA of the export name pointer table.
+    pub address_of_names: u32,
+    /// The RVA of the ordinal table.
+    pub address_of_name_ordinals: u32,
+}
+
+impl ExportDirectory {
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8])> {
+        let (characteristics, bytes) = take_u32(bytes)?;
+        let (time_date_stamp, bytes) = take_u32(bytes)?;
+        let (major_version, bytes) = take_u16(bytes)?;
+        let (minor_version, bytes) = take_u16(bytes)?;
+        let (name, bytes) = take_u32(bytes)?;
+        let (ordinal_base, bytes) = take_u32(bytes)?;
+        let (number_of_functions, bytes) = take_u32(bytes)?;
+        let (number_of_names, bytes) = take_u32(bytes)?;
+        let (address_of_functions, bytes) = take_u32(bytes)?;
+        let (address_of_names, bytes) = take_u32(bytes)?;
+        let (address_of_name_ordinals, bytes) = take_u32(bytes)?;
+
+        Ok((Self {
+            characteristics, time_date_stamp, major_version, minor_version,
+            name, ordinal_base, number_of_functions, number_of_names,
+            address_of_functions, address_of_names, address_of_name_ordinals
+        }, bytes))
+    }
+
+    pub fn len() -> usize {
+        40usize
+    }
+}
+
+/// A single exported symbol
+#[derive(Debug, Clone, PartialEq)]
+pub struct Export {
+    /// The public name of the symbol, if it is exported by name
+    pub name: Option<String>,
+    /// The biased ordinal of the symbol, i.e. its index in the export address
+    /// table plus the ordinal base
+    pub ordinal: u32,
+    /// The RVA of the symbol. For forwarders this points to the forwarder
+    /// string inside the export directory.
+    pub rva: u32,
+    /// The `DLL.Symbol` or `DLL.#Ordinal` string this export forwards to
+    pub forwarder: Option<String>,
+}
+
+/// The exports of an image, sorted by ordinal
+#[derive(Debug, Clone, Default)]
+pub struct ExportTable {
+    /// The raw export directory
+    pub directory: Option<ExportDirectory>,
+    /// The name of the DLL as recorded by the linker
+    pub module_name: String,
+    /// Every exported symbol
+    pub exports: Vec<Export>,
+    /// Maps each name to its index in `exports`
+    names: HashMap<String, usize>,
+}
+
+impl ExportTable {
+    /// Parses the export directory of `pe`. An image without exports gives an
+    /// empty table.
+    pub fn from_pe(pe: &PE) -> Result<Self> {
+        let range = match pe.opt_header.data_directories.export() {
+            Some(directory) => *directory,
+            None => return Ok(Self::default()),
+        };
+        let data = pe.read_at_rva(range.virtual_address,
+            ExportDirectory::len())?;
+        let (directory, _) = ExportDirectory::from_bytes(&data)?;
+        let module_name = pe.read_cstr_at_rva(directory.name)?;
+
+        let functions = read_table(pe, directory.address_of_functions,
+            directory.number_of_functions as usize, 4)?;
+        let name_pointers = read_table(pe, directory.address_of_names,
+            directory.number_of_names as usize, 4)?;
+        let name_ordinals = read_table(pe,
+            directory.address_of_name_ordinals,
+            directory.number_of_names as usize, 2)?;
+
+        // Map each address table index to the names pointing at it
+        let mut names_by_index: HashMap<usize, Vec<String>> = HashMap::new();
+        for (name_rva, index) in name_pointers.iter().zip(name_ordinals) {
+            let name = pe.read_cstr_at_rva(*name_rva as u32)?;
+            names_by_index.entry(index as usize).or_default().push(name);
+        }
+
+        let forwarder_range = range.virtual_address as u64
+            ..range.virtual_address as u64 + range.size as u64;
+        let mut exports = Vec::with_capacity(functions.len());
+        for (index, rva) in functions.into_iter().enumerate() {
+            let rva = rva as u32;
+            let names = names_by_index.remove(&index).unwrap_or_default();
+            // Zero entries are unused slots in the address table
+            if rva == 0 && names.is_empty() {
+                continue;
+            }
+
+            let forwarder = if forwarder_range.contains(&(rva as u64)) {
+                Some(pe.read_cstr_at_rva(rva)?)
+            } else {
+                None
+            };
+            let ordinal = directory.ordinal_base.wrapping_add(index as u32);
+
+            if names.is_empty() {
+                exports.push(Export { name: None, ordinal, rva, forwarder });
+                continue;
+            }
+            for name in names {
+                exports.push(Export {
+                    name: Some(name),
+                    ordinal,
+                    rva,
+                    forwarder: forwarder.clone(),
+                });
+            }
+        }
+
+        let names = exports.iter()
+            .enumerate()
+            .filter_map(|(index, export)| {
+                export.name.clone().map(|name| (name, index))
+            })
+            .collect();
+
+        Ok(Self { directory: Some(directory), module_name, exports, names })
+    }
+
+    /// Returns the export with the given `name`
+    pub fn by_name(&self, name: &str) -> Option<&Export> {
+        self.names.get(name).map(|&index| &self.exports[index])
+    }
+
+    /// Returns the first export with the given biased `ordinal`
+    pub fn by_ordinal(&self, ordinal: u32) -> Option<&Export> {
+        let index = self.exports
+            .partition_point(|export| export.ordinal < ordinal);
+        self.exports.get(index).filter(|export| export.ordinal == ordinal)
+    }
+}
+
+/// Reads `count` little endian integers of `size` bytes starting at `rva`
+fn read_table(pe: &PE, rva: u32, count: usize, size: usize)
+        -> Result<Vec<u64>> {
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+
+    let len = count.checked_mul(size)
+        .ok_or(PeError::UnmappedRva(rva))?;
+    let data = pe.read_at_rva(rva, len)?;
+    let values = data.chunks_exact(size)
+        .map(|chunk| match size {
+            2 => u16::from_le_bytes([chunk[0], chunk[1]]) as u64,
+            _ => u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]])
+                as u64,
+        })
+        .collect();
+
+    Ok(values)
+}
@@ -0,0 +1,225 @@
+use std::borrow::Cow;
+
+use crate::{
+    crypto::md5::Md5,
+    directories::ordinals::ordinal_name,
+    error::Result,
+    parsing::*,
+    PE,
+};
+
+/// Upper bound on the number of thunks read from a single lookup table, so
+/// that a table missing its null terminator cannot keep us busy forever.
+const MAX_THUNKS: usize = 0x10000;
+
+/// Upper bound on the number of import descriptors read from the directory.
+const MAX_DESCRIPTORS: usize = 0x4000;
+
+/// Extensions dropped from DLL names in the imphash
+const IMPHASH_EXTENSIONS: [&str; 3] = ["ocx", "sys", "dll"];
+
+/// An `IMAGE_IMPORT_DESCRIPTOR`. The import directory is an array of these,
+/// one per imported DLL, terminated by an all-zero entry.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImportDescriptor {
+    /// The RVA of the import lookup table, which holds a name or ordinal for
+    /// each import.
+    pub original_first_thunk: u32,
+    /// Zero until the image is bound. After binding, the time/data stamp of
+    /// the DLL the image was bound against.
+    pub time_date_stamp: u32,
+    /// The index of the first forwarder reference.
+    pub forwarder_chain: u32,
+    /// The RVA of an ASCII string that contains the name of the DLL.
+    pub name: u32,
+    /// The RVA of the import address table. Its contents are identical to the
+    /// import lookup table until the image is bound.
+    pub first_thunk: u32,
+}
+
+impl ImportDescriptor {
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8])> {
+        let (original_first_thunk, bytes) = take_u32(bytes)?;
+        let (time_date_stamp, bytes) = take_u32(bytes)?;
+        let (forwarder_chain, bytes) = take_u32(bytes)?;
+        let (name, bytes) = take_u32(bytes)?;
+        let (first_thunk, bytes) = take_u32(bytes)?;
+
+        Ok((Self {
+            original_first_thunk, time_date_stamp, forwarder_chain, name,
+            first_thunk
+        }, bytes))
+    }
+
+    pub fn len() -> usize {
+        20usize
+    }
+
+    fn is_null(&self) -> bool {
+        self.original_first_thunk == 0 && self.time_date_stamp == 0
+            && self.forwarder_chain == 0 && self.name == 0
+            && self.first_thunk == 0
+    }
+}
+
+/// How an imported function is identified
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportName {
+    /// Imported by ordinal
+    Ordinal(u16),
+    /// Imported by name. `hint` is an index into the export name pointer
+    /// table of the DLL that is tried first.
+    Name { hint: u16, name: String },
+}
+
+/// A single function imported from a DLL
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedFunction {
+    /// The name or ordinal of the function
+    pub name: ImportName,
+    /// The RVA of the import address table slot that the loader fills with
+    /// the address of the function
+    pub iat_rva: u32,
+}
+
+/// A DLL and the functions imported from it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedModule {
+    /// The name of the DLL
+    pub name: String,
+    /// The raw descriptor for the DLL
+    pub descriptor: ImportDescriptor,
+    /// The functions imported from the DLL
+    pub functions: Vec<ImportedFunction>,
+}
+
+/// Reads a null terminated lookup table of 32-bit or 64-bit thunks starting
+/// at `rva` and returns the imports in table order. Lookup tables have the
+/// same layout in the import and delay-import directories, except for legacy
+/// delay-import tables where `va_based` is set and hint/name entries are
+/// referenced by virtual address.
+pub(crate) fn parse_thunks(pe: &PE, rva: u32, va_based: bool)
+        -> Result<Vec<ImportName>> {
+    let is_pe64 = pe.opt_header.win_fields.is_pe64();
+    let thunk_size = thunk_size(pe);
+    let ordinal_flag = 1u64 << (thunk_size * 8 - 1);
+
+    let mut names = Vec::new();
+    let mut thunk_rva = rva;
+    while names.len() < MAX_THUNKS {
+        let data = pe.read_at_rva(thunk_rva, thunk_size as usize)?;
+        let thunk = if is_pe64 {
+            take_u64(&data)?.0
+        } else {
+            take_u32(&data)?.0 as u64
+        };
+        if thunk == 0 {
+            break;
+        }
+
+        let name = if thunk & ordinal_flag != 0 {
+            ImportName::Ordinal(thunk as u16)
+        } else {
+            // Bits 0-30 hold the RVA of the hint/name table entry
+            let hint_rva = if va_based {
+                pe.va_to_rva(thunk)?
+            } else {
+                (thunk & 0x7fff_ffff) as u32
+            };
+            let (hint, _) = take_u16(&pe.read_at_rva(hint_rva, 2)?)?;
+            let name = pe.read_cstr_at_rva(hint_rva.wrapping_add(2))?;
+            ImportName::Name { hint, name }
+        };
+        names.push(name);
+        thunk_rva = thunk_rva.wrapping_add(thunk_size);
+    }
+
+    Ok(names)
+}
+
+/// Size in bytes of a thunk in the lookup and address tables of `pe`
+pub(crate) fn thunk_size(pe: &PE) -> u32 {
+    if pe.opt_header.win_fields.is_pe64() { 8 } else { 4 }
+}
+
+/// Walks the import directory of `pe`
+pub fn parse_imports(pe: &PE) -> Result<Vec<ImportedModule>> {
+    let directory = match pe.opt_header.data_directories.import() {
+        Some(directory) => *directory,
+        None => return Ok(Vec::new()),
+    };
+
+    let thunk_size = thunk_size(pe);
+    let mut modules = Vec::new();
+    let mut rva = directory.virtual_address;
+    while modules.len() < MAX_DESCRIPTORS {
+        let data = pe.read_at_rva(rva, ImportDescriptor::len())?;
+        let (descriptor, _) = ImportDescriptor::from_bytes(&data)?;
+        if descriptor.is_null() {
+            break;
+        }
+
+        let name = pe.read_cstr_at_rva(descriptor.name)?;
+
+        // Fall back on the IAT when the lookup table was stripped, as old
+        // Borland linkers do
+        let lookup_rva = match descriptor.original_first_thunk {
+            0 => descriptor.first_thunk,
+            lookup_rva => lookup_rva,
+        };
+        let functions = parse_thunks(pe, lookup_rva, false)?
+            .into_iter()
+            .enumerate()
+            .map(|(index, name)| ImportedFunction {
+                name,
+                iat_rva: descriptor.first_thunk
+                    .wrapping_add(index as u32 * thunk_size),
+            })
+            .collect();
+
+        modules.push(ImportedModule { name, descriptor, functions });
+        rva = rva.wrapping_add(ImportDescriptor::len() as u32);
+    }
+
+    Ok(modules)
+}
+
+/// Computes the imphash of `pe` the way pefile does: every import is
+/// written as `dll.function` in lowercase, the DLL name without its
+/// extension, and the MD5 of the comma separated list is returned in hex.
+/// Ordinals are resolved to names for the DLLs pefile knows and written as
+/// `ord<n>` otherwise. Returns `None` when nothing is imported, where pefile
+/// returns an empty string.
+pub fn imphash(pe: &PE) -> Result<Option<String>> {
+    let modules = parse_imports(pe)?;
+    if modules.is_empty() {
+        return Ok(None);
+    }
+
+    let mut imports = Vec::new();
+    for module in &modules {
+        let dll = module.name.to_lowercase();
+        let dll = match dll.rsplit_once('.') {
+            Some((stem, extension))
+                if IMPHASH_EXTENSIONS.contains(&extension) => stem,
+            _ => dll.as_str(),
+        };
+        for function in &module.functions {
+            let name = match &function.name {
+                ImportName::Name { name, .. } => Cow::from(name.as_str()),
+                ImportName::Ordinal(ordinal) => {
+                    match ordinal_name(&module.name, *ordinal) {
+                        Some(name) => Cow::from(name),
+                        None => Cow::from(format!("ord{}", ordinal)),
+                    }
+                },
+            };
+            if !name.is_empty() {
+                imports.push(format!("{}.{}", dll, name.to_lowercase()));
+            }
+        }
+    }
+
+    let digest = Md5::digest(imports.join(",").as_bytes());
+    Ok(Some(digest.iter().map(|b| format!("{:02x}", b)).collect()))
+}
@@ -0,0 +1,338 @@
+use crate::{
+    error::Result,
+    parsing::*,
+    PE,
+};
+
+/// The groups of fields the load configuration directory gained over the
+/// Windows releases, in the order they were appended to the structure. A
+/// directory at a given level holds every field of the previous levels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LoadConfigLevel {
+    /// Everything up to `security_cookie`
+    Base,
+    /// The safe structured exception handler table (`SafeSEH`)
+    SafeSeh,
+    /// Control Flow Guard check and dispatch pointers, function table and
+    /// guard flags
+    ControlFlowGuard,
+    /// The code integrity block
+    CodeIntegrity,
+    /// Address taken IAT entries and long jump targets
+    LongJumpGuard,
+    /// Dynamic value relocations and the CHPE (hybrid ARM64) metadata
+    Chpe,
+    /// Return Flow Guard failure routines and stack pointer verification
+    ReturnFlowGuard,
+    /// The hot patch table
+    HotPatch,
+    /// The enclave configuration
+    Enclave,
+    /// The volatile metadata
+    VolatileMetadata,
+    /// The EH continuation target table
+    EhContinuation,
+    /// eXtended Flow Guard (XFG) check and dispatch pointers
+    Xfg,
+    /// The CastGuard failure mode
+    CastGuard,
+    /// The guarded memcpy function pointer
+    MemcpyGuard,
+}
+
+/// The `IMAGE_LOAD_CONFIG_CODE_INTEGRITY` structure
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CodeIntegrity {
+    /// Flags to indicate if CI information is available
+    pub flags: u16,
+    /// 0xFFFF means not available
+    pub catalog: u16,
+    pub catalog_offset: u32,
+    /// Additional bitmask to be defined later
+    pub reserved: u32,
+}
+
+impl CodeIntegrity {
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8])> {
+        let (flags, bytes) = take_u16(bytes)?;
+        let (catalog, bytes) = take_u16(bytes)?;
+        let (catalog_offset, bytes) = take_u32(bytes)?;
+        let (reserved, bytes) = take_u32(bytes)?;
+
+        Ok((Self { flags, catalog, catalog_offset, reserved }, bytes))
+    }
+
+    pub fn len() -> usize {
+        12usize
+    }
+}
+
+/// The `IMAGE_LOAD_CONFIG_DIRECTORY32` and `IMAGE_LOAD_CONFIG_DIRECTORY64`
+/// structures. Only the fields covered by `size` are present; the others are
+/// `None`. Pointer sized fields are widened to 64 bits for PE32 images.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoadConfig {
+    /// The size of the structure, which decides the fields it holds
+    pub size: u32,
+    pub time_date_stamp: Option<u32>,
+    pub major_version: Option<u16>,
+    pub minor_version: Option<u16>,
+    /// The global loader flags to clear for this process as the loader
+    /// starts the process.
+    pub global_flags_clear: Option<u32>,
+    /// The global loader flags to set for this process as the loader starts
+    /// the process.
+    pub global_flags_set: Option<u32>,
+    /// The default timeout value to use for this process's critical sections
+    /// that are abandoned.
+    pub critical_section_default_timeout: Option<u32>,
+    /// Memory that must be freed before it is returned to the system, in
+    /// bytes.
+    pub decommit_free_block_threshold: Option<u64>,
+    /// Total amount of free memory, in bytes.
+    pub decommit_total_free_threshold: Option<u64>,
+    /// The VA of a list of addresses where the LOCK prefix is used so that
+    /// they can be replaced with NOP on single processor machines. x86 only.
+    pub lock_prefix_table: Option<u64>,
+    /// Maximum allocation size, in bytes.
+    pub maximum_allocation_size: Option<u64>,
+    /// Maximum virtual memory size, in bytes.
+    pub virtual_memory_threshold: Option<u64>,
+    /// Process heap flags that correspond to the first argument of the
+    /// HeapCreate function.
+    pub process_heap_flags: Option<u32>,
+    /// Setting this field to a non-zero value is equivalent to calling
+    /// SetProcessAffinityMask with this value during process startup.
+    pub process_affinity_mask: Option<u64>,
+    /// The service pack version identifier.
+    pub csd_version: Option<u16>,
+    /// The default load flags used when the operating system resolves the
+    /// statically linked imports of a module.
+    pub dependent_load_flags: Option<u16>,
+    /// Reserved for use by the system.
+    pub edit_list: Option<u64>,
+    /// A pointer to a cookie that is used by Visual C++ or GS
+    /// implementation.
+    pub security_cookie: Option<u64>,
+    /// The VA of the sorted table of RVAs of each valid, unique SE handler in
+    /// the image. x86 only.
+    pub se_handler_table: Option<u64>,
+    /// The count of unique handlers in the table. x86 only.
+    pub se_handler_count: Option<u64>,
+    /// The VA where Control Flow Guard check-function pointer is stored.
+    pub guard_cf_check_function_pointer: Option<u64>,
+    /// The VA where Control Flow Guard dispatch-function pointer is stored.
+    pub guard_cf_dispatch_function_pointer: Option<u64>,
+    /// The VA of the sorted table of RVAs of each Control Flow Guard function
+    /// in the image.
+    pub guard_cf_function_table: Option<u64>,
+    /// The count of unique RVAs in the above table.
+    pub guard_cf_function_count: Option<u64>,
+    /// Control Flow Guard related flags.
+    pub guard_flags: Option<u32>,
+    /// Code integrity information.
+    pub code_integrity: Option<CodeIntegrity>,
+    /// The VA where Control Flow Guard address taken IAT table is stored.
+    pub guard_address_taken_iat_entry_table: Option<u64>,
+    /// The count of unique RVAs in the above table.
+    pub guard_address_taken_iat_entry_count: Option<u64>,
+    /// The VA where Control Flow Guard long jump target table is stored.
+    pub guard_long_jump_target_table: Option<u64>,
+    /// The count of unique RVAs in the above table.
+    pub guard_long_jump_target_count: Option<u64>,
+    pub dynamic_value_reloc_table: Option<u64>,
+    /// The VA of the hybrid PE (CHPE) metadata.
+    pub chpe_metadata_pointer: Option<u64>,
+    pub guard_rf_failure_routine: Option<u64>,
+    pub guard_rf_failure_routine_function_pointer: Option<u64>,
+    pub dynamic_value_reloc_table_offset: Option<u32>,
+    pub dynamic_value_reloc_table_section: Option<u16>,
+    pub reserved2: Option<u16>,
+    pub guard_rf_verify_stack_pointer_function_pointer: Option<u64>,
+    pub hot_patch_table_offset: Option<u32>,
+    pub reserved3: Option<u32>,
+    pub enclave_configuration_pointer: Option<u64>,
+    pub volatile_metadata_pointer: Option<u64>,
+    /// The VA of the table of EH continuation targets.
+    pub guard_eh_continuation_table: Option<u64>,
+    /// The count of targets in the above table.
+    pub guard_eh_continuation_count: Option<u64>,
+    pub guard_xfg_check_function_pointer: Option<u64>,
+    pub guard_xfg_dispatch_function_pointer: Option<u64>,
+    pub guard_xfg_table_dispatch_function_pointer: Option<u64>,
+    pub cast_guard_os_determined_failure_mode: Option<u64>,
+    pub guard_memcpy_function_pointer: Option<u64>,
+}
+
+/// Reads the fields of a load config directory one after the other and stops
+/// for good at the first field that does not fit in the declared size
+struct FieldReader<'a> {
+    bytes: &'a [u8],
+    is_pe64: bool,
+    exhausted: bool,
+}
+
+impl<'a> FieldReader<'a> {
+    fn take<T>(&mut self, parse: impl Fn(&'a [u8]) -> Result<(T, &'a [u8])>)
+            -> Option<T> {
+        if self.exhausted {
+            return None;
+        }
+        match parse(self.bytes) {
+            Ok((value, bytes)) => {
+                self.bytes = bytes;
+                Some(value)
+            },
+            Err(_) => {
+                self.exhausted = true;
+                None
+            },
+        }
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.take(take_u16)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take(take_u32)
+    }
+
+    /// Reads a pointer sized field
+    fn ptr(&mut self) -> Option<u64> {
+        if self.is_pe64 {
+            self.take(take_u64)
+        } else {
+            self.u32().map(|value| value as u64)
+        }
+    }
+}
+
+impl LoadConfig {
+    /// Parses a load config directory from `bytes`, using 64-bit pointer
+    /// fields if `is_pe64` is set. Fields past the declared size are left
+    /// empty, even if `bytes` holds more data.
+    pub fn from_bytes(bytes: &[u8], is_pe64: bool) -> Result<Self> {
+        let (size, rest) = take_u32(bytes)?;
+        let declared = (size as usize).saturating_sub(4).min(rest.len());
+        let mut reader = FieldReader {
+            bytes: &rest[..declared],
+            is_pe64,
+            exhausted: false,
+        };
+
+        let mut config = Self {
+            size,
+            time_date_stamp: reader.u32(),
+            major_version: reader.u16(),
+            minor_version: reader.u16(),
+            global_flags_clear: reader.u32(),
+            global_flags_set: reader.u32(),
+            critical_section_default_timeout: reader.u32(),
+            decommit_free_block_threshold: reader.ptr(),
+            decommit_total_free_threshold: reader.ptr(),
+            lock_prefix_table: reader.ptr(),
+            maximum_allocation_size: reader.ptr(),
+            virtual_memory_threshold: reader.ptr(),
+            ..Self::default()
+        };
+        // The heap flags and affinity mask are swapped in the 64-bit layout
+        if is_pe64 {
+            config.process_affinity_mask = reader.ptr();
+            config.process_heap_flags = reader.u32();
+        } else {
+            config.process_heap_flags = reader.u32();
+            config.process_affinity_mask = reader.ptr();
+        }
+        config.csd_version = reader.u16();
+        config.dependent_load_flags = reader.u16();
+        config.edit_list = reader.ptr();
+        config.security_cookie = reader.ptr();
+        config.se_handler_table = reader.ptr();
+        config.se_handler_count = reader.ptr();
+        config.guard_cf_check_function_pointer = reader.ptr();
+        config.guard_cf_dispatch_function_pointer = reader.ptr();
+        config.guard_cf_function_table = reader.ptr();
+        config.guard_cf_function_count = reader.ptr();
+        config.guard_flags = reader.u32();
+        config.code_integrity = reader.take(CodeIntegrity::from_bytes);
+        config.guard_address_taken_iat_entry_table = reader.ptr();
+        config.guard_address_taken_iat_entry_count = reader.ptr();
+        config.guard_long_jump_target_table = reader.ptr();
+        config.guard_long_jump_target_count = reader.ptr();
+        config.dynamic_value_reloc_table = reader.ptr();
+        config.chpe_metadata_pointer = reader.ptr();
+        config.guard_rf_failure_routine = reader.ptr();
+        config.guard_rf_failure_routine_function_pointer = reader.ptr();
+        config.dynamic_value_reloc_table_offset = reader.u32();
+        config.dynamic_value_reloc_table_section = reader.u16();
+        config.reserved2 = reader.u16();
+        config.guard_rf_verify_stack_pointer_function_pointer = reader.ptr();
+        config.hot_patch_table_offset = reader.u32();
+        config.reserved3 = reader.u32();
+        config.enclave_configuration_pointer = reader.ptr();
+        config.volatile_metadata_pointer = reader.ptr();
+        config.guard_eh_continuation_table = reader.ptr();
+        config.guard_eh_continuation_count = reader.ptr();
+        config.guard_xfg_check_function_pointer = reader.ptr();
+        config.guard_xfg_dispatch_function_pointer = reader.ptr();
+        config.guard_xfg_table_dispatch_function_pointer = reader.ptr();
+        config.cast_guard_os_determined_failure_mode = reader.ptr();
+        config.guard_memcpy_function_pointer = reader.ptr();
+
+        Ok(config)
+    }
+
+    /// Parses the load config directory of `pe`. Returns `None` if the image
+    /// has no load config directory.
+    pub fn from_pe(pe: &PE) -> Result<Option<Self>> {
+        let range = match pe.opt_header.data_directories.load_config() {
+            Some(directory) => *directory,
+            None => return Ok(None),
+        };
+        let is_pe64 = pe.opt_header.win_fields.is_pe64();
+
+        // The `size` field of the structure is authoritative, the size in the
+        // data directory is only used by old linkers. A size past the mapped
+        // bytes is clamped, the fields that are missing are left out.
+        let (size, _) = take_u32(&pe.read_at_rva(range.virtual_address, 4)?)?;
+        let mapped = pe.map_rva(range.virtual_address)?.mapped;
+        let data = pe.read_at_rva(range.virtual_address,
+            (size.max(4) as usize).min(mapped))?;
+
+        Self::from_bytes(&data, is_pe64).map(Some)
+    }
+
+    /// Returns the most recent group of fields fully covered by `size`, or
+    /// `None` if not even the base fields are present
+    pub fn level(&self) -> Option<LoadConfigLevel> {
+        let levels = [
+            (self.guard_memcpy_function_pointer.is_some(),
+                LoadConfigLevel::MemcpyGuard),
+            (self.cast_guard_os_determined_failure_mode.is_some(),
+                LoadConfigLevel::CastGuard),
+            (self.guard_xfg_table_dispatch_function_pointer.is_some(),
+                LoadConfigLevel::Xfg),
+            (self.guard_eh_continuation_count.is_some(),
+                LoadConfigLevel::EhContinuation),
+            (self.volatile_metadata_pointer.is_some(),
+                LoadConfigLevel::VolatileMetadata),
+            (self.enclave_configuration_pointer.is_some(),
+                LoadConfigLevel::Enclave),
+            (self.reserved3.is_some(), LoadConfigLevel::HotPatch),
+            (self.guard_rf_verify_stack_pointer_function_pointer.is_some(),
+                LoadConfigLevel::ReturnFlowGuard),
+            (self.chpe_metadata_pointer.is_some(), LoadConfigLevel::Chpe),
+            (self.guard_long_jump_target_count.is_some(),
+                LoadConfigLevel::LongJumpGuard),
+            (self.code_integrity.is_some(), LoadConfigLevel::CodeIntegrity),
+            (self.guard_flags.is_some(), LoadConfigLevel::ControlFlowGuard),
+            (self.se_handler_count.is_some(), LoadConfigLevel::SafeSeh),
+            (self.security_cookie.is_some(), LoadConfigLevel::Base),
+        ];
+
+        levels.into_iter()
+            .find(|(present, _)| *present)
+            .map(|(_, level)| level)
+    }
+}
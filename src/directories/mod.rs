@@ -0,0 +1,9 @@
+pub mod certificates;
+pub mod debug;
+pub mod delay_imports;
+pub mod exports;
+pub mod imports;
+pub mod load_config;
+pub mod ordinals;
+pub mod relocations;
+pub mod tls;
@@ -0,0 +1,390 @@
+use crate::{
+    align_up,
+    error::{Result, PeError},
+    headers::pe::machine::MachineType,
+    parsing::*,
+    PE,
+};
+
+/// Upper bound on the number of relocation blocks read from the directory.
+const MAX_BLOCKS: usize = 0x10000;
+
+/// The kind of fixup a base relocation applies. Types 5, 7, 8 and 9 are
+/// machine specific and are decoded based on the machine of the image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RelocationType {
+    /// The base relocation is skipped. This type can be used to pad a block.
+    Absolute,
+    /// Adds the high 16 bits of the difference to the 16-bit field at offset.
+    High,
+    /// Adds the low 16 bits of the difference to the 16-bit field at offset.
+    Low,
+    /// Applies all 32 bits of the difference to the 32-bit field at offset.
+    HighLow,
+    /// Adds the high 16 bits of the difference to the 16-bit field at offset,
+    /// using the low 16 bits stored in the following entry.
+    HighAdj,
+    /// The relocation applies to a MIPS jump instruction.
+    MipsJmpAddr,
+    /// Applies the difference to a MOVW/MOVT pair of ARM instructions.
+    ArmMov32,
+    /// Applies the high 20 bits of the difference to a RISC-V U-type
+    /// instruction (LUI/AUIPC).
+    RiscvHigh20,
+    /// Applies the difference to a MOVW/MOVT pair of Thumb-2 instructions.
+    ThumbMov32,
+    /// Applies the low 12 bits of the difference to a RISC-V I-type
+    /// instruction.
+    RiscvLow12I,
+    /// Applies the low 12 bits of the difference to a RISC-V S-type
+    /// instruction.
+    RiscvLow12S,
+    /// Applies the difference to a LoongArch address loading sequence.
+    LoongArchMarkLa,
+    /// The relocation applies to a MIPS16 jump instruction.
+    MipsJmpAddr16,
+    /// Applies the difference to the 64-bit field at offset.
+    Dir64,
+    /// A type that is reserved or has no meaning on this machine
+    Unknown(u8),
+}
+
+impl RelocationType {
+    /// Decodes the 4-bit type of an entry for the given `machine`
+    pub fn from_raw(value: u8, machine: MachineType) -> Self {
+        let is_mips = matches!(machine, MachineType::R4000
+            | MachineType::WceMipsV2 | MachineType::Mips16
+            | MachineType::MipsFpu | MachineType::MipsFpu16);
+        let is_arm = matches!(machine, MachineType::Arm
+            | MachineType::ArmNt | MachineType::Thumb);
+        let is_riscv = matches!(machine, MachineType::RiscV32
+            | MachineType::RiscV64 | MachineType::RiscV128);
+        let is_loongarch = matches!(machine, MachineType::LoongArch32
+            | MachineType::LoongArch64);
+
+        match value {
+            0 => Self::Absolute,
+            1 => Self::High,
+            2 => Self::Low,
+            3 => Self::HighLow,
+            4 => Self::HighAdj,
+            5 if is_mips => Self::MipsJmpAddr,
+            5 if is_arm => Self::ArmMov32,
+            5 if is_riscv => Self::RiscvHigh20,
+            7 if is_arm => Self::ThumbMov32,
+            7 if is_riscv => Self::RiscvLow12I,
+            8 if is_riscv => Self::RiscvLow12S,
+            8 if is_loongarch => Self::LoongArchMarkLa,
+            9 if is_mips => Self::MipsJmpAddr16,
+            10 => Self::Dir64,
+            value => Self::Unknown(value),
+        }
+    }
+
+    /// Returns the 4-bit type stored in the entry
+    pub fn to_raw(self) -> u16 {
+        match self {
+            Self::Absolute => 0,
+            Self::High => 1,
+            Self::Low => 2,
+            Self::HighLow => 3,
+            Self::HighAdj => 4,
+            Self::MipsJmpAddr | Self::ArmMov32 | Self::RiscvHigh20 => 5,
+            Self::ThumbMov32 | Self::RiscvLow12I => 7,
+            Self::RiscvLow12S | Self::LoongArchMarkLa => 8,
+            Self::MipsJmpAddr16 => 9,
+            Self::Dir64 => 10,
+            Self::Unknown(value) => value as u16,
+        }
+    }
+}
+
+/// A single fixup inside a relocation block
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Relocation {
+    /// The kind of fixup to apply
+    pub kind: RelocationType,
+    /// The RVA of the field to fix, i.e. the page RVA plus the 12-bit offset
+    /// stored in the entry
+    pub rva: u32,
+    /// The low 16 bits of the target for `HighAdj` relocations, taken from
+    /// the entry that follows it
+    pub adjust: Option<u16>,
+}
+
+/// A base relocation block, covering a single 4K page
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelocationBlock {
+    /// The image base plus the page RVA is added to each offset to create
+    /// the VA where the base relocation must be applied.
+    pub page_rva: u32,
+    /// The total number of bytes in the block, including the 8 bytes of the
+    /// header.
+    pub block_size: u32,
+    /// The fixups of the block
+    pub entries: Vec<Relocation>,
+}
+
+/// Walks the base relocation directory of `pe`
+pub fn parse_relocations(pe: &PE) -> Result<Vec<RelocationBlock>> {
+    let directory = match pe.opt_header.data_directories.basereloc() {
+        Some(directory) => *directory,
+        None => return Ok(Vec::new()),
+    };
+    let machine = pe.file_header.machine;
+
+    let mut blocks = Vec::new();
+    let mut consumed = 0u32;
+    while consumed < directory.size && blocks.len() < MAX_BLOCKS {
+        let rva = directory.virtual_address.wrapping_add(consumed);
+        let header = pe.read_at_rva(rva, 8)?;
+        let (page_rva, bytes) = take_u32(&header)?;
+        let (block_size, _) = take_u32(bytes)?;
+        // A block smaller than its header would never advance
+        if block_size < 8 {
+            break;
+        }
+
+        let count = (block_size as usize - 8) / 2;
+        let data = pe.read_at_rva(rva.wrapping_add(8), count * 2)?;
+        let raw: Vec<u16> = data.chunks_exact(2)
+            .map(|entry| u16::from_le_bytes([entry[0], entry[1]]))
+            .collect();
+
+        let mut entries = Vec::with_capacity(count);
+        let mut raw = raw.into_iter();
+        while let Some(entry) = raw.next() {
+            let kind = RelocationType::from_raw((entry >> 12) as u8, machine);
+            let adjust = match kind {
+                RelocationType::HighAdj => raw.next(),
+                _ => None,
+            };
+            entries.push(Relocation {
+                kind,
+                rva: page_rva.wrapping_add((entry & 0xfff) as u32),
+                adjust,
+            });
+        }
+
+        blocks.push(RelocationBlock { page_rva, block_size, entries });
+        consumed = consumed.saturating_add(block_size);
+    }
+
+    Ok(blocks)
+}
+
+/// Lays out the headers and sections of `pe` the way the loader maps them in
+/// memory, without applying any relocation. The buffer is `size_of_image`
+/// bytes long and indexed by RVA. `size_of_image` may not exceed the end of
+/// the last section, so that a forged header cannot make us allocate more
+/// than the sections describe.
+pub fn map_image(pe: &PE) -> Result<Vec<u8>> {
+    let win_fields = &pe.opt_header.win_fields;
+    let size_of_image = win_fields.size_of_image();
+    let alignment = pe.effective_section_alignment() as u64;
+    let extent = pe.sections.iter()
+        .map(|section| pe.section_range(section).1)
+        .fold(align_up(win_fields.size_of_headers() as u64, alignment),
+            u64::max);
+    if size_of_image as u64 > extent {
+        return Err(PeError::InvalidSizeOfImage(size_of_image));
+    }
+
+    let mut image = Vec::new();
+    image.try_reserve_exact(size_of_image as usize)
+        .map_err(|_| PeError::InvalidSizeOfImage(size_of_image))?;
+    image.resize(size_of_image as usize, 0);
+
+    let headers = (win_fields.size_of_headers() as usize)
+        .min(pe.data().len())
+        .min(image.len());
+    image[..headers].copy_from_slice(&pe.data()[..headers]);
+
+    for section in &pe.sections {
+        let size = match section.virtual_size {
+            0 => section.size_of_raw_data,
+            size => size,
+        };
+        let start = section.virtual_address as usize;
+        let end = start.saturating_add(size as usize).min(image.len());
+        if start >= end {
+            continue;
+        }
+        let data = pe.read_at_rva(section.virtual_address, end - start)?;
+        image[start..end].copy_from_slice(&data);
+    }
+
+    Ok(image)
+}
+
+/// Applies the base relocations of `pe` to `image`, a buffer indexed by RVA
+/// such as the one returned by `map_image` or a memory dump, so that it
+/// matches an image loaded at `new_base`. The fields in `image` are assumed
+/// to be relative to the preferred image base of `pe`.
+pub fn apply_relocations(pe: &PE, image: &mut [u8], new_base: u64)
+        -> Result<()> {
+    let delta = new_base.wrapping_sub(pe.opt_header.win_fields.image_base());
+    if delta == 0 {
+        return Ok(());
+    }
+
+    for block in parse_relocations(pe)? {
+        for relocation in block.entries {
+            apply_relocation(image, &relocation, delta)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Applies a single base relocation to `image`, a buffer indexed by RVA,
+/// adding `delta` to the field it fixes
+pub fn apply_relocation(image: &mut [u8], relocation: &Relocation,
+        delta: u64) -> Result<()> {
+    let rva = relocation.rva;
+    let offset = rva as usize;
+    match relocation.kind {
+        RelocationType::Absolute => {},
+        RelocationType::High => {
+            let value = read_u16(image, offset, rva)?;
+            write_u16(image, offset,
+                value.wrapping_add((delta >> 16) as u16));
+        },
+        RelocationType::Low => {
+            let value = read_u16(image, offset, rva)?;
+            write_u16(image, offset, value.wrapping_add(delta as u16));
+        },
+        RelocationType::HighLow => {
+            let value = read_u32(image, offset, rva)?;
+            write_u32(image, offset, value.wrapping_add(delta as u32));
+        },
+        RelocationType::HighAdj => {
+            // Rebuild the full 32-bit value, relocate it and round the
+            // result so that the sign extended low half still adds up
+            let high = read_u16(image, offset, rva)? as u32;
+            let low = relocation.adjust.unwrap_or(0) as i16 as i32 as u32;
+            let value = (high << 16).wrapping_add(low)
+                .wrapping_add(delta as u32)
+                .wrapping_add(0x8000);
+            write_u16(image, offset, (value >> 16) as u16);
+        },
+        RelocationType::Dir64 => {
+            let value = read_u64(image, offset, rva)?;
+            write_u64(image, offset, value.wrapping_add(delta));
+        },
+        RelocationType::ArmMov32 => {
+            let movw = read_u32(image, offset, rva)?;
+            let movt = read_u32(image, offset + 4, rva)?;
+            let value = (arm_imm16(movt) << 16 | arm_imm16(movw))
+                .wrapping_add(delta as u32);
+            write_u32(image, offset, set_arm_imm16(movw, value as u16));
+            write_u32(image, offset + 4,
+                set_arm_imm16(movt, (value >> 16) as u16));
+        },
+        RelocationType::ThumbMov32 => {
+            let movw = read_thumb(image, offset, rva)?;
+            let movt = read_thumb(image, offset + 4, rva)?;
+            let value = (thumb_imm16(movt) << 16 | thumb_imm16(movw))
+                .wrapping_add(delta as u32);
+            write_thumb(image, offset, set_thumb_imm16(movw, value as u16));
+            write_thumb(image, offset + 4,
+                set_thumb_imm16(movt, (value >> 16) as u16));
+        },
+        kind @ (RelocationType::RiscvHigh20
+                | RelocationType::RiscvLow12I
+                | RelocationType::RiscvLow12S) if delta & 0xfff != 0 => {
+            // The halves are not paired in the relocation table, so they
+            // can only be rebased on their own if the low 12 bits of the
+            // delta, and with them the carry into the high 20 bits, are zero
+            return Err(PeError::UnalignedRelocation(kind.to_raw()));
+        },
+        RelocationType::RiscvHigh20 => {
+            let instruction = read_u32(image, offset, rva)?;
+            write_u32(image, offset, instruction.wrapping_add(delta as u32));
+        },
+        RelocationType::RiscvLow12I | RelocationType::RiscvLow12S => {
+            // The low 12 bits of the delta are zero, nothing to add
+            field(image, offset, 4, rva)?;
+        },
+        kind @ (RelocationType::MipsJmpAddr
+                | RelocationType::MipsJmpAddr16
+                | RelocationType::LoongArchMarkLa
+                | RelocationType::Unknown(_)) => {
+            return Err(PeError::UnsupportedRelocation(kind.to_raw()));
+        },
+    }
+
+    Ok(())
+}
+
+/// Extracts the 16-bit immediate of an ARM MOVW/MOVT instruction, stored as
+/// imm4 in bits 16-19 and imm12 in bits 0-11
+fn arm_imm16(instruction: u32) -> u32 {
+    (instruction >> 4) & 0xf000 | instruction & 0xfff
+}
+
+fn set_arm_imm16(instruction: u32, imm: u16) -> u32 {
+    let imm = imm as u32;
+    (instruction & 0xfff0_f000) | (imm & 0xf000) << 4 | imm & 0xfff
+}
+
+/// Extracts the 16-bit immediate of a Thumb-2 MOVW/MOVT instruction, given
+/// as its first halfword in the upper 16 bits and its second halfword in the
+/// lower 16 bits. The immediate is split as imm4:i:imm3:imm8.
+fn thumb_imm16(instruction: u32) -> u32 {
+    let imm4 = (instruction >> 16) & 0xf;
+    let i = (instruction >> 26) & 0x1;
+    let imm3 = (instruction >> 12) & 0x7;
+    let imm8 = instruction & 0xff;
+    imm4 << 12 | i << 11 | imm3 << 8 | imm8
+}
+
+fn set_thumb_imm16(instruction: u32, imm: u16) -> u32 {
+    let imm = imm as u32;
+    let imm4 = (imm >> 12) & 0xf;
+    let i = (imm >> 11) & 0x1;
+    let imm3 = (imm >> 8) & 0x7;
+    let imm8 = imm & 0xff;
+    (instruction & 0xfbf0_8f00) | imm4 << 16 | i << 26 | imm3 << 12 | imm8
+}
+
+fn field(image: &[u8], offset: usize, len: usize, rva: u32) -> Result<&[u8]> {
+    image.get(offset..offset.saturating_add(len))
+        .ok_or(PeError::UnmappedRva(rva))
+}
+
+fn read_u16(image: &[u8], offset: usize, rva: u32) -> Result<u16> {
+    Ok(take_u16(field(image, offset, 2, rva)?)?.0)
+}
+
+fn read_u32(image: &[u8], offset: usize, rva: u32) -> Result<u32> {
+    Ok(take_u32(field(image, offset, 4, rva)?)?.0)
+}
+
+fn read_u64(image: &[u8], offset: usize, rva: u32) -> Result<u64> {
+    Ok(take_u64(field(image, offset, 8, rva)?)?.0)
+}
+
+/// Reads a Thumb-2 instruction as two little endian halfwords
+fn read_thumb(image: &[u8], offset: usize, rva: u32) -> Result<u32> {
+    let first = read_u16(image, offset, rva)? as u32;
+    let second = read_u16(image, offset + 2, rva)? as u32;
+    Ok(first << 16 | second)
+}
+
+fn write_u16(image: &mut [u8], offset: usize, value: u16) {
+    image[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+fn write_u32(image: &mut [u8], offset: usize, value: u32) {
+    image[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+fn write_u64(image: &mut [u8], offset: usize, value: u64) {
+    image[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+}
+
+fn write_thumb(image: &mut [u8], offset: usize, value: u32) {
+    write_u16(image, offset, (value >> 16) as u16);
+    write_u16(image, offset + 2, value as u16);
+}
@@ -0,0 +1,130 @@
+use crate::{
+    directories::imports::thunk_size,
+    error::Result,
+    parsing::*,
+    PE,
+};
+
+/// Upper bound on the number of TLS callbacks read from the callback array.
+const MAX_CALLBACKS: usize = 0x1000;
+
+/// The `IMAGE_TLS_DIRECTORY32` and `IMAGE_TLS_DIRECTORY64` structures. The
+/// address fields are virtual addresses, widened to 64 bits for PE32 images.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TlsDirectory {
+    /// The starting address of the TLS template. The template is a block of
+    /// data that is used to initialize TLS data.
+    pub start_address_of_raw_data: u64,
+    /// The address of the last byte of the TLS, except for the zero fill.
+    pub end_address_of_raw_data: u64,
+    /// The location to receive the TLS index, which the loader assigns.
+    pub address_of_index: u64,
+    /// The pointer to an array of TLS callback functions. The array is null
+    /// terminated.
+    pub address_of_callbacks: u64,
+    /// The size in bytes of the template, beyond the initialized data
+    /// delimited by the raw data fields, that is filled with zeroes.
+    pub size_of_zero_fill: u32,
+    /// The four bits [23:20] describe alignment info. The other bits are
+    /// reserved.
+    pub characteristics: u32,
+}
+
+impl TlsDirectory {
+    /// Parses a TLS directory, using 64-bit address fields if `is_pe64` is
+    /// set
+    pub fn from_bytes(bytes: &[u8], is_pe64: bool) -> Result<(Self, &[u8])> {
+        let take_address = |bytes| -> Result<(u64, &[u8])> {
+            if is_pe64 {
+                take_u64(bytes)
+            } else {
+                take_u32(bytes).map(|(value, bytes)| (value as u64, bytes))
+            }
+        };
+        let (start_address_of_raw_data, bytes) = take_address(bytes)?;
+        let (end_address_of_raw_data, bytes) = take_address(bytes)?;
+        let (address_of_index, bytes) = take_address(bytes)?;
+        let (address_of_callbacks, bytes) = take_address(bytes)?;
+        let (size_of_zero_fill, bytes) = take_u32(bytes)?;
+        let (characteristics, bytes) = take_u32(bytes)?;
+
+        Ok((Self {
+            start_address_of_raw_data, end_address_of_raw_data,
+            address_of_index, address_of_callbacks, size_of_zero_fill,
+            characteristics
+        }, bytes))
+    }
+
+    /// The size of the directory for PE32 (`is_pe64` unset) or PE32+ images
+    pub fn len(is_pe64: bool) -> usize {
+        if is_pe64 { 40usize } else { 24usize }
+    }
+
+    /// Returns the alignment of the TLS data in bytes, if one is encoded in
+    /// `characteristics`
+    pub fn alignment(&self) -> Option<u32> {
+        match (self.characteristics >> 20) & 0xf {
+            0 => None,
+            shift => Some(1 << (shift - 1)),
+        }
+    }
+}
+
+/// An entry of the TLS callback array
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TlsCallback {
+    /// The virtual address stored in the array
+    pub va: u64,
+    /// The RVA of the callback, `None` if the address lies outside of the
+    /// image
+    pub rva: Option<u32>,
+}
+
+/// The TLS directory of an image and its callbacks
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tls {
+    /// The raw TLS directory
+    pub directory: TlsDirectory,
+    /// The TLS callbacks, in the order the loader calls them
+    pub callbacks: Vec<TlsCallback>,
+}
+
+impl Tls {
+    /// Parses the TLS directory of `pe` and walks its callback array. Returns
+    /// `None` if the image has no TLS directory. Callbacks outside of the
+    /// image are kept, without an RVA.
+    pub fn from_pe(pe: &PE) -> Result<Option<Self>> {
+        let range = match pe.opt_header.data_directories.tls() {
+            Some(directory) => *directory,
+            None => return Ok(None),
+        };
+        let is_pe64 = pe.opt_header.win_fields.is_pe64();
+        let data = pe.read_at_rva(range.virtual_address,
+            TlsDirectory::len(is_pe64))?;
+        let (directory, _) = TlsDirectory::from_bytes(&data, is_pe64)?;
+
+        let mut callbacks = Vec::new();
+        if directory.address_of_callbacks != 0 {
+            let thunk_size = thunk_size(pe);
+            let mut rva = pe.va_to_rva(directory.address_of_callbacks)?;
+            while callbacks.len() < MAX_CALLBACKS {
+                let data = pe.read_at_rva(rva, thunk_size as usize)?;
+                let callback = if is_pe64 {
+                    take_u64(&data)?.0
+                } else {
+                    take_u32(&data)?.0 as u64
+                };
+                if callback == 0 {
+                    break;
+                }
+                callbacks.push(TlsCallback {
+                    va: callback,
+                    rva: pe.va_to_rva(callback).ok(),
+                });
+                rva = rva.wrapping_add(thunk_size);
+            }
+        }
+
+        Ok(Some(Self { directory, callbacks }))
+    }
+}
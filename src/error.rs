@@ -10,12 +10,53 @@ pub enum PeError {
     IntConversionFailed(std::num::TryFromIntError),
     BufferTooSmall,
     InvalidMachineType(u16),
+    /// The `e_lfanew` field of the MS-DOS header points before the end of the
+    /// MS-DOS header or past the end of the file
+    InvalidPeOffset(u32),
+    /// The RVA is not backed by the headers or by any section of the file
+    UnmappedRva(u32),
+    /// The virtual address lies outside of the image
+    InvalidVa(u64),
+    /// No null terminator was found for the string at the given RVA
+    UnterminatedString(u32),
+    /// `size_of_image` reaches past the end of the last section, or the
+    /// image cannot be allocated
+    InvalidSizeOfImage(u32),
+    /// The base relocation type, as stored in the entry, cannot be applied
+    UnsupportedRelocation(u16),
+    /// The base relocation type can only be applied with a delta that is a
+    /// multiple of 4 KiB
+    UnalignedRelocation(u16),
+    /// A resource directory at the given offset contains itself
+    ResourceCycle(u32),
+    /// The resource directory at the given offset is nested too deeply
+    ResourceTooDeep(u32),
+    /// The resource tree has too many entries
+    ResourceTooLarge,
+    /// The `RT_VERSION` resource is not a valid `VS_VERSIONINFO` structure
+    InvalidVersionInfo,
+    /// The header of an icon or cursor group does not match its type
+    InvalidIconGroup,
+    /// A block of a message table is malformed
+    InvalidMessageTable,
+    /// A menu template has an unknown version or nests too deep
+    InvalidMenu,
+    /// The attribute certificate table, or its entry at this file offset, is
+    /// malformed
+    InvalidCertificate(u32),
+    /// A signature, or a certificate it holds, is not valid DER or does not
+    /// have the expected structure
+    InvalidDer,
+    /// A PEM file has no certificate or its base64 body is malformed
+    InvalidPem,
+    /// A `Rich` marker was found without a matching `DanS` header
+    InvalidRichHeader,
     Unimplemented,
 }
 
 impl fmt::Display for PeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self)
+        write!(f, "{:?}", self)
     }
 }
 
@@ -46,7 +46,7 @@ pub struct DosHeader {
 }
 
 impl DosHeader {
-    pub fn from_bytes<'pe>(bytes: &'pe [u8]) -> Result<(Self, &[u8])> {
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8])> {
         let (e_magic, bytes)     = take_u16(bytes)?;
         let (e_cblp, bytes)      = take_u16(bytes)?;
         let (e_cp, bytes)        = take_u16(bytes)?;
@@ -63,22 +63,23 @@ impl DosHeader {
         let (e_ovno, bytes)      = take_u16(bytes)?;
 
         let mut e_res = Vec::with_capacity(4usize);
-        for chunk in bytes.chunks(2) {
-            let (value, bytes) = take_u16(bytes)?;
+        let mut bytes = bytes;
+        for _ in 0..4 {
+            let (value, rest) = take_u16(bytes)?;
             e_res.push(value);
+            bytes = rest;
         }
 
-        let (_, bytes) = bytes.split_at(8usize);
-
         let (e_oemid, bytes)     = take_u16(bytes)?;
         let (e_oeminfo, bytes)   = take_u16(bytes)?;
 
         let mut e_res2 = Vec::with_capacity(10usize);
-        for chunk in bytes.chunks(2) {
-            let (value, bytes) = take_u16(bytes)?;
-            e_res.push(value);
+        let mut bytes = bytes;
+        for _ in 0..10 {
+            let (value, rest) = take_u16(bytes)?;
+            e_res2.push(value);
+            bytes = rest;
         }
-        let (_, bytes) = bytes.split_at(20usize);
 
         let (e_lfanew, bytes)    = take_u32(bytes)?;
 
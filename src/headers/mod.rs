@@ -1,2 +1,3 @@
 pub mod dos;
 pub mod pe;
+pub mod rich;
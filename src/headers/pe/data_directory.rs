@@ -0,0 +1,173 @@
+use crate::{
+    error::Result,
+    parsing::*
+};
+
+/// Each data directory gives the address and size of a table or string that
+/// Windows uses. The `virtual_address` is an RVA for every directory except
+/// the security one, where it is a file offset.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DataDirectory {
+    /// The RVA of the table, i.e. the address of the table relative to the
+    /// base address of the image when the table is loaded.
+    pub virtual_address: u32,
+    /// The size in bytes of the table.
+    pub size: u32,
+}
+
+impl DataDirectory {
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8])> {
+        let (virtual_address, bytes) = take_u32(bytes)?;
+        let (size, bytes) = take_u32(bytes)?;
+
+        Ok((Self { virtual_address, size }, bytes))
+    }
+
+    /// Returns true if the directory does not point to any table
+    pub fn is_empty(&self) -> bool {
+        self.virtual_address == 0 && self.size == 0
+    }
+
+    pub fn len() -> usize {
+        8usize
+    }
+}
+
+/// The index of each data directory in the optional header
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DataDirectoryType {
+    /// The export table
+    Export = 0,
+    /// The import table
+    Import = 1,
+    /// The resource table
+    Resource = 2,
+    /// The exception table
+    Exception = 3,
+    /// The attribute certificate table. Its address is a file offset.
+    Security = 4,
+    /// The base relocation table
+    BaseReloc = 5,
+    /// The debug data starting address and size
+    Debug = 6,
+    /// Reserved, must be zero
+    Architecture = 7,
+    /// The RVA of the value to be stored in the global pointer register
+    GlobalPtr = 8,
+    /// The thread local storage (TLS) table
+    Tls = 9,
+    /// The load configuration table
+    LoadConfig = 10,
+    /// The bound import table
+    BoundImport = 11,
+    /// The import address table
+    Iat = 12,
+    /// The delay import descriptor
+    DelayImport = 13,
+    /// The CLR runtime header
+    ClrRuntime = 14,
+    /// Reserved, must be zero
+    Reserved = 15,
+}
+
+/// The data directories that follow the Windows specific fields of the
+/// optional header. Only `number_of_rva_and_sizes` entries are present, which
+/// may be less than the 16 documented ones. Entries past the 16th are kept
+/// but have no meaning to the loader.
+#[derive(Debug, Clone, Default)]
+pub struct DataDirectories {
+    entries: Vec<DataDirectory>,
+}
+
+impl DataDirectories {
+    /// Parses `count` data directories from `bytes`. A count that does not
+    /// fit in `bytes` is clamped to the number of complete entries available,
+    /// since the directories cannot extend past the optional header.
+    pub fn from_bytes(bytes: &[u8], count: u32) -> Result<(Self, &[u8])> {
+        let available = bytes.len() / DataDirectory::len();
+        let count = (count as usize).min(available);
+
+        let mut entries = Vec::with_capacity(count);
+        let mut bytes = bytes;
+        for _ in 0..count {
+            let (entry, rest) = DataDirectory::from_bytes(bytes)?;
+            entries.push(entry);
+            bytes = rest;
+        }
+
+        Ok((Self { entries }, bytes))
+    }
+
+    /// Returns the number of data directories that were parsed
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the optional header declares no data directories
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns every parsed entry, including empty ones
+    pub fn entries(&self) -> &[DataDirectory] {
+        &self.entries
+    }
+
+    /// Returns the directory of the given type, if it is declared and not
+    /// empty
+    pub fn get(&self, kind: DataDirectoryType) -> Option<&DataDirectory> {
+        self.entries.get(kind as usize).filter(|entry| !entry.is_empty())
+    }
+
+    pub fn export(&self) -> Option<&DataDirectory> {
+        self.get(DataDirectoryType::Export)
+    }
+
+    pub fn import(&self) -> Option<&DataDirectory> {
+        self.get(DataDirectoryType::Import)
+    }
+
+    pub fn resource(&self) -> Option<&DataDirectory> {
+        self.get(DataDirectoryType::Resource)
+    }
+
+    pub fn exception(&self) -> Option<&DataDirectory> {
+        self.get(DataDirectoryType::Exception)
+    }
+
+    pub fn security(&self) -> Option<&DataDirectory> {
+        self.get(DataDirectoryType::Security)
+    }
+
+    pub fn basereloc(&self) -> Option<&DataDirectory> {
+        self.get(DataDirectoryType::BaseReloc)
+    }
+
+    pub fn debug(&self) -> Option<&DataDirectory> {
+        self.get(DataDirectoryType::Debug)
+    }
+
+    pub fn tls(&self) -> Option<&DataDirectory> {
+        self.get(DataDirectoryType::Tls)
+    }
+
+    pub fn load_config(&self) -> Option<&DataDirectory> {
+        self.get(DataDirectoryType::LoadConfig)
+    }
+
+    pub fn bound_import(&self) -> Option<&DataDirectory> {
+        self.get(DataDirectoryType::BoundImport)
+    }
+
+    pub fn iat(&self) -> Option<&DataDirectory> {
+        self.get(DataDirectoryType::Iat)
+    }
+
+    pub fn delay_import(&self) -> Option<&DataDirectory> {
+        self.get(DataDirectoryType::DelayImport)
+    }
+
+    pub fn clr(&self) -> Option<&DataDirectory> {
+        self.get(DataDirectoryType::ClrRuntime)
+    }
+}
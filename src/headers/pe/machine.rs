@@ -1,5 +1,6 @@
 use crate::error::{PeError};
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum MachineType {
     /// The content of this field is assumed to be applicable to any machine
     Unknown     = 0x0,
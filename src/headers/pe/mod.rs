@@ -2,3 +2,5 @@ pub mod file_header;
 pub mod machine;
 pub mod characteristics;
 pub mod opt_header;
+pub mod section;
+pub mod data_directory;
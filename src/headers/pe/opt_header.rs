@@ -1,33 +1,44 @@
 use crate::{
     error::{Result, PeError},
+    headers::pe::data_directory::DataDirectories,
     parsing::*
 };
 
 pub struct OptionalHeader {
     /// Defines the type of the image file. The most common is 0x10B which is
     /// a normal executable file.
+    #[allow(dead_code)]
     magic: ImageType,
     /// The linker major version number
+    #[allow(dead_code)]
     major_linker_version: u8,
     /// The linker minor version number
+    #[allow(dead_code)]
     minor_linker_version: u8,
     /// The size of code(text) section, or the sum of all code sections if
     /// there are multiple sections.
+    #[allow(dead_code)]
     size_of_code: u32,
     /// The size of the initialized data section, or the sum of all such
     /// sections if there are multiple data sections
+    #[allow(dead_code)]
     size_of_initialized_data: u32,
     /// The size of the uninitialized data section(BSS), of the sum of all such
     /// sections if there are multiple BSS sections.
+    #[allow(dead_code)]
     size_of_uninitialized_data: u32,
     /// The address of the entry point relative to the image base when the exe
     /// file is loaded into memory
+    #[allow(dead_code)]
     addr_of_entry_point: u32,
     /// The address that is relative to the image base of the beginning-of-code
     /// section when it is loaded into memory.
+    #[allow(dead_code)]
     base_of_code: u32,
     /// Windows specific fields
-    pub win_fields: WindowsSpecific
+    pub win_fields: WindowsSpecific,
+    /// Address and size of the tables used by Windows
+    pub data_directories: DataDirectories,
 }
 
 impl OptionalHeader {
@@ -42,6 +53,8 @@ impl OptionalHeader {
         let (addr_of_entry_point, bytes) = take_u32(bytes)?;
         let (base_of_code, bytes) = take_u32(bytes)?;
         let (win_fields, bytes) = WindowsSpecific::from_bytes(magic, bytes)?;
+        let (data_directories, bytes) = DataDirectories::from_bytes(bytes,
+            win_fields.number_of_rva_and_sizes())?;
 
         Ok(( Self {
             magic,
@@ -52,7 +65,8 @@ impl OptionalHeader {
             size_of_uninitialized_data,
             addr_of_entry_point,
             base_of_code,
-            win_fields
+            win_fields,
+            data_directories,
         }, bytes))
     }
 }
@@ -97,6 +111,72 @@ impl WindowsSpecific {
             ImageType::Unknown => Err(PeError::Unimplemented)
         }
     }
+
+    /// Returns true for PE32+ images, which use 64-bit addresses
+    pub fn is_pe64(&self) -> bool {
+        matches!(self, Self::PE64(_))
+    }
+
+    /// The preferred address of the first byte of image when loaded into
+    /// memory.
+    pub fn image_base(&self) -> u64 {
+        match self {
+            Self::PE32(pe32) => pe32.image_base as u64,
+            Self::PE64(pe64) => pe64.image_base,
+        }
+    }
+
+    /// The alignment (in bytes) of sections when they are loaded into memory.
+    pub fn section_alignment(&self) -> u32 {
+        match self {
+            Self::PE32(pe32) => pe32.section_alignment,
+            Self::PE64(pe64) => pe64.section_alignment,
+        }
+    }
+
+    /// The alignment factor(in bytes) that is used to align the raw data of
+    /// sections in the image file.
+    pub fn file_alignment(&self) -> u32 {
+        match self {
+            Self::PE32(pe32) => pe32.file_alignment,
+            Self::PE64(pe64) => pe64.file_alignment,
+        }
+    }
+
+    /// The size of the image, including all headers, as the image is loaded in
+    /// memory.
+    pub fn size_of_image(&self) -> u32 {
+        match self {
+            Self::PE32(pe32) => pe32.size_of_image,
+            Self::PE64(pe64) => pe64.size_of_image,
+        }
+    }
+
+    /// The combines size of an MS-DOS stub, PE header, and section headers
+    /// rounded up to a multiple of `file_alignment`.
+    pub fn size_of_headers(&self) -> u32 {
+        match self {
+            Self::PE32(pe32) => pe32.size_of_headers,
+            Self::PE64(pe64) => pe64.size_of_headers,
+        }
+    }
+
+    /// The image file checksum.
+    pub fn checksum(&self) -> u32 {
+        match self {
+            Self::PE32(pe32) => pe32.checksum,
+            Self::PE64(pe64) => pe64.checksum,
+        }
+    }
+
+    /// The number of data-directory entries in the remainder of the optional
+    /// header.
+    pub fn number_of_rva_and_sizes(&self) -> u32 {
+        match self {
+            Self::PE32(pe32) => pe32.number_of_rva_and_sizes,
+            Self::PE64(pe64) => pe64.number_of_rva_and_sizes,
+        }
+    }
 }
 
 pub struct Pe32 {
@@ -119,18 +199,25 @@ pub struct Pe32 {
     /// FileAlignment must match SectionAlignment.
     file_alignment: u32,
     /// The major version number of the required operating system.
+    #[allow(dead_code)]
     major_os_version: u16,
     /// The minor version number of the required operating system.
+    #[allow(dead_code)]
     minor_os_version: u16,
     /// the major version number of the image.
+    #[allow(dead_code)]
     major_image_version: u16,
     /// The minor version number of the image.
+    #[allow(dead_code)]
     minor_image_version: u16,
     /// the major version number of the subsystem.
+    #[allow(dead_code)]
     major_subsys_version: u16,
     /// The minor version number of the subsystem.
+    #[allow(dead_code)]
     minor_subsys_version: u16,
     /// Reserved, must be zero.
+    #[allow(dead_code)]
     win32_version_value: u32,
     /// The size of the image, including all headers, as the image is loaded in
     /// memory. It must be a multiple of `section_alignment`.
@@ -142,21 +229,28 @@ pub struct Pe32 {
     /// incorporated into IMAGHELP.dll.
     checksum: u32,
     /// The subsytem that is required to run this image.
+    #[allow(dead_code)]
     subsystem: u16,
+    #[allow(dead_code)]
     dll_characteristics: u16,
     /// The size of the stack to reserve. Only `size_of_stack_commit` is
     /// commited; the rest is made available one page at a time until the
     /// reserve size if reached.
+    #[allow(dead_code)]
     size_of_stack_reserve: u32,
     /// The size of the stack to commit.
+    #[allow(dead_code)]
     size_of_stack_commit: u32,
     /// The size of the heap to reserve. Only `size_of_heap_commit` is
     /// commited; the rest is made available one page at a time until the
     /// reserve size if reached.
+    #[allow(dead_code)]
     size_of_heap_reserve: u32,
     /// The size of the heap to commit.
+    #[allow(dead_code)]
     size_of_heap_commit: u32,
     /// Reserved, must be zero.
+    #[allow(dead_code)]
     loader_flags: u32,
     /// The number of data-directory entries in the remainder of the optional
     /// header. Each describes a location and size.
@@ -219,18 +313,25 @@ pub struct Pe64 {
     /// FileAlignment must match SectionAlignment.
     file_alignment: u32,
     /// The major version number of the required operating system.
+    #[allow(dead_code)]
     major_os_version: u16,
     /// The minor version number of the required operating system.
+    #[allow(dead_code)]
     minor_os_version: u16,
     /// the major version number of the image.
+    #[allow(dead_code)]
     major_image_version: u16,
     /// The minor version number of the image.
+    #[allow(dead_code)]
     minor_image_version: u16,
     /// the major version number of the subsystem.
+    #[allow(dead_code)]
     major_subsys_version: u16,
     /// The minor version number of the subsystem.
+    #[allow(dead_code)]
     minor_subsys_version: u16,
     /// Reserved, must be zero.
+    #[allow(dead_code)]
     win32_version_value: u32,
     /// The size of the image, including all headers, as the image is loaded in
     /// memory. It must be a multiple of `section_alignment`.
@@ -242,21 +343,28 @@ pub struct Pe64 {
     /// incorporated into IMAGHELP.dll.
     checksum: u32,
     /// The subsytem that is required to run this image.
+    #[allow(dead_code)]
     subsystem: u16,
+    #[allow(dead_code)]
     dll_characteristics: u16,
     /// The size of the stack to reserve. Only `size_of_stack_commit` is
     /// commited; the rest is made available one page at a time until the
     /// reserve size if reached.
+    #[allow(dead_code)]
     size_of_stack_reserve: u64,
     /// The size of the stack to commit.
+    #[allow(dead_code)]
     size_of_stack_commit: u64,
     /// The size of the heap to reserve. Only `size_of_heap_commit` is
     /// commited; the rest is made available one page at a time until the
     /// reserve size if reached.
+    #[allow(dead_code)]
     size_of_heap_reserve: u64,
     /// The size of the heap to commit.
+    #[allow(dead_code)]
     size_of_heap_commit: u64,
     /// Reserved, must be zero.
+    #[allow(dead_code)]
     loader_flags: u32,
     /// The number of data-directory entries in the remainder of the optional
     /// header. Each describes a location and size.
@@ -0,0 +1,251 @@
+use crate::{
+    error::Result,
+    parsing::*
+};
+
+/// Size of a single entry in the COFF symbol table. The string table follows
+/// immediately after the last symbol.
+const COFF_SYMBOL_SIZE: usize = 18;
+
+/// Each row of the section table is, in effect, a section header. The number
+/// of entries in the section table is given by the `number_of_sections` field
+/// in the file header.
+#[derive(Debug, Clone)]
+pub struct SectionHeader {
+    /// The name of the section with long names (`/123`) already resolved
+    /// through the COFF string table.
+    pub name: String,
+    /// An 8-byte, null-padded UTF-8 encoded string, exactly as it is stored in
+    /// the section table.
+    pub raw_name: [u8; 8],
+    /// The total size of the section when loaded into memory. If this value is
+    /// greater than `size_of_raw_data`, the section is zero-padded.
+    pub virtual_size: u32,
+    /// The address of the first byte of the section relative to the image
+    /// base when the section is loaded into memory.
+    pub virtual_address: u32,
+    /// The size of the initialized data on disk. It must be a multiple of
+    /// `file_alignment` from the optional header.
+    pub size_of_raw_data: u32,
+    /// The file pointer to the first page of the section within the file.
+    pub pointer_to_raw_data: u32,
+    /// The file pointer to the beginning of relocation entries for the
+    /// section. This is set to zero for executable images.
+    pub pointer_to_relocations: u32,
+    /// The file pointer to the beginning of line-number entries for the
+    /// section. This value should be zero because COFF debugging information
+    /// is deprecated.
+    pub pointer_to_linenumbers: u32,
+    /// The number of relocation entries for the section. This is set to zero
+    /// for executable images.
+    pub number_of_relocations: u16,
+    /// The number of line-number entries for the section.
+    pub number_of_linenumbers: u16,
+    /// The flags that describe the characteristics of the section.
+    pub characteristics: u32,
+}
+
+impl SectionHeader {
+    /// Parses a section header from `bytes`. `string_table` is the COFF
+    /// string table of the file, if any, and is used to resolve long section
+    /// names.
+    pub fn from_bytes<'a>(bytes: &'a [u8], string_table: Option<&[u8]>)
+            -> Result<(Self, &'a [u8])> {
+        let (raw_name, bytes) = take_bytes(bytes, 8)?;
+        let (virtual_size, bytes) = take_u32(bytes)?;
+        let (virtual_address, bytes) = take_u32(bytes)?;
+        let (size_of_raw_data, bytes) = take_u32(bytes)?;
+        let (pointer_to_raw_data, bytes) = take_u32(bytes)?;
+        let (pointer_to_relocations, bytes) = take_u32(bytes)?;
+        let (pointer_to_linenumbers, bytes) = take_u32(bytes)?;
+        let (number_of_relocations, bytes) = take_u16(bytes)?;
+        let (number_of_linenumbers, bytes) = take_u16(bytes)?;
+        let (characteristics, bytes) = take_u32(bytes)?;
+
+        let raw_name: [u8; 8] = raw_name.try_into()?;
+        let name = Self::resolve_name(&raw_name, string_table);
+
+        Ok((Self {
+            name, raw_name, virtual_size, virtual_address, size_of_raw_data,
+            pointer_to_raw_data, pointer_to_relocations,
+            pointer_to_linenumbers, number_of_relocations,
+            number_of_linenumbers, characteristics
+        }, bytes))
+    }
+
+    /// Resolves the name of a section. Names longer than 8 bytes are stored
+    /// in the string table and the section table only holds a slash followed
+    /// by the offset in decimal (`/123`) or, for very large offsets, two
+    /// slashes followed by the offset in base64 (`//AAAAAA`).
+    fn resolve_name(raw_name: &[u8; 8], string_table: Option<&[u8]>)
+            -> String {
+        let offset = match raw_name {
+            [b'/', b'/', rest @ ..] => decode_base64_offset(rest),
+            [b'/', rest @ ..] => decode_decimal_offset(rest),
+            _ => None,
+        };
+        let long_name = offset.zip(string_table)
+            .and_then(|(offset, table)| table.get(offset..));
+
+        let name = match long_name {
+            Some(name) => {
+                let len = name.iter().position(|&b| b == 0)
+                    .unwrap_or(name.len());
+                &name[..len]
+            },
+            // A slash that does not lead to the string table, because the
+            // offset is malformed or out of range or there is no table, is
+            // kept verbatim
+            None => {
+                let len = raw_name.iter().position(|&b| b == 0)
+                    .unwrap_or(raw_name.len());
+                &raw_name[..len]
+            }
+        };
+
+        String::from_utf8_lossy(name).into_owned()
+    }
+
+    /// The length of a section header in bytes
+    pub fn len() -> usize {
+        40usize
+    }
+
+    /// Returns the list of flags set in `characteristics`
+    pub fn flags(&self) -> Vec<SectionFlag> {
+        SectionFlag::to_vec(self.characteristics)
+    }
+
+}
+
+/// Returns the COFF string table of a file, which sits right after the COFF
+/// symbol table. The first 4 bytes of the table hold its total size.
+pub fn string_table(file: &[u8], pointer_to_symbol_table: u32,
+        number_of_symbols: u32) -> Option<&[u8]> {
+    if pointer_to_symbol_table == 0 {
+        return None;
+    }
+
+    let offset = (pointer_to_symbol_table as usize)
+        .checked_add((number_of_symbols as usize)
+            .checked_mul(COFF_SYMBOL_SIZE)?)?;
+    let table = file.get(offset..)?;
+    let (size, _) = take_u32(table).ok()?;
+    table.get(..size as usize)
+}
+
+fn decode_decimal_offset(digits: &[u8]) -> Option<usize> {
+    let mut offset = 0usize;
+    for &digit in digits.iter().take_while(|&&b| b != 0) {
+        if !digit.is_ascii_digit() {
+            return None;
+        }
+        offset = offset * 10 + (digit - b'0') as usize;
+    }
+    Some(offset)
+}
+
+fn decode_base64_offset(digits: &[u8]) -> Option<usize> {
+    let mut offset = 0usize;
+    for &digit in digits.iter().take_while(|&&b| b != 0) {
+        let value = match digit {
+            b'A'..=b'Z' => digit - b'A',
+            b'a'..=b'z' => digit - b'a' + 26,
+            b'0'..=b'9' => digit - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        offset = (offset << 6) | value as usize;
+    }
+    Some(offset)
+}
+
+#[derive(Debug, PartialEq)]
+#[repr(u32)]
+pub enum SectionFlag {
+    /// The section should not be padded to the next boundary. This flag is
+    /// obsolete and is replaced by the alignment flags.
+    TypeNoPad = 0x0000_0008,
+    /// The section contains executable code.
+    CntCode = 0x0000_0020,
+    /// The section contains initialized data.
+    CntInitializedData = 0x0000_0040,
+    /// The section contains uninitialized data.
+    CntUninitializedData = 0x0000_0080,
+    /// Reserved for future use.
+    LnkOther = 0x0000_0100,
+    /// The section contains comments or other information. Valid for object
+    /// files only.
+    LnkInfo = 0x0000_0200,
+    /// The section will not become part of the image. Valid only for object
+    /// files.
+    LnkRemove = 0x0000_0800,
+    /// The section contains COMDAT data. Valid only for object files.
+    LnkComdat = 0x0000_1000,
+    /// The section contains data referenced through the global pointer.
+    Gprel = 0x0000_8000,
+    /// Reserved for future use.
+    MemPurgeable = 0x0002_0000,
+    /// Reserved for future use.
+    MemLocked = 0x0004_0000,
+    /// Reserved for future use.
+    MemPreload = 0x0008_0000,
+    /// The section contains extended relocations.
+    LnkNrelocOvfl = 0x0100_0000,
+    /// The section can be discarded as needed.
+    MemDiscardable = 0x0200_0000,
+    /// The section cannot be cached.
+    MemNotCached = 0x0400_0000,
+    /// The section is not pageable.
+    MemNotPaged = 0x0800_0000,
+    /// The section can be shared in memory.
+    MemShared = 0x1000_0000,
+    /// The section can be executed as code.
+    MemExecute = 0x2000_0000,
+    /// The section can be read.
+    MemRead = 0x4000_0000,
+    /// The section can be written to.
+    MemWrite = 0x8000_0000,
+    /// Invalid
+    Invalid = 0x0,
+}
+
+impl SectionFlag {
+    pub fn to_vec(value: u32) -> Vec<Self> {
+        let mut flags = Vec::new();
+
+        // Cycle through every bit flag. The alignment bits (0x00F0_0000) hold
+        // a value rather than a flag and are skipped.
+        for i in 0..32 {
+            let check_flag = 1 << i;
+            let new_flag = match check_flag & value {
+                0x0000_0008 => Self::TypeNoPad,
+                0x0000_0020 => Self::CntCode,
+                0x0000_0040 => Self::CntInitializedData,
+                0x0000_0080 => Self::CntUninitializedData,
+                0x0000_0100 => Self::LnkOther,
+                0x0000_0200 => Self::LnkInfo,
+                0x0000_0800 => Self::LnkRemove,
+                0x0000_1000 => Self::LnkComdat,
+                0x0000_8000 => Self::Gprel,
+                0x0002_0000 => Self::MemPurgeable,
+                0x0004_0000 => Self::MemLocked,
+                0x0008_0000 => Self::MemPreload,
+                0x0100_0000 => Self::LnkNrelocOvfl,
+                0x0200_0000 => Self::MemDiscardable,
+                0x0400_0000 => Self::MemNotCached,
+                0x0800_0000 => Self::MemNotPaged,
+                0x1000_0000 => Self::MemShared,
+                0x2000_0000 => Self::MemExecute,
+                0x4000_0000 => Self::MemRead,
+                0x8000_0000 => Self::MemWrite,
+                _ => Self::Invalid,
+            };
+            if new_flag != Self::Invalid {
+                flags.push(new_flag);
+            }
+        }
+        flags
+    }
+}
@@ -0,0 +1,236 @@
+use crate::{
+    crypto::md5::Md5,
+    error::{PeError, Result},
+};
+
+/// `DanS`, the start marker once unmasked
+const DANS: u32 = 0x536e_6144;
+/// `Rich`, the end marker, followed by the key
+const RICH: &[u8] = b"Rich";
+/// Offset of `e_lfanew` in the MS-DOS header, left out of the checksum
+const E_LFANEW_OFFSET: usize = 0x3c;
+
+/// The names Microsoft's tools give to the product ids, indexed by id
+const PRODUCT_NAMES: [&str; 271] = [
+    "Unknown", "Import0", "Linker510", "Cvtomf510", "Linker600", "Cvtomf600",
+    "Cvtres500", "Utc11_Basic", "Utc11_C", "Utc12_Basic", "Utc12_C",
+    "Utc12_CPP", "AliasObj60", "VisualBasic60", "Masm613", "Masm710",
+    "Linker511", "Cvtomf511", "Masm614", "Linker512", "Cvtomf512",
+    "Utc12_C_Std", "Utc12_CPP_Std", "Utc12_C_Book", "Utc12_CPP_Book",
+    "Implib700", "Cvtomf700", "Utc13_Basic", "Utc13_C", "Utc13_CPP",
+    "Linker610", "Cvtomf610", "Linker601", "Cvtomf601", "Utc12_1_Basic",
+    "Utc12_1_C", "Utc12_1_CPP", "Linker620", "Cvtomf620", "AliasObj70",
+    "Linker621", "Cvtomf621", "Masm615", "Utc13_LTCG_C", "Utc13_LTCG_CPP",
+    "Masm620", "ILAsm100", "Utc12_2_Basic", "Utc12_2_C", "Utc12_2_CPP",
+    "Utc12_2_C_Std", "Utc12_2_CPP_Std", "Utc12_2_C_Book", "Utc12_2_CPP_Book",
+    "Implib622", "Cvtomf622", "Cvtres501", "Utc13_C_Std", "Utc13_CPP_Std",
+    "Cvtpgd1300", "Linker622", "Linker700", "Export622", "Export700", "Masm700",
+    "Utc13_POGO_I_C", "Utc13_POGO_I_CPP", "Utc13_POGO_O_C", "Utc13_POGO_O_CPP",
+    "Cvtres700", "Cvtres710p", "Linker710p", "Cvtomf710p", "Export710p",
+    "Implib710p", "Masm710p", "Utc1310p_C", "Utc1310p_CPP", "Utc1310p_C_Std",
+    "Utc1310p_CPP_Std", "Utc1310p_LTCG_C", "Utc1310p_LTCG_CPP",
+    "Utc1310p_POGO_I_C", "Utc1310p_POGO_I_CPP", "Utc1310p_POGO_O_C",
+    "Utc1310p_POGO_O_CPP", "Linker624", "Cvtomf624", "Export624", "Implib624",
+    "Linker710", "Cvtomf710", "Export710", "Implib710", "Cvtres710",
+    "Utc1310_C", "Utc1310_CPP", "Utc1310_C_Std", "Utc1310_CPP_Std",
+    "Utc1310_LTCG_C", "Utc1310_LTCG_CPP", "Utc1310_POGO_I_C",
+    "Utc1310_POGO_I_CPP", "Utc1310_POGO_O_C", "Utc1310_POGO_O_CPP",
+    "AliasObj710", "AliasObj710p", "Cvtpgd1310", "Cvtpgd1310p", "Utc1400_C",
+    "Utc1400_CPP", "Utc1400_C_Std", "Utc1400_CPP_Std", "Utc1400_LTCG_C",
+    "Utc1400_LTCG_CPP", "Utc1400_POGO_I_C", "Utc1400_POGO_I_CPP",
+    "Utc1400_POGO_O_C", "Utc1400_POGO_O_CPP", "Cvtpgd1400", "Linker800",
+    "Cvtomf800", "Export800", "Implib800", "Cvtres800", "Masm800",
+    "AliasObj800", "PhoenixPrerelease", "Utc1400_CVTCIL_C",
+    "Utc1400_CVTCIL_CPP", "Utc1400_LTCG_MSIL", "Utc1500_C", "Utc1500_CPP",
+    "Utc1500_C_Std", "Utc1500_CPP_Std", "Utc1500_CVTCIL_C",
+    "Utc1500_CVTCIL_CPP", "Utc1500_LTCG_C", "Utc1500_LTCG_CPP",
+    "Utc1500_LTCG_MSIL", "Utc1500_POGO_I_C", "Utc1500_POGO_I_CPP",
+    "Utc1500_POGO_O_C", "Utc1500_POGO_O_CPP", "Cvtpgd1500", "Linker900",
+    "Export900", "Implib900", "Cvtres900", "Masm900", "AliasObj900", "Resource",
+    "AliasObj1000", "Cvtpgd1600", "Cvtres1000", "Export1000", "Implib1000",
+    "Linker1000", "Masm1000", "Phx1600_C", "Phx1600_CPP", "Phx1600_CVTCIL_C",
+    "Phx1600_CVTCIL_CPP", "Phx1600_LTCG_C", "Phx1600_LTCG_CPP",
+    "Phx1600_LTCG_MSIL", "Phx1600_POGO_I_C", "Phx1600_POGO_I_CPP",
+    "Phx1600_POGO_O_C", "Phx1600_POGO_O_CPP", "Utc1600_C", "Utc1600_CPP",
+    "Utc1600_CVTCIL_C", "Utc1600_CVTCIL_CPP", "Utc1600_LTCG_C",
+    "Utc1600_LTCG_CPP", "Utc1600_LTCG_MSIL", "Utc1600_POGO_I_C",
+    "Utc1600_POGO_I_CPP", "Utc1600_POGO_O_C", "Utc1600_POGO_O_CPP",
+    "AliasObj1010", "Cvtpgd1610", "Cvtres1010", "Export1010", "Implib1010",
+    "Linker1010", "Masm1010", "Utc1610_C", "Utc1610_CPP", "Utc1610_CVTCIL_C",
+    "Utc1610_CVTCIL_CPP", "Utc1610_LTCG_C", "Utc1610_LTCG_CPP",
+    "Utc1610_LTCG_MSIL", "Utc1610_POGO_I_C", "Utc1610_POGO_I_CPP",
+    "Utc1610_POGO_O_C", "Utc1610_POGO_O_CPP", "AliasObj1100", "Cvtpgd1700",
+    "Cvtres1100", "Export1100", "Implib1100", "Linker1100", "Masm1100",
+    "Utc1700_C", "Utc1700_CPP", "Utc1700_CVTCIL_C", "Utc1700_CVTCIL_CPP",
+    "Utc1700_LTCG_C", "Utc1700_LTCG_CPP", "Utc1700_LTCG_MSIL",
+    "Utc1700_POGO_I_C", "Utc1700_POGO_I_CPP", "Utc1700_POGO_O_C",
+    "Utc1700_POGO_O_CPP", "AliasObj1200", "Cvtpgd1800", "Cvtres1200",
+    "Export1200", "Implib1200", "Linker1200", "Masm1200", "Utc1800_C",
+    "Utc1800_CPP", "Utc1800_CVTCIL_C", "Utc1800_CVTCIL_CPP", "Utc1800_LTCG_C",
+    "Utc1800_LTCG_CPP", "Utc1800_LTCG_MSIL", "Utc1800_POGO_I_C",
+    "Utc1800_POGO_I_CPP", "Utc1800_POGO_O_C", "Utc1800_POGO_O_CPP",
+    "AliasObj1210", "Cvtpgd1810", "Cvtres1210", "Export1210", "Implib1210",
+    "Linker1210", "Masm1210", "Utc1810_C", "Utc1810_CPP", "Utc1810_CVTCIL_C",
+    "Utc1810_CVTCIL_CPP", "Utc1810_LTCG_C", "Utc1810_LTCG_CPP",
+    "Utc1810_LTCG_MSIL", "Utc1810_POGO_I_C", "Utc1810_POGO_I_CPP",
+    "Utc1810_POGO_O_C", "Utc1810_POGO_O_CPP", "AliasObj1400", "Cvtpgd1900",
+    "Cvtres1400", "Export1400", "Implib1400", "Linker1400", "Masm1400",
+    "Utc1900_C", "Utc1900_CPP", "Utc1900_CVTCIL_C", "Utc1900_CVTCIL_CPP",
+    "Utc1900_LTCG_C", "Utc1900_LTCG_CPP", "Utc1900_LTCG_MSIL",
+    "Utc1900_POGO_I_C", "Utc1900_POGO_I_CPP", "Utc1900_POGO_O_C",
+    "Utc1900_POGO_O_CPP",
+];
+
+/// One record of the Rich header: how many objects a build of a tool
+/// contributed to the image
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RichEntry {
+    pub product_id: u16,
+    pub build: u16,
+    pub count: u32,
+}
+
+impl RichEntry {
+    /// The `@comp.id` value, product id and build packed together
+    pub fn comp_id(&self) -> u32 {
+        (self.product_id as u32) << 16 | self.build as u32
+    }
+
+    /// Returns the name of the tool, such as `Utc1900_CPP` for the C++
+    /// compiler of Visual Studio 2015 and later, if the product id is known
+    pub fn product_name(&self) -> Option<&'static str> {
+        PRODUCT_NAMES.get(self.product_id as usize).copied()
+    }
+
+    /// Returns the Visual Studio release the tool shipped with, if it is
+    /// known. Releases since 2015 share their product ids and are told
+    /// apart by the build number.
+    pub fn visual_studio(&self) -> Option<&'static str> {
+        let release = match self.product_id {
+            0x0002..=0x0003 | 0x0006..=0x0008 | 0x0010..=0x0011
+                | 0x0013..=0x0014 => "Visual Studio 97 (5.0)",
+            0x0004..=0x0005 | 0x0009..=0x000d | 0x0015..=0x0018
+                | 0x0020..=0x0024 | 0x002f..=0x0035 => "Visual Studio 6.0",
+            0x0019..=0x001d | 0x0027 | 0x002b..=0x002c | 0x0039..=0x003b
+                | 0x003d | 0x003f..=0x0045 => "Visual Studio .NET 2002 (7.0)",
+            0x0046..=0x0055 | 0x005a..=0x006c
+                => "Visual Studio .NET 2003 (7.1)",
+            0x006d..=0x0082 => "Visual Studio 2005 (8.0)",
+            0x0083..=0x0097 => "Visual Studio 2008 (9.0)",
+            0x0098..=0x00b4 => "Visual Studio 2010 (10.0)",
+            0x00b5..=0x00c6 => "Visual Studio 2010 SP1 (10.10)",
+            0x00c7..=0x00d8 => "Visual Studio 2012 (11.0)",
+            0x00d9..=0x00ea => "Visual Studio 2013 (12.0)",
+            0x00eb..=0x00fc => "Visual Studio 2013 (12.10)",
+            0x00fd..=0x010e => match self.build {
+                0..=25016 => "Visual Studio 2015 (14.0)",
+                25017..=27507 => "Visual Studio 2017 (14.1)",
+                27508..=30704 => "Visual Studio 2019 (14.2)",
+                _ => "Visual Studio 2022 (14.3)",
+            },
+            _ => return None,
+        };
+        Some(release)
+    }
+}
+
+/// The Rich header the Microsoft linker writes between the MS-DOS stub and
+/// the PE header. It is masked with a key that doubles as its checksum.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RichHeader {
+    /// The file offset of the `DanS` marker
+    pub offset: usize,
+    /// The key the header is masked with, stored after `Rich`
+    pub key: u32,
+    pub entries: Vec<RichEntry>,
+    /// The checksum recomputed over the MS-DOS header and the entries
+    pub checksum: u32,
+    /// The unmasked header, from `DanS` up to `Rich`
+    pub data: Vec<u8>,
+}
+
+impl RichHeader {
+    /// Looks for a Rich header in `bytes`, the start of the file up to the
+    /// PE header
+    pub fn from_bytes(bytes: &[u8]) -> Result<Option<Self>> {
+        // The header is made of dwords and follows the MS-DOS header
+        let Some(rich) = (0x40..bytes.len().saturating_sub(7))
+            .step_by(4)
+            .find(|&offset| &bytes[offset..offset + 4] == RICH) else {
+            return Ok(None)
+        };
+        let key = read_u32(bytes, rich + 4);
+
+        let offset = (0x40..rich).step_by(4).rev()
+            .find(|&offset| read_u32(bytes, offset) ^ key == DANS)
+            .ok_or(PeError::InvalidRichHeader)?;
+        // `DanS` is followed by three masked zeroes
+        let entries_offset = offset + 16;
+        if entries_offset > rich || (rich - entries_offset) % 8 != 0
+                || (offset + 4..entries_offset)
+                    .step_by(4)
+                    .any(|padding| read_u32(bytes, padding) != key) {
+            return Err(PeError::InvalidRichHeader);
+        }
+
+        let entries: Vec<RichEntry> = (entries_offset..rich).step_by(8)
+            .map(|entry| {
+                let comp_id = read_u32(bytes, entry) ^ key;
+                RichEntry {
+                    product_id: (comp_id >> 16) as u16,
+                    build: comp_id as u16,
+                    count: read_u32(bytes, entry + 4) ^ key,
+                }
+            })
+            .collect();
+
+        // Every byte before the header, rotated by its offset, then every
+        // entry, rotated by its count
+        let mut checksum = offset as u32;
+        for (index, &byte) in bytes[..offset].iter().enumerate() {
+            if (E_LFANEW_OFFSET..E_LFANEW_OFFSET + 4).contains(&index) {
+                continue;
+            }
+            checksum = checksum
+                .wrapping_add((byte as u32).rotate_left(index as u32));
+        }
+        for entry in &entries {
+            checksum = checksum
+                .wrapping_add(entry.comp_id().rotate_left(entry.count));
+        }
+
+        let data = bytes[offset..rich].iter().enumerate()
+            .map(|(index, byte)| byte ^ key.to_le_bytes()[index % 4])
+            .collect();
+
+        Ok(Some(Self { offset, key, entries, checksum, data }))
+    }
+
+    /// Returns whether the stored key is the recomputed checksum, which
+    /// tools that patch the header usually fail to keep
+    pub fn checksum_valid(&self) -> bool {
+        self.key == self.checksum
+    }
+
+    /// Returns the Rich header hash of pefile: the MD5 in hex of the bytes
+    /// from offset 0x80 up to `Rich`, unmasked with the key. pefile only
+    /// reads a header whose `DanS` is at 0x80, so `None` is returned for any
+    /// other header, where pefile returns an empty string.
+    pub fn hash(&self) -> Option<String> {
+        const PEFILE_OFFSET: usize = 0x80;
+        if self.offset != PEFILE_OFFSET {
+            return None
+        }
+        // `data` starts at `DanS` and is unmasked from there, so it is the
+        // range pefile hashes
+        Some(Md5::digest(&self.data).iter()
+            .map(|b| format!("{:02x}", b))
+            .collect())
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]
+    ])
+}
@@ -1,63 +1,125 @@
-use std::{fs};
-
 pub mod headers;
 pub mod parsing;
 pub mod error;
+pub mod directories;
+pub mod resources;
+pub mod symbols;
+pub mod crypto;
+pub mod authenticode;
+pub mod checksum;
+
+use std::{borrow::Cow, ops::Range};
 
 use crate::{
+    authenticode::{
+        page_hashes::PageHashes,
+        pkcs7::SignedData,
+        verify::{self, TrustStore, Verification},
+    },
+    crypto::DigestAlgorithm,
+    directories::{
+        certificates::{self, Certificate},
+        debug::{self, CodeView, DebugEntry, DebugPayload, PdbInfo},
+        delay_imports::{self, DelayImportedModule},
+        exports::ExportTable,
+        imports::{self, ImportedModule},
+        load_config::LoadConfig,
+        relocations::{self, RelocationBlock},
+        tls::Tls,
+    },
     headers::{
         dos::DosHeader,
         pe::{
             file_header::FileHeader,
-            opt_header::{WindowsSpecific, OptionalHeader}
+            opt_header::OptionalHeader,
+            section::{self, SectionHeader},
         },
+        rich::RichHeader,
+    },
+    parsing::{take_bytes, utf16_to_string},
+    resources::{
+        accelerators::AcceleratorTable,
+        dialogs::Dialog,
+        icons::IconGroup,
+        manifest::Manifest,
+        menus::Menu,
+        strings::{self, LocalizedStrings},
+        version::VersionInfo,
+        ResourceTree,
     },
+    symbols::SymbolKeys,
 };
-use error::Result;
+use error::{Result, PeError};
 
 /// Object representing a Portable Executable file(or PE) as described by
 /// Microsoft's documentation
 pub struct PE<'pe> {
     /// A slice of bytes representing the content in the file
+    data: &'pe [u8],
+    /// A slice of bytes representing the content in the file that follows
+    /// the section table
     bytes: &'pe [u8],
     /// MS-DOS Header
     pub dos_header: DosHeader,
-    /// MS-DOS Stub -> Not parsed
+    /// MS-DOS Stub -> Not parsed, but see `rich_header` for the Rich header
+    /// the linker stores here
     pub dos_stub: Vec<u8>,
     /// PE File Header
     pub file_header: FileHeader,
     /// PE Optional Header
     pub opt_header: OptionalHeader,
+    /// Section table
+    pub sections: Vec<SectionHeader>,
 }
 
 impl<'pe> PE<'pe> {
     /// Attempts to construct a PE from the given `bytes` slice
     pub fn from_bytes(bytes: &'pe [u8]) -> Result<Self> {
-        println!("{}", bytes.len());
+        let data = bytes;
         // Parse the MS-DOS header
         let (dos_header, bytes) = DosHeader::from_bytes(bytes)?;
 
         // Initialize the file header offset
         let file_header_offset = usize::try_from(dos_header.e_lfanew)?;
-        println!("{}", bytes.len());
-        println!("{}", file_header_offset);
 
         // Consume the MS-DOS stub (or everything until the PE header)
-        let (dos_stub, bytes) = bytes
-            .split_at(file_header_offset - DosHeader::len());
+        let stub_len = file_header_offset.checked_sub(DosHeader::len())
+            .ok_or(PeError::InvalidPeOffset(dos_header.e_lfanew))?;
+        let (dos_stub, bytes) = take_bytes(bytes, stub_len)
+            .map_err(|_| PeError::InvalidPeOffset(dos_header.e_lfanew))?;
 
         // Read the PE File header
         let (file_header, bytes) = FileHeader::from_bytes(bytes)?;
 
-        // Read the PE optional header
-        let (opt_header, bytes) = OptionalHeader::from_bytes(bytes)?;
+        // Read the PE optional header. The section table starts right after
+        // the declared size of the optional header, regardless of how many
+        // bytes we actually parsed from it.
+        let (opt_bytes, bytes) = take_bytes(bytes,
+            file_header.size_of_optional_header as usize)?;
+        let (opt_header, _) = OptionalHeader::from_bytes(opt_bytes)?;
+
+        // Read the section table
+        let string_table = section::string_table(data,
+            file_header.pointer_to_symbol_table,
+            file_header.number_of_symbols);
+        let mut sections =
+            Vec::with_capacity(file_header.number_of_sections as usize);
+        let mut bytes = bytes;
+        for _ in 0..file_header.number_of_sections {
+            let (section, rest) =
+                SectionHeader::from_bytes(bytes, string_table)?;
+            sections.push(section);
+            bytes = rest;
+        }
 
         Ok(Self {
+            data,
             bytes,
             dos_header,
             dos_stub: dos_stub.to_vec(),
             file_header,
-            opt_header
+            opt_header,
+            sections,
         })
     }
 
@@ -65,17 +127,437 @@ impl<'pe> PE<'pe> {
     pub fn remaining_bytes(&self) -> usize {
         self.bytes.len()
     }
+
+    /// Returns the raw content of the file
+    pub fn data(&self) -> &'pe [u8] {
+        self.data
+    }
+
+    /// Decodes the Rich header hidden between the MS-DOS stub and the PE
+    /// header, if the linker wrote one
+    pub fn rich_header(&self) -> Result<Option<RichHeader>> {
+        RichHeader::from_bytes(&self.data[..self.dos_header.e_lfanew as usize])
+    }
+
+    /// Returns the first section with the given `name`
+    pub fn section_by_name(&self, name: &str) -> Option<&SectionHeader> {
+        self.sections.iter().find(|section| section.name == name)
+    }
+
+    /// Returns the DLLs and functions listed in the import directory
+    pub fn imports(&self) -> Result<Vec<ImportedModule>> {
+        imports::parse_imports(self)
+    }
+
+    /// Returns the imphash of the image, as computed by pefile, or `None`
+    /// if it imports nothing
+    pub fn imphash(&self) -> Result<Option<String>> {
+        imports::imphash(self)
+    }
+
+    /// Returns the DLLs and functions listed in the delay import directory
+    pub fn delay_imports(&self) -> Result<Vec<DelayImportedModule>> {
+        delay_imports::parse_delay_imports(self)
+    }
+
+    /// Returns the symbols listed in the export directory
+    pub fn exports(&self) -> Result<ExportTable> {
+        ExportTable::from_pe(self)
+    }
+
+    /// Returns the blocks of the base relocation directory
+    pub fn relocations(&self) -> Result<Vec<RelocationBlock>> {
+        relocations::parse_relocations(self)
+    }
+
+    /// Maps the image in memory and rebases it to `new_base`. The returned
+    /// buffer is indexed by RVA.
+    pub fn rebased_image(&self, new_base: u64) -> Result<Vec<u8>> {
+        let mut image = relocations::map_image(self)?;
+        relocations::apply_relocations(self, &mut image, new_base)?;
+        Ok(image)
+    }
+
+    /// Returns the TLS directory and its callbacks, if the image has one
+    pub fn tls(&self) -> Result<Option<Tls>> {
+        Tls::from_pe(self)
+    }
+
+    /// Returns the load configuration directory, if the image has one
+    pub fn load_config(&self) -> Result<Option<LoadConfig>> {
+        LoadConfig::from_pe(self)
+    }
+
+    /// Returns the entries of the attribute certificate table, which hold the
+    /// Authenticode signatures of the image
+    pub fn certificates(&self) -> Result<Vec<Certificate<'pe>>> {
+        certificates::parse_certificates(self)
+    }
+
+    /// Parses the Authenticode signatures of the image. Nested signatures
+    /// hang off the signers of the first one.
+    pub fn signatures(&self) -> Result<Vec<SignedData>> {
+        self.certificates()?
+            .iter()
+            .filter_map(|certificate| certificate.signed_data())
+            .map(SignedData::from_der)
+            .collect()
+    }
+
+    /// Returns the file ranges covered by the Authenticode digest, in the
+    /// order they are hashed
+    pub fn authenticode_ranges(&self) -> Result<Vec<Range<usize>>> {
+        authenticode::authenticode_ranges(self)
+    }
+
+    /// Computes the Authenticode digest of the image with `algorithm`
+    pub fn authenticode_digest(&self, algorithm: DigestAlgorithm)
+            -> Result<Vec<u8>> {
+        authenticode::authenticode_digest(self, algorithm)
+    }
+
+    /// Returns the signed page hashes, from the first signature that has
+    /// them, nested signatures included
+    pub fn page_hashes(&self) -> Result<Option<PageHashes>> {
+        for signature in self.signatures()? {
+            for signed_data in signature.all_signatures() {
+                let Some(indirect_data) = &signed_data.indirect_data else {
+                    continue
+                };
+                if let Some(page_hashes) = indirect_data.page_hashes()? {
+                    return Ok(Some(page_hashes));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Recomputes the hash of every 4K page of the image, the way signed
+    /// page hashes are made
+    pub fn compute_page_hashes(&self, algorithm: DigestAlgorithm)
+            -> PageHashes {
+        PageHashes::compute(self, algorithm)
+    }
+
+    /// Checks every Authenticode signature: the image digest, the signer's
+    /// signature and its chain up to a root of `store`, offline
+    pub fn verify_signatures(&self, store: &TrustStore)
+            -> Result<Verification> {
+        verify::verify_signatures(self, store)
+    }
+
+    /// Computes the checksum of the image with the algorithm of
+    /// `CheckSumMappedFile`
+    pub fn compute_checksum(&self) -> u32 {
+        checksum::compute_checksum(self)
+    }
+
+    /// Returns whether the `checksum` field of the optional header matches
+    /// the computed one. Most user mode images leave it zero, which does
+    /// not match.
+    pub fn checksum_valid(&self) -> bool {
+        self.opt_header.win_fields.checksum() == self.compute_checksum()
+    }
+
+    /// Returns the entries of the debug directory
+    pub fn debug_entries(&self) -> Result<Vec<DebugEntry>> {
+        debug::parse_debug_entries(self)
+    }
+
+    /// Returns the GUID, age and path of the PDB matching the image, taken
+    /// from the first RSDS CodeView entry of the debug directory
+    pub fn pdb_info(&self) -> Result<Option<PdbInfo>> {
+        let info = self.debug_entries()?
+            .into_iter()
+            .find_map(|entry| match entry.payload {
+                Some(DebugPayload::CodeView(CodeView::Rsds {
+                    guid, age, path
+                })) => Some(PdbInfo { guid, age, path }),
+                _ => None,
+            });
+
+        Ok(info)
+    }
+
+    /// Walks the resource directory
+    pub fn resources(&self) -> Result<ResourceTree> {
+        ResourceTree::from_pe(self)
+    }
+
+    /// Collects the icon and cursor groups, ready to be written out as
+    /// `.ico` and `.cur` files
+    pub fn icon_groups(&self) -> Result<Vec<IconGroup>> {
+        IconGroup::from_pe(self)
+    }
+
+    /// Decodes the `RT_STRING` resources into a map of strings per language
+    pub fn string_tables(&self) -> Result<Vec<LocalizedStrings>> {
+        strings::string_tables(self)
+    }
+
+    /// Decodes the `RT_MESSAGETABLE` resources into a map of messages per
+    /// language
+    pub fn message_tables(&self) -> Result<Vec<LocalizedStrings>> {
+        strings::message_tables(self)
+    }
+
+    /// Decodes the `RT_ACCELERATOR` resources
+    pub fn accelerators(&self) -> Result<Vec<AcceleratorTable>> {
+        AcceleratorTable::from_pe(self)
+    }
+
+    /// Decodes the `RT_DIALOG` resources, in both the standard and the
+    /// extended format
+    pub fn dialogs(&self) -> Result<Vec<Dialog>> {
+        Dialog::from_pe(self)
+    }
+
+    /// Decodes the `RT_MENU` resources, in both the standard and the extended
+    /// format
+    pub fn menus(&self) -> Result<Vec<Menu>> {
+        Menu::from_pe(self)
+    }
+
+    /// Decodes the application manifests embedded as `RT_MANIFEST` resources
+    /// under IDs 1, 2 and 3
+    pub fn manifests(&self) -> Result<Vec<Manifest>> {
+        Manifest::from_pe(self)
+    }
+
+    /// Decodes the `VS_VERSIONINFO` structure of the `RT_VERSION` resource,
+    /// if the image has one
+    pub fn version_info(&self) -> Result<Option<VersionInfo>> {
+        VersionInfo::from_pe(self)
+    }
+
+    /// Returns the keys of the image and of its PDB in a symbol store
+    pub fn symbol_keys(&self) -> Result<SymbolKeys> {
+        SymbolKeys::from_pe(self)
+    }
+
+    /// Returns the section that `rva` is mapped into, if any
+    pub fn section_for_rva(&self, rva: u32) -> Option<&SectionHeader> {
+        self.sections.iter().find(|section| {
+            let (start, end) = self.section_range(section);
+            (rva as u64) >= start && (rva as u64) < end
+        })
+    }
+
+    /// Translates `rva` into an offset in the file. RVAs that fall in the
+    /// zero filled tail of a section have no file backing and are reported
+    /// as unmapped.
+    pub fn rva_to_offset(&self, rva: u32) -> Result<usize> {
+        let mapping = self.map_rva(rva)?;
+        if mapping.backed == 0 {
+            return Err(PeError::UnmappedRva(rva));
+        }
+        Ok(mapping.offset)
+    }
+
+    /// Translates a virtual address into an RVA using the preferred image
+    /// base
+    pub fn va_to_rva(&self, va: u64) -> Result<u32> {
+        let win_fields = &self.opt_header.win_fields;
+        let rva = va.checked_sub(win_fields.image_base())
+            .filter(|rva| *rva < win_fields.size_of_image() as u64)
+            .ok_or(PeError::InvalidVa(va))?;
+
+        Ok(rva as u32)
+    }
+
+    /// Reads `len` bytes starting at `rva` as they would appear in memory
+    /// once the image is loaded. Reads that run past the raw data of a
+    /// section but stay within its virtual size are zero filled. Reads may not
+    /// span multiple sections.
+    pub fn read_at_rva(&self, rva: u32, len: usize) -> Result<Cow<'pe, [u8]>> {
+        let mapping = self.map_rva(rva)?;
+        if len > mapping.mapped {
+            let end = (rva as u64).saturating_add(len as u64);
+            return Err(PeError::UnmappedRva(end.min(u32::MAX as u64) as u32));
+        }
+
+        let backed = len.min(mapping.backed);
+        let data = &self.data[mapping.offset..mapping.offset + backed];
+        if backed == len {
+            Ok(Cow::Borrowed(data))
+        } else {
+            let mut data = data.to_vec();
+            data.resize(len, 0);
+            Ok(Cow::Owned(data))
+        }
+    }
+
+    /// Reads a null terminated string starting at `rva`
+    pub fn read_cstr_at_rva(&self, rva: u32) -> Result<String> {
+        let mapping = self.map_rva(rva)?;
+        let data = &self.data[mapping.offset..mapping.offset + mapping.backed];
+        let len = match data.iter().position(|&b| b == 0) {
+            Some(len) => len,
+            // The zero filled tail of the section terminates the string
+            None if mapping.mapped > mapping.backed => data.len(),
+            None => return Err(PeError::UnterminatedString(rva)),
+        };
+
+        Ok(String::from_utf8_lossy(&data[..len]).into_owned())
+    }
+
+    /// Reads `len` UTF-16 code units starting at `rva`
+    pub fn read_utf16_at_rva(&self, rva: u32, len: usize) -> Result<String> {
+        let size = len.checked_mul(2).ok_or(PeError::UnmappedRva(rva))?;
+        let data = self.read_at_rva(rva, size)?;
+
+        Ok(utf16_to_string(&data))
+    }
+
+    /// Section alignment as used by the loader. Images with an alignment
+    /// below the page size are mapped using their file alignment.
+    fn effective_section_alignment(&self) -> u32 {
+        let win_fields = &self.opt_header.win_fields;
+        if win_fields.section_alignment() < 0x1000 {
+            win_fields.file_alignment()
+        } else {
+            win_fields.section_alignment()
+        }
+    }
+
+    /// Returns the memory range `[start, end)` a section occupies once loaded
+    fn section_range(&self, section: &SectionHeader) -> (u64, u64) {
+        let alignment = self.effective_section_alignment() as u64;
+        let start = align_down(section.virtual_address as u64, alignment);
+        let size = match section.virtual_size {
+            0 => section.size_of_raw_data,
+            size => size,
+        };
+        let end = align_up(section.virtual_address as u64 + size as u64,
+            alignment);
+        (start, end)
+    }
+
+    /// Locates `rva` in the file
+    fn map_rva(&self, rva: u32) -> Result<RvaMapping> {
+        let file_len = self.data.len();
+
+        if let Some(section) = self.section_for_rva(rva) {
+            let (start, end) = self.section_range(section);
+            let delta = rva as u64 - start;
+            let mapped = (end - rva as u64) as usize;
+
+            // The loader rounds the raw data pointer down to 512 bytes
+            // whenever the file alignment allows it
+            let raw_start = if self.opt_header.win_fields.file_alignment()
+                    >= 0x200 {
+                align_down(section.pointer_to_raw_data as u64, 0x200)
+            } else {
+                section.pointer_to_raw_data as u64
+            };
+            let raw_size = (section.size_of_raw_data as u64)
+                .min(end - start)
+                .min((file_len as u64).saturating_sub(raw_start));
+
+            let offset = raw_start + delta;
+            let backed = raw_size.saturating_sub(delta) as usize;
+            return Ok(RvaMapping {
+                offset: offset.min(file_len as u64) as usize,
+                backed,
+                mapped,
+            });
+        }
+
+        // Anything below the size of the headers is mapped one to one
+        let size_of_headers = self.opt_header.win_fields.size_of_headers();
+        if rva < size_of_headers && (rva as usize) < file_len {
+            let end = (size_of_headers as usize).min(file_len);
+            let available = end - rva as usize;
+            return Ok(RvaMapping {
+                offset: rva as usize,
+                backed: available,
+                mapped: available,
+            });
+        }
+
+        Err(PeError::UnmappedRva(rva))
+    }
+}
+
+/// Describes where an RVA lives in the file
+struct RvaMapping {
+    /// File offset of the RVA
+    offset: usize,
+    /// Number of bytes starting at `offset` that are present in the file
+    backed: usize,
+    /// Number of bytes starting at the RVA that are mapped in memory. Bytes
+    /// past `backed` are zero filled.
+    mapped: usize,
+}
+
+fn align_down(value: u64, alignment: u64) -> u64 {
+    if alignment == 0 {
+        return value;
+    }
+    value - value % alignment
+}
+
+fn align_up(value: u64, alignment: u64) -> u64 {
+    if alignment == 0 {
+        return value;
+    }
+    value.div_ceil(alignment) * alignment
 }
 
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::time::{Duration, Instant};
+    use crate::authenticode::{
+        der::{self, Tlv},
+        pkcs7::{SignedData, NESTED_SIGNATURE, SPC_PE_IMAGE_DATA},
+        verify::{verify_signature, VerificationFailure, CODE_SIGNING,
+            TIME_STAMPING},
+        x509::{Extension, X509Certificate, EXTENDED_KEY_USAGE},
+    };
+    use crate::crypto::{md5::Md5, sha1::Sha1, sha256::Sha256};
+    use crate::directories::{
+        certificates::{CertificateRevision, CertificateType},
+        debug::{DebugDirectory, DebugType},
+        imports::ImportName,
+        load_config::LoadConfigLevel,
+        ordinals::ordinal_name,
+        relocations::{apply_relocation, Relocation, RelocationType},
+        tls::TlsCallback,
+    };
+    use crate::resources::{
+        accelerators::{AcceleratorFlag, AcceleratorTable},
+        dialogs::{DialogFont, DialogTemplate, NameOrOrdinal},
+        icons::GroupKind,
+        manifest::{ExecutionLevel, Manifest, ManifestKind, SupportedOs},
+        menus::{MenuItem, MenuTemplate},
+        strings::{parse_message_table, parse_string_block},
+        version::{FileFlag, FileOs, FileType},
+        ResourceId, ResourceType,
+    };
+    use crate::headers::pe::{
+        data_directory::{DataDirectories, DataDirectory, DataDirectoryType},
+        opt_header::WindowsSpecific,
+        section::{SectionFlag, SectionHeader},
+    };
+    use crate::parsing::take_u16;
+    use std::{fs, time::Instant};
 
     /// `MZ` Magic used to identify a PE in MS-DOS Header
     const MZ: u16 = 0x5a4d;
 
+    /// Every sample in the testdata folder
+    const TESTDATA: [&str; 8] = [
+        "testdata/32bit/kernel32.dll",
+        "testdata/32bit/notepad.exe",
+        "testdata/32bit/ntdll.dll",
+        "testdata/32bit/user32.dll",
+        "testdata/64bit/kernel32.dll",
+        "testdata/64bit/notepad.exe",
+        "testdata/64bit/ntdll.dll",
+        "testdata/64bit/user32.dll",
+    ];
+
     #[test]
     fn it_works() {
         let result = 2 + 2;
@@ -92,11 +574,1607 @@ mod tests {
         assert_eq!(MZ, new.dos_header.e_magic);
         assert_eq!(0xf8, new.dos_header.e_lfanew);
         assert_eq!(0x4550, new.file_header.magic);
-        let image_base = match new.opt_header.win_fields {
+        match new.opt_header.win_fields {
             WindowsSpecific::PE64(pe64) => println!("{}", pe64.image_base),
             WindowsSpecific::PE32(pe32) => println!("{}", pe32.image_base)
         };
         let duration = start.elapsed();
         println!("Time elapsed in expensive_function() is: {:?}", duration);
     }
+
+    #[test]
+    fn parse_section_table() {
+        let data = fs::read("testdata/64bit/notepad.exe").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+        let names: Vec<&str> = pe.sections.iter()
+            .map(|section| section.name.as_str())
+            .collect();
+        assert_eq!(names, [".text", ".rdata", ".data", ".pdata", ".didat",
+            ".rsrc", ".reloc"]);
+
+        let text = pe.section_by_name(".text").unwrap();
+        assert_eq!(text.virtual_address, 0x1000);
+        assert_eq!(text.pointer_to_raw_data, 0x400);
+        assert!(text.flags().contains(&SectionFlag::MemExecute));
+
+        for path in TESTDATA {
+            let data = fs::read(path).unwrap();
+            let pe = PE::from_bytes(&data).unwrap();
+            assert_eq!(pe.sections.len(),
+                pe.file_header.number_of_sections as usize);
+        }
+
+        // Long names point into the string table, names that do not are
+        // kept verbatim instead of failing the image
+        let mut table = 22u32.to_le_bytes().to_vec();
+        table.extend(b".text$long_name\0\0\0");
+        for (raw_name, name) in [(&b"/4\0\0\0\0\0\0"[..], ".text$long_name"),
+                (b"//AAAAAE", ".text$long_name"), (b"/xyz\0\0\0\0", "/xyz"),
+                (b"/999\0\0\0\0", "/999"), (b"//a!\0\0\0\0", "//a!")] {
+            let mut header = raw_name.to_vec();
+            header.resize(SectionHeader::len(), 0);
+            let (section, _) = SectionHeader::from_bytes(&header, Some(&table))
+                .unwrap();
+            assert_eq!(section.name, name);
+            assert_eq!(section.raw_name, raw_name);
+        }
+
+        let text = data.windows(8).position(|name| name == b".text\0\0\0")
+            .unwrap();
+        let mut patched = data.clone();
+        patched[text..text + 8].copy_from_slice(b"/xyz\0\0\0\0");
+        let pe = PE::from_bytes(&patched).unwrap();
+        assert_eq!(pe.sections[0].name, "/xyz");
+    }
+
+    #[test]
+    fn parse_data_directories() {
+        let data = fs::read("testdata/64bit/notepad.exe").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+        let dirs = &pe.opt_header.data_directories;
+        assert_eq!(dirs.len(), 16);
+        assert_eq!(dirs.import(),
+            Some(&DataDirectory { virtual_address: 0x2f908, size: 0x230 }));
+        assert_eq!(dirs.tls().map(|dir| dir.virtual_address), Some(0x28828));
+        assert!(dirs.export().is_none());
+        assert!(dirs.security().is_none());
+
+        // Fewer directories than documented
+        let bytes = [1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 4, 0, 0, 0];
+        let (dirs, rest) = DataDirectories::from_bytes(&bytes, 2).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(dirs.import().map(|dir| dir.size), Some(4));
+        assert!(dirs.get(DataDirectoryType::Resource).is_none());
+
+        // More directories than fit in the optional header
+        let bytes = [0u8; 17 * 8 + 4];
+        let (dirs, rest) =
+            DataDirectories::from_bytes(&bytes, 0xffff_ffff).unwrap();
+        assert_eq!(dirs.len(), 17);
+        assert_eq!(rest.len(), 4);
+    }
+
+    #[test]
+    fn translate_rvas() {
+        let data = fs::read("testdata/64bit/notepad.exe").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+
+        // Headers are mapped one to one
+        assert_eq!(pe.rva_to_offset(0).unwrap(), 0);
+        assert_eq!(&pe.read_at_rva(0, 2).unwrap()[..], b"MZ");
+
+        // .rdata starts at RVA 0x28000 and file offset 0x26c00
+        assert_eq!(pe.rva_to_offset(0x28010).unwrap(), 0x26c10);
+        assert_eq!(pe.section_for_rva(0x28010).unwrap().name, ".rdata");
+        assert_eq!(pe.va_to_rva(0x1_4002_8010).unwrap(), 0x28010);
+        assert!(pe.va_to_rva(0x1000).is_err());
+
+        // .data has a virtual size of 0x2808 but only 0xe00 bytes on disk
+        let data_section = pe.section_by_name(".data").unwrap();
+        let tail = data_section.virtual_address + 0x1000;
+        assert!(matches!(pe.rva_to_offset(tail),
+            Err(PeError::UnmappedRva(_))));
+        let zeroes = pe.read_at_rva(tail, 0x10).unwrap();
+        assert!(matches!(zeroes, Cow::Owned(_)));
+        assert!(zeroes.iter().all(|&b| b == 0));
+
+        // Past the end of the image
+        assert!(pe.read_at_rva(0x10_0000, 1).is_err());
+        assert!(pe.read_at_rva(0x39000, 0x2000).is_err());
+    }
+
+    #[test]
+    fn parse_imports() {
+        let data = fs::read("testdata/64bit/notepad.exe").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+        let imports = pe.imports().unwrap();
+        assert_eq!(imports.len(), 27);
+
+        let kernel32 = &imports[0];
+        assert_eq!(kernel32.name, "KERNEL32.dll");
+        assert_eq!(kernel32.functions.len(), 85);
+        assert!(matches!(&kernel32.functions[0].name,
+            ImportName::Name { name, .. } if name == "GetProcAddress"));
+        assert_eq!(kernel32.functions[0].iat_rva, 0x28920);
+        assert_eq!(kernel32.functions[1].iat_rva, 0x28928);
+
+        let comctl32 = imports.iter()
+            .find(|module| module.name == "COMCTL32.dll")
+            .unwrap();
+        assert_eq!(comctl32.functions[1].name, ImportName::Ordinal(345));
+
+        // 32-bit thunks
+        let data = fs::read("testdata/32bit/notepad.exe").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+        let imports = pe.imports().unwrap();
+        assert!(imports.iter().any(|module| module.name == "KERNEL32.dll"));
+        for module in imports {
+            let iat = module.descriptor.first_thunk;
+            for (index, function) in module.functions.iter().enumerate() {
+                assert_eq!(function.iat_rva, iat + index as u32 * 4);
+            }
+        }
+    }
+
+    #[test]
+    fn parse_exports() {
+        let data = fs::read("testdata/64bit/kernel32.dll").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+        let exports = pe.exports().unwrap();
+        assert_eq!(exports.module_name, "KERNEL32.dll");
+        assert_eq!(exports.directory.unwrap().ordinal_base, 1);
+        assert_eq!(exports.exports.len(), 1633);
+
+        let heap_alloc = exports.by_name("HeapAlloc").unwrap();
+        assert_eq!(heap_alloc.ordinal, 850);
+        assert_eq!(heap_alloc.forwarder.as_deref(),
+            Some("NTDLL.RtlAllocateHeap"));
+
+        let create_file = exports.by_ordinal(207).unwrap();
+        assert_eq!(create_file.name.as_deref(), Some("CreateFileW"));
+        assert_eq!(create_file.rva, 0x24b60);
+        assert!(create_file.forwarder.is_none());
+
+        let data = fs::read("testdata/32bit/ntdll.dll").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+        let exports = pe.exports().unwrap();
+        assert!(exports.by_ordinal(7).is_none());
+        assert_eq!(exports.by_ordinal(757).unwrap().name.as_deref(),
+            Some("RtlAllocateHeap"));
+
+        // Images without an export directory
+        let data = fs::read("testdata/64bit/notepad.exe").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+        assert!(pe.exports().unwrap().exports.is_empty());
+    }
+
+    #[test]
+    fn parse_delay_imports() {
+        let data = fs::read("testdata/64bit/notepad.exe").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+        let modules = pe.delay_imports().unwrap();
+        let names: Vec<&str> = modules.iter()
+            .map(|module| module.name.as_str())
+            .collect();
+        assert_eq!(names, ["ADVAPI32.dll", "COMDLG32.dll", "PROPSYS.dll",
+            "SHELL32.dll", "WINSPOOL.DRV", "urlmon.dll"]);
+
+        let advapi32 = &modules[0];
+        assert!(advapi32.descriptor.is_rva_based());
+        assert_eq!(advapi32.module_handle_rva, 0x32cb8);
+        assert_eq!(advapi32.iat_rva, 0x37000);
+        assert_eq!(advapi32.int_rva, 0x2f300);
+        assert_eq!(advapi32.bound_iat_rva, Some(0x2f790));
+        assert_eq!(advapi32.unload_iat_rva, None);
+        assert_eq!(advapi32.functions.len(), 18);
+        assert!(matches!(&advapi32.functions[1].name,
+            ImportName::Name { name, .. } if name == "IsTextUnicode"));
+        assert_eq!(advapi32.functions[1].iat_rva, 0x37008);
+
+        let data = fs::read("testdata/32bit/kernel32.dll").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+        let modules = pe.delay_imports().unwrap();
+        assert_eq!(modules[1].name, "RPCRT4.dll");
+        assert_eq!(modules[1].functions.len(), 10);
+        assert_eq!(modules[1].functions[1].iat_rva, 0xc0004);
+    }
+
+    #[test]
+    fn apply_base_relocations() {
+        let data = fs::read("testdata/64bit/notepad.exe").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+        let blocks = pe.relocations().unwrap();
+        assert!(!blocks.is_empty());
+        let fixups: Vec<u32> = blocks.iter()
+            .flat_map(|block| &block.entries)
+            .filter(|entry| entry.kind == RelocationType::Dir64)
+            .map(|entry| entry.rva)
+            .collect();
+        assert!(blocks.iter().flat_map(|block| &block.entries).all(|entry| {
+            matches!(entry.kind, RelocationType::Dir64
+                | RelocationType::Absolute)
+        }));
+
+        let image_base = pe.opt_header.win_fields.image_base();
+        let original = pe.rebased_image(image_base).unwrap();
+        let rebased = pe.rebased_image(image_base + 0x1_0000).unwrap();
+        for rva in &fixups {
+            let rva = *rva as usize;
+            let before = u64::from_le_bytes(
+                original[rva..rva + 8].try_into().unwrap());
+            let after = u64::from_le_bytes(
+                rebased[rva..rva + 8].try_into().unwrap());
+            assert_eq!(after, before + 0x1_0000);
+        }
+
+        // Only the relocated fields change
+        let changed = original.iter().zip(&rebased)
+            .filter(|(a, b)| a != b)
+            .count();
+        assert!(changed <= fixups.len() * 8);
+
+        let data = fs::read("testdata/32bit/notepad.exe").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+        let blocks = pe.relocations().unwrap();
+        assert!(blocks.iter().flat_map(|block| &block.entries)
+            .any(|entry| entry.kind == RelocationType::HighLow));
+
+        // A size of image past the last section is rejected before anything
+        // is allocated
+        let data = fs::read("testdata/64bit/notepad.exe").unwrap();
+        let size_of_image = PE::from_bytes(&data).unwrap().dos_header.e_lfanew
+            as usize + 24 + 56;
+        let mut patched = data.clone();
+        patched[size_of_image..size_of_image + 4]
+            .copy_from_slice(&0xffff_f000u32.to_le_bytes());
+        assert!(matches!(PE::from_bytes(&patched).unwrap().rebased_image(0),
+            Err(PeError::InvalidSizeOfImage(0xffff_f000))));
+
+        // The instruction encodings are checked on synthetic buffers
+        fn apply(kind: RelocationType, adjust: Option<u16>, field: &[u8],
+                delta: u64) -> Result<Vec<u8>> {
+            let mut image = field.to_vec();
+            let relocation = Relocation { kind, rva: 0, adjust };
+            apply_relocation(&mut image, &relocation, delta)?;
+            Ok(image)
+        }
+        fn words(values: &[u32]) -> Vec<u8> {
+            values.iter().flat_map(|value| value.to_le_bytes()).collect()
+        }
+        fn halves(values: &[u16]) -> Vec<u8> {
+            values.iter().flat_map(|value| value.to_le_bytes()).collect()
+        }
+
+        let field = 0x1234u16.to_le_bytes();
+        assert_eq!(apply(RelocationType::High, None, &field, 0x1_8000)
+            .unwrap(), 0x1235u16.to_le_bytes());
+        assert_eq!(apply(RelocationType::Low, None, &field, 0x1_0010)
+            .unwrap(), 0x1244u16.to_le_bytes());
+
+        // 0x1234_0000 plus the sign extended 0x8000 is 0x1233_8000, which
+        // becomes 0x1235_0000: the low half, relocated on its own, turns
+        // into 0x0000 and the high half has to carry
+        let high = apply(RelocationType::HighAdj, Some(0x8000), &field,
+            0x1_8000).unwrap();
+        assert_eq!(high, 0x1235u16.to_le_bytes());
+        let low = apply(RelocationType::Low, None, &0x8000u16.to_le_bytes(),
+            0x1_8000).unwrap();
+        assert_eq!(low, [0, 0]);
+
+        // movw r0, #0x5678 and movt r0, #0x1234, rebased to 0x123f_0e78
+        let mov32 = words(&[0xe305_0678, 0xe341_0234]);
+        assert_eq!(apply(RelocationType::ArmMov32, None, &mov32, 0xa_b800)
+            .unwrap(), words(&[0xe300_0e78, 0xe341_023f]));
+        // The same pair in Thumb-2, where bit 11 of the new low immediate
+        // lands in the `i` bit of the first halfword
+        let mov32 = halves(&[0xf245, 0x6078, 0xf2c1, 0x2034]);
+        assert_eq!(apply(RelocationType::ThumbMov32, None, &mov32, 0xa_b800)
+            .unwrap(), halves(&[0xf640, 0x6078, 0xf2c1, 0x203f]));
+
+        // lui a0, 0x12345 moves by whole pages, addi a0, a0, 0x678 and
+        // sw a0, 0x678(sp) keep their low 12 bits
+        let lui = words(&[0x1234_5537]);
+        assert_eq!(apply(RelocationType::RiscvHigh20, None, &lui, 0x1_0000)
+            .unwrap(), words(&[0x1235_5537]));
+        let addi = words(&[0x6785_0513]);
+        assert_eq!(apply(RelocationType::RiscvLow12I, None, &addi, 0x1_0000)
+            .unwrap(), addi);
+        let sw = words(&[0x66a1_2c23]);
+        assert_eq!(apply(RelocationType::RiscvLow12S, None, &sw, 0x1_0000)
+            .unwrap(), sw);
+        // Without the paired instruction, the carry of an unaligned delta
+        // into the high 20 bits is unknown
+        for (kind, field, raw) in [(RelocationType::RiscvHigh20, &lui, 5),
+                (RelocationType::RiscvLow12I, &addi, 7),
+                (RelocationType::RiscvLow12S, &sw, 8)] {
+            assert_eq!(kind.to_raw(), raw);
+            assert!(matches!(apply(kind, None, field, 0x1_0800),
+                Err(PeError::UnalignedRelocation(error)) if error == raw));
+        }
+    }
+
+    #[test]
+    fn parse_tls_directory() {
+        let data = fs::read("testdata/64bit/notepad.exe").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+        let tls = pe.tls().unwrap().unwrap();
+        assert_eq!(tls.directory.start_address_of_raw_data, 0x1_4002_e0f0);
+        assert_eq!(tls.directory.end_address_of_raw_data, 0x1_4002_e0f8);
+        assert_eq!(tls.directory.address_of_index, 0x1_4003_32e8);
+        assert_eq!(tls.directory.address_of_callbacks, 0x1_4002_9240);
+        assert_eq!(tls.directory.alignment(), Some(4));
+        assert!(tls.callbacks.is_empty());
+
+        // Fill the callback array with one callback in the image and one
+        // far outside of it
+        let array = pe.va_to_rva(tls.directory.address_of_callbacks)
+            .and_then(|rva| pe.rva_to_offset(rva))
+            .unwrap();
+        let mut patched = data.clone();
+        for (index, va) in [0x1_4000_1000u64, 0xdead_0000_0000, 0]
+                .iter().enumerate() {
+            let offset = array + index * 8;
+            patched[offset..offset + 8].copy_from_slice(&va.to_le_bytes());
+        }
+        let tls = PE::from_bytes(&patched).unwrap().tls().unwrap().unwrap();
+        assert_eq!(tls.callbacks, [
+            TlsCallback { va: 0x1_4000_1000, rva: Some(0x1000) },
+            TlsCallback { va: 0xdead_0000_0000, rva: None },
+        ]);
+
+        let data = fs::read("testdata/32bit/notepad.exe").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+        let tls = pe.tls().unwrap().unwrap();
+        assert_eq!(tls.directory.address_of_callbacks, 0x4015a8);
+
+        let data = fs::read("testdata/64bit/kernel32.dll").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+        assert!(pe.tls().unwrap().is_none());
+    }
+
+    #[test]
+    fn parse_load_config() {
+        let data = fs::read("testdata/64bit/notepad.exe").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+        let config = pe.load_config().unwrap().unwrap();
+        assert_eq!(config.size, 0x118);
+        assert_eq!(config.level(), Some(LoadConfigLevel::EhContinuation));
+        assert_eq!(config.security_cookie, Some(0x1_4003_24a8));
+        assert_eq!(config.guard_cf_function_count, Some(0x77));
+        assert_eq!(config.guard_flags, Some(0x417500));
+        assert!(config.guard_eh_continuation_count.is_some());
+        assert!(config.guard_xfg_check_function_pointer.is_none());
+
+        // A size past the end of the section is clamped to the mapped bytes
+        let range = pe.opt_header.data_directories.load_config().unwrap();
+        let offset = pe.rva_to_offset(range.virtual_address).unwrap();
+        let mut patched = data.clone();
+        patched[offset..offset + 4]
+            .copy_from_slice(&0x7fff_0000u32.to_le_bytes());
+        let patched = PE::from_bytes(&patched).unwrap().load_config().unwrap()
+            .unwrap();
+        assert_eq!(patched.size, 0x7fff_0000);
+        assert_eq!(patched.security_cookie, config.security_cookie);
+        assert_eq!(patched.guard_flags, config.guard_flags);
+
+        let data = fs::read("testdata/32bit/notepad.exe").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+        let config = pe.load_config().unwrap().unwrap();
+        assert_eq!(config.size, 0xac);
+        assert_eq!(config.level(), Some(LoadConfigLevel::EhContinuation));
+        assert_eq!(config.security_cookie, Some(0x424210));
+
+        // A Windows 7 era PE32 directory stops after the SafeSEH table
+        let mut bytes = vec![0u8; 0xac];
+        bytes[0] = 0x48;
+        let config = LoadConfig::from_bytes(&bytes, false).unwrap();
+        assert_eq!(config.level(), Some(LoadConfigLevel::SafeSeh));
+        assert!(config.se_handler_count.is_some());
+        assert!(config.guard_cf_check_function_pointer.is_none());
+
+        // Truncated in the middle of the base fields
+        bytes[0] = 0x20;
+        let config = LoadConfig::from_bytes(&bytes, false).unwrap();
+        assert_eq!(config.level(), None);
+        assert!(config.lock_prefix_table.is_none());
+    }
+
+    #[test]
+    fn parse_debug_directory() {
+        let data = fs::read("testdata/64bit/notepad.exe").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+        let info = pe.pdb_info().unwrap().unwrap();
+        assert_eq!(info.guid.to_string(),
+            "6539CE99-8C7C-AFD7-3A8E-13A54542E112");
+        assert_eq!(info.age, 1);
+        assert_eq!(info.path, "notepad.pdb");
+
+        let entries = pe.debug_entries().unwrap();
+        let types: Vec<DebugType> = entries.iter()
+            .map(|entry| entry.directory.debug_type)
+            .collect();
+        assert_eq!(types, [DebugType::CodeView, DebugType::Pogo,
+            DebugType::Repro]);
+        match &entries[1].payload {
+            Some(DebugPayload::Pogo(pogo)) => {
+                assert_eq!(pogo.entries[0].rva, 0x1000);
+                assert_eq!(pogo.entries[0].name, ".text");
+                assert_eq!(pogo.entries[1].name, ".text$di");
+            },
+            payload => panic!("unexpected payload {:?}", payload),
+        }
+        match &entries[2].payload {
+            Some(DebugPayload::Repro(hash)) => assert_eq!(hash.len(), 32),
+            payload => panic!("unexpected payload {:?}", payload),
+        }
+
+        // Point the POGO data nowhere and truncate the Repro data: both
+        // are reported as malformed, the CodeView entry is still decoded
+        let range = pe.opt_header.data_directories.debug().unwrap();
+        let offset = pe.rva_to_offset(range.virtual_address).unwrap();
+        let mut patched = data.clone();
+        let pogo = offset + DebugDirectory::len();
+        patched[pogo + 20..pogo + 28].copy_from_slice(&[0; 8]);
+        let repro = offset + 2 * DebugDirectory::len();
+        patched[repro + 16..repro + 20].copy_from_slice(&2u32.to_le_bytes());
+        let pe = PE::from_bytes(&patched).unwrap();
+        let entries = pe.debug_entries().unwrap();
+        assert!(matches!(entries[0].payload,
+            Some(DebugPayload::CodeView(_))));
+        assert_eq!(entries[1].payload, Some(DebugPayload::Malformed));
+        assert_eq!(entries[2].payload, Some(DebugPayload::Malformed));
+        assert_eq!(pe.pdb_info().unwrap().unwrap().path, "notepad.pdb");
+        assert!(pe.symbol_keys().unwrap().pdb_key.is_some());
+
+        // RVA 0 is never debug data
+        assert!(entries[1].directory.data(&pe).is_err());
+        let empty = DebugDirectory { size_of_data: 0, ..entries[1].directory };
+        assert_eq!(empty.data(&pe).unwrap(), b"");
+
+        let data = fs::read("testdata/32bit/ntdll.dll").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+        assert_eq!(pe.pdb_info().unwrap().unwrap().path, "wntdll.pdb");
+        let entries = pe.debug_entries().unwrap();
+        assert_eq!(entries[3].payload,
+            Some(DebugPayload::ExDllCharacteristics(1)));
+    }
+
+    #[test]
+    fn compute_symbol_keys() {
+        let data = fs::read("testdata/64bit/notepad.exe").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+        let keys = pe.symbol_keys().unwrap();
+        assert_eq!(keys.image_key, "F57E80D43a000");
+        assert_eq!(keys.pdb_name.as_deref(), Some("notepad.pdb"));
+        assert_eq!(keys.pdb_key.as_deref(),
+            Some("6539CE998C7CAFD73A8E13A54542E1121"));
+
+        let store = std::env::temp_dir()
+            .join(format!("pe-parser-symbols-{}", std::process::id()));
+        let pdb = keys.pdb_path(&store).unwrap();
+        assert!(pdb.ends_with(
+            "notepad.pdb/6539CE998C7CAFD73A8E13A54542E1121/notepad.pdb"));
+        assert!(keys.find_pdb(&store).is_none());
+
+        // The age is in lowercase hex, like the image key's size
+        let codeview = &pe.debug_entries().unwrap()[0].directory;
+        let age = codeview.pointer_to_raw_data as usize + 20;
+        let mut patched = data.clone();
+        patched[age..age + 4].copy_from_slice(&0x2bu32.to_le_bytes());
+        let patched_keys = PE::from_bytes(&patched).unwrap()
+            .symbol_keys().unwrap();
+        assert_eq!(patched_keys.pdb_key.as_deref(),
+            Some("6539CE998C7CAFD73A8E13A54542E1122b"));
+
+        // Compressed files are found as well
+        let image = keys.image_path(&store, "notepad.exe");
+        fs::create_dir_all(image.parent().unwrap()).unwrap();
+        fs::write(image.with_file_name("notepad.ex_"), b"").unwrap();
+        assert_eq!(keys.find_image(&store, "notepad.exe"),
+            Some(image.with_file_name("notepad.ex_")));
+        fs::remove_dir_all(&store).unwrap();
+    }
+
+    #[test]
+    fn walk_resource_tree() {
+        let data = fs::read("testdata/64bit/notepad.exe").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+        let tree = pe.resources().unwrap();
+        let resources = tree.resources();
+        assert_eq!(resources.len(), 5);
+        assert_eq!(resources[0].kind,
+            ResourceId::Name("EDPENLIGHTENEDAPPINFOID".to_string()));
+        assert_eq!(resources[2].kind, ResourceId::Name("MUI".to_string()));
+
+        let manifest = tree.find(ResourceType::Manifest, &ResourceId::Id(1))
+            .unwrap();
+        assert_eq!(manifest.language, ResourceId::Id(1033));
+        assert_eq!(manifest.data.rva, 0x38260);
+        assert_eq!(manifest.data.size, 1199);
+        assert_eq!(manifest.data.code_page, 0);
+        assert!(manifest.data(&pe).unwrap().starts_with(b"<?xml"));
+
+        let data = fs::read("testdata/64bit/user32.dll").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+        let tree = pe.resources().unwrap();
+        assert_eq!(tree.resources().len(), 201);
+        assert_eq!(tree.resources_of_type(ResourceType::Icon).len(), 77);
+
+        // Point the first subdirectory of the root back at the root
+        let mut data = fs::read("testdata/64bit/notepad.exe").unwrap();
+        let rsrc = 0x32c00 + 16 + 4;
+        data[rsrc..rsrc + 4].copy_from_slice(&0x8000_0000u32.to_le_bytes());
+        let pe = PE::from_bytes(&data).unwrap();
+        assert!(matches!(pe.resources(), Err(PeError::ResourceCycle(0))));
+    }
+
+    #[test]
+    fn decode_version_info() {
+        let data = fs::read("testdata/64bit/kernel32.dll").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+        let info = pe.version_info().unwrap().unwrap();
+
+        let fixed = info.fixed.unwrap();
+        assert_eq!(fixed.file_version(), (10, 0, 19041, 1202));
+        assert_eq!(fixed.product_version(), (10, 0, 19041, 1202));
+        assert_eq!(fixed.file_os, FileOs::NtWindows32);
+        assert_eq!(fixed.file_type, FileType::Dll);
+        assert_eq!(fixed.flags(), Vec::<FileFlag>::new());
+
+        assert_eq!(info.string_tables.len(), 1);
+        assert_eq!(info.string_tables[0].key, "040904B0");
+        assert_eq!(info.string_tables[0].language, 0x409);
+        assert_eq!(info.string_tables[0].code_page, 1200);
+        assert_eq!(info.get("CompanyName"), Some("Microsoft Corporation"));
+        assert_eq!(info.get("FileVersion"),
+            Some("10.0.19041.1202 (WinBuild.160101.0800)"));
+        assert_eq!(info.get("ProductVersion"), Some("10.0.19041.1202"));
+        assert_eq!(info.get("OriginalFilename"), Some("kernel32"));
+        assert_eq!(info.get_for_language(0x409, "InternalName"),
+            Some("kernel32"));
+        assert_eq!(info.get_for_language(0x407, "InternalName"), None);
+        assert_eq!(info.translations, [(0x409, 1200)]);
+
+        for path in TESTDATA {
+            let data = fs::read(path).unwrap();
+            let pe = PE::from_bytes(&data).unwrap();
+            let info = pe.version_info().unwrap().unwrap();
+            assert!(info.get("ProductName").is_some());
+        }
+    }
+
+    #[test]
+    fn parse_manifests() {
+        let data = fs::read("testdata/64bit/notepad.exe").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+        let manifests = pe.manifests().unwrap();
+        assert_eq!(manifests.len(), 1);
+
+        let manifest = &manifests[0];
+        assert_eq!(manifest.kind, Some(ManifestKind::CreateProcess));
+        assert!(manifest.xml.starts_with("<?xml"));
+        assert!(manifest.xml.ends_with("</assembly>\r\n"));
+        assert_eq!(manifest.execution_level, Some(ExecutionLevel::AsInvoker));
+        assert_eq!(manifest.ui_access, Some(false));
+        assert_eq!(manifest.dpi_aware, None);
+        assert_eq!(manifest.dpi_awareness.as_deref(), Some("PerMonitorV2"));
+        assert_eq!(manifest.long_path_aware, None);
+        assert!(manifest.supported_os.is_empty());
+
+        let xml = r#"<?xml version="1.0" encoding="UTF-16"?>
+<assembly xmlns="urn:schemas-microsoft-com:asm.v1" manifestVersion="1.0">
+  <!-- <requestedExecutionLevel level="asInvoker"/> -->
+  <compatibility xmlns="urn:schemas-microsoft-com:compatibility.v1">
+    <application>
+      <supportedOS Id="{8e0f7a12-bfb3-4fe8-b9a5-48fd50a15a9a}"/>
+      <supportedOS Id='{35138B9A-5D96-4FBD-8E2D-A2440225F93A}' />
+      <supportedOS Id="{00000000-0000-0000-0000-000000000000}"/>
+    </application>
+  </compatibility>
+  <trustInfo xmlns="urn:schemas-microsoft-com:asm.v2">
+    <security><requestedPrivileges>
+      <requestedExecutionLevel level="requireAdministrator"
+          uiAccess="true"></requestedExecutionLevel>
+    </requestedPrivileges></security>
+  </trustInfo>
+  <application xmlns="urn:schemas-microsoft-com:asm.v3">
+    <windowsSettings>
+      <dpiAware xmlns="http://schemas.microsoft.com/SMI/2005/WindowsSettings"
+          >true/pm</dpiAware>
+      <ws2:longPathAware xmlns:ws2="http://schemas.microsoft.com/SMI/2016/WindowsSettings"> true </ws2:longPathAware>
+    </windowsSettings>
+  </application>
+</assembly>"#;
+        let mut bytes = vec![0xff, 0xfe];
+        bytes.extend(xml.encode_utf16().flat_map(u16::to_le_bytes));
+        bytes.extend([0, 0]);
+
+        let manifest = Manifest::from_bytes(&bytes);
+        assert_eq!(manifest.kind, None);
+        assert_eq!(manifest.xml, xml);
+        assert_eq!(manifest.execution_level,
+            Some(ExecutionLevel::RequireAdministrator));
+        assert_eq!(manifest.ui_access, Some(true));
+        assert_eq!(manifest.dpi_aware.as_deref(), Some("true/pm"));
+        assert_eq!(manifest.long_path_aware, Some(true));
+        assert_eq!(manifest.supported_os, [
+            SupportedOs::Windows10,
+            SupportedOs::Windows7,
+            SupportedOs::Other(
+                "{00000000-0000-0000-0000-000000000000}".to_string()),
+        ]);
+
+        let mut bytes = b"\xef\xbb\xbf".to_vec();
+        bytes.extend(xml.as_bytes());
+        assert_eq!(Manifest::from_bytes(&bytes), manifest);
+    }
+
+    #[test]
+    fn rebuild_icon_groups() {
+        let data = fs::read("testdata/64bit/user32.dll").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+        let groups = pe.icon_groups().unwrap();
+        let icons = groups.iter().filter(|group| group.kind == GroupKind::Icon);
+        assert_eq!(icons.count(), 7);
+        let cursors = groups.iter()
+            .filter(|group| group.kind == GroupKind::Cursor);
+        assert_eq!(cursors.count(), 34);
+
+        for group in &groups {
+            let file = group.to_file(&pe).unwrap();
+            let (reserved, rest) = take_u16(&file).unwrap();
+            let (kind, rest) = take_u16(rest).unwrap();
+            let (count, mut rest) = take_u16(rest).unwrap();
+            assert_eq!(reserved, 0);
+            assert_eq!(kind, group.kind as u16);
+            assert_eq!(count as usize, group.entries.len());
+
+            let mut expected_offset = 6 + count as u32 * 16;
+            for (entry, image) in group.entries.iter().zip(&group.images) {
+                let (size, offset) = (
+                    u32::from_le_bytes(rest[8..12].try_into().unwrap()),
+                    u32::from_le_bytes(rest[12..16].try_into().unwrap()));
+                let image = image.data(&pe).unwrap();
+                let header = match group.kind {
+                    GroupKind::Icon => {
+                        assert_eq!(rest[0] as u16, entry.width);
+                        assert_eq!(&rest[4..6], &entry.planes.to_le_bytes());
+                        &image[..]
+                    },
+                    GroupKind::Cursor => {
+                        assert_eq!(rest[1] as u16, entry.height / 2);
+                        // The hotspot moves from the image to the entry
+                        assert_eq!(&rest[4..8], &image[..4]);
+                        &image[4..]
+                    },
+                };
+                assert_eq!(offset, expected_offset);
+                assert_eq!(size as usize, header.len());
+                let start = offset as usize;
+                assert_eq!(&file[start..start + header.len()], header);
+                // Images are either BITMAPINFOHEADERs or PNG files
+                assert!(header.starts_with(&40u32.to_le_bytes())
+                    || header.starts_with(b"\x89PNG"));
+
+                expected_offset += size;
+                rest = &rest[16..];
+            }
+            assert_eq!(file.len(), expected_offset as usize);
+        }
+        assert!(groups.iter().all(|group| group.missing.is_empty()));
+
+        // Point the first entry of the first icon group at an image that
+        // does not exist: only that entry is set aside
+        let tree = pe.resources().unwrap();
+        let group = &tree.resources_of_type(ResourceType::GroupIcon)[0];
+        let id = pe.rva_to_offset(group.data.rva).unwrap() + 6 + 12;
+        let mut patched = data.clone();
+        patched[id..id + 2].copy_from_slice(&0xfffeu16.to_le_bytes());
+        let pe = PE::from_bytes(&patched).unwrap();
+        let patched_groups = pe.icon_groups().unwrap();
+        assert_eq!(patched_groups.len(), groups.len());
+        let [missing] = &patched_groups[0].missing[..] else {
+            panic!("expected one missing image")
+        };
+        assert_eq!(missing.id, 0xfffe);
+        assert_eq!(patched_groups[0].entries, groups[0].entries[1..]);
+        assert_eq!(patched_groups[0].images, groups[0].images[1..]);
+        assert_eq!(patched_groups[1..], groups[1..]);
+        let file = patched_groups[0].to_file(&pe).unwrap();
+        assert_eq!(take_u16(&file[4..]).unwrap().0 as usize,
+            groups[0].entries.len() - 1);
+
+        let data = fs::read("testdata/64bit/notepad.exe").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+        assert!(pe.icon_groups().unwrap().is_empty());
+    }
+
+    #[test]
+    fn decode_string_resources() {
+        let data = fs::read("testdata/64bit/ntdll.dll").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+        let tables = pe.message_tables().unwrap();
+        assert_eq!(tables.len(), 1);
+        assert_eq!(tables[0].language, ResourceId::Id(1033));
+        assert_eq!(tables[0].strings.len(), 2711);
+        assert_eq!(tables[0].get(0), Some("STATUS_SUCCESS\r\n"));
+        assert_eq!(tables[0].get(0x3f), Some("STATUS_WAIT_63\r\n"));
+        assert_eq!(tables[0].get(4), None);
+        assert!(pe.string_tables().unwrap().is_empty());
+        assert!(pe.accelerators().unwrap().is_empty());
+
+        // Block 2 holds IDs 16 to 31
+        let mut block = Vec::new();
+        for index in 0..16u16 {
+            let string: Vec<u16> = match index {
+                1 => "Open".encode_utf16().collect(),
+                15 => "Exit".encode_utf16().collect(),
+                _ => Vec::new(),
+            };
+            block.extend((string.len() as u16).to_le_bytes());
+            block.extend(string.iter().flat_map(|unit| unit.to_le_bytes()));
+        }
+        assert_eq!(parse_string_block(&block, 2).unwrap(),
+            [(17, "Open".to_string()), (31, "Exit".to_string())]);
+        assert!(parse_string_block(&block[..block.len() - 1], 2).is_err());
+
+        // One block covering IDs 0x10-0x11, an ANSI entry and a UTF-16 one
+        let mut table = Vec::new();
+        table.extend(1u32.to_le_bytes());
+        table.extend([0x10u32, 0x11, 16].iter().flat_map(|v| v.to_le_bytes()));
+        table.extend([12u16, 0].iter().flat_map(|v| v.to_le_bytes()));
+        table.extend(b"caf\xe9!\r\n\0");
+        table.extend([12u16, 1].iter().flat_map(|v| v.to_le_bytes()));
+        table.extend("ok\r\n".encode_utf16().flat_map(u16::to_le_bytes));
+        assert_eq!(parse_message_table(&table).unwrap(), [
+            (0x10, "caf\u{e9}!\r\n".to_string()),
+            (0x11, "ok\r\n".to_string()),
+        ]);
+        table[28..30].copy_from_slice(&0u16.to_le_bytes());
+        assert!(matches!(parse_message_table(&table),
+            Err(PeError::InvalidMessageTable)));
+
+        // Ctrl+O, then F5 flagged as the last entry, then garbage
+        let mut accelerators = Vec::new();
+        for entry in [[0x09u16, b'O' as u16, 100, 0], [0x81, 0x74, 101, 0],
+                [0xffff, 0xffff, 0xffff, 0xffff]] {
+            accelerators.extend(entry.iter().flat_map(|v| v.to_le_bytes()));
+        }
+        let entries = AcceleratorTable::parse_entries(&accelerators).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].flags(),
+            [AcceleratorFlag::VirtKey, AcceleratorFlag::Control]);
+        assert_eq!((entries[0].key, entries[0].id), (b'O' as u16, 100));
+        assert_eq!(entries[1].flags(),
+            [AcceleratorFlag::VirtKey, AcceleratorFlag::End]);
+        assert_eq!((entries[1].key, entries[1].id), (0x74, 101));
+    }
+
+    #[test]
+    fn decode_dialogs_and_menus() {
+        fn utf16z(text: &str) -> Vec<u8> {
+            text.encode_utf16().chain([0]).flat_map(u16::to_le_bytes).collect()
+        }
+        fn words(values: &[u16]) -> Vec<u8> {
+            values.iter().flat_map(|v| v.to_le_bytes()).collect()
+        }
+        fn pad(bytes: &mut Vec<u8>) {
+            bytes.resize(bytes.len().next_multiple_of(4), 0);
+        }
+
+        for pe in TESTDATA {
+            let data = fs::read(pe).unwrap();
+            let pe = PE::from_bytes(&data).unwrap();
+            assert!(pe.dialogs().unwrap().is_empty());
+            assert!(pe.menus().unwrap().is_empty());
+        }
+
+        // DS_SETFONT | WS_POPUP, two controls, the second with creation data
+        let mut dialog = Vec::new();
+        dialog.extend(0x8000_0040u32.to_le_bytes());
+        dialog.extend(0u32.to_le_bytes());
+        dialog.extend(words(&[2, 10, 20, 200, 100, 0, 0]));
+        dialog.extend(utf16z("About"));
+        dialog.extend(words(&[8]));
+        dialog.extend(utf16z("MS Shell Dlg"));
+        pad(&mut dialog);
+        dialog.extend(0x5001_0000u32.to_le_bytes());
+        dialog.extend(0u32.to_le_bytes());
+        dialog.extend(words(&[5, 6, 50, 14, 1, 0xffff, 0x80]));
+        dialog.extend(utf16z("OK"));
+        dialog.extend(words(&[0]));
+        pad(&mut dialog);
+        dialog.extend(0x5000_0000u32.to_le_bytes());
+        dialog.extend(0u32.to_le_bytes());
+        dialog.extend(words(&[5, 30, 20, 20, 0xffff]));
+        dialog.extend(utf16z("MyClass"));
+        dialog.extend(words(&[0xffff, 101, 2, 0xabcd]));
+
+        let template = DialogTemplate::from_bytes(&dialog).unwrap();
+        assert!(!template.extended);
+        assert_eq!((template.x, template.y, template.cx, template.cy),
+            (10, 20, 200, 100));
+        assert_eq!(template.menu, NameOrOrdinal::None);
+        assert_eq!(template.title, "About");
+        let font = template.font.unwrap();
+        assert_eq!((font.point_size, font.typeface.as_str()),
+            (8, "MS Shell Dlg"));
+        assert_eq!(template.controls.len(), 2);
+        assert_eq!(template.controls[0].id, 1);
+        assert_eq!(template.controls[0].class_name(), Some("Button"));
+        assert_eq!(template.controls[0].title,
+            NameOrOrdinal::Name("OK".to_string()));
+        assert_eq!(template.controls[1].id, 0xffff);
+        assert_eq!(template.controls[1].class_name(), Some("MyClass"));
+        assert_eq!(template.controls[1].title, NameOrOrdinal::Ordinal(101));
+        assert_eq!(template.controls[1].creation_data, [0xcd, 0xab]);
+        assert!(DialogTemplate::from_bytes(&dialog[..dialog.len() - 1])
+            .is_err());
+
+        // The same dialog in the extended format, with a menu and one control
+        let mut dialog = words(&[1, 0xffff]);
+        dialog.extend([7u32, 0, 0x8000_0048].iter()
+            .flat_map(|v| v.to_le_bytes()));
+        dialog.extend(words(&[1, 10, 20, 200, 100, 0xffff, 300, 0]));
+        dialog.extend(utf16z("About"));
+        dialog.extend(words(&[9, 700]));
+        dialog.extend([1, 0]);
+        dialog.extend(utf16z("Segoe UI"));
+        pad(&mut dialog);
+        dialog.extend([9u32, 0, 0x5001_0000].iter()
+            .flat_map(|v| v.to_le_bytes()));
+        dialog.extend(words(&[5, 6, 50, 14]));
+        dialog.extend(0x12345u32.to_le_bytes());
+        dialog.extend(words(&[0xffff, 0x81, 0, 0]));
+
+        let template = DialogTemplate::from_bytes(&dialog).unwrap();
+        assert!(template.extended);
+        assert_eq!(template.help_id, 7);
+        assert_eq!(template.menu, NameOrOrdinal::Ordinal(300));
+        let font = template.font.unwrap();
+        assert_eq!((font.point_size, font.weight, font.italic),
+            (9, 700, true));
+        assert_eq!(font.typeface, "Segoe UI");
+        assert_eq!(template.controls.len(), 1);
+        assert_eq!(template.controls[0].help_id, 9);
+        assert_eq!(template.controls[0].id, 0x12345);
+        assert_eq!(template.controls[0].class_name(), Some("Edit"));
+        assert_eq!(template.controls[0].title, NameOrOrdinal::None);
+        // An item count past the last control
+        let mut truncated = dialog.clone();
+        truncated[16..18].copy_from_slice(&2u16.to_le_bytes());
+        assert!(DialogTemplate::from_bytes(&truncated).is_err());
+
+        // DS_SETFONT | DS_MODALFRAME | WS_POPUP | WS_CAPTION | WS_SYSMENU
+        // with a named menu and the #32770 class, then a static control
+        // showing icon 100 and a list view with a named class and title
+        let mut dialog = Vec::new();
+        dialog.extend(0x80c8_00c0u32.to_le_bytes());
+        dialog.extend(0x0000_0101u32.to_le_bytes());
+        dialog.extend(words(&[2, 0, 0, 120, 60]));
+        dialog.extend(utf16z("MAINMENU"));
+        dialog.extend(words(&[0xffff, 0x8002]));
+        dialog.extend(utf16z("Properties"));
+        dialog.extend(words(&[9]));
+        dialog.extend(utf16z("Tahoma"));
+        pad(&mut dialog);
+        dialog.extend(0x5000_0003u32.to_le_bytes());
+        dialog.extend(0u32.to_le_bytes());
+        dialog.extend(words(&[7, 7, 21, 20, 0xffff, 0xffff, 0x82, 0xffff,
+            100, 0]));
+        pad(&mut dialog);
+        dialog.extend(0x5001_0001u32.to_le_bytes());
+        dialog.extend(0x200u32.to_le_bytes());
+        dialog.extend(words(&[7, 30, 100, 20, 1000]));
+        dialog.extend(utf16z("SysListView32"));
+        dialog.extend(utf16z("items"));
+        dialog.extend(words(&[4]));
+        dialog.extend([1, 2, 3, 4]);
+
+        let template = DialogTemplate::from_bytes(&dialog).unwrap();
+        assert!(!template.extended);
+        assert_eq!((template.style, template.ext_style),
+            (0x80c8_00c0, 0x101));
+        assert_eq!(template.menu, NameOrOrdinal::Name("MAINMENU".to_string()));
+        assert_eq!(template.class, NameOrOrdinal::Ordinal(0x8002));
+        assert_eq!(template.title, "Properties");
+        assert_eq!(template.font, Some(DialogFont {
+            point_size: 9, weight: 0, italic: false, charset: 0,
+            typeface: "Tahoma".to_string(),
+        }));
+        let [icon, list] = &template.controls[..] else {
+            panic!("expected two controls")
+        };
+        assert_eq!((icon.x, icon.y, icon.cx, icon.cy), (7, 7, 21, 20));
+        assert_eq!(icon.id, 0xffff);
+        assert_eq!(icon.class, NameOrOrdinal::Ordinal(0x82));
+        assert_eq!(icon.class_name(), Some("Static"));
+        assert_eq!(icon.title, NameOrOrdinal::Ordinal(100));
+        assert!(icon.creation_data.is_empty());
+        assert_eq!((list.style, list.ext_style, list.id),
+            (0x5001_0001, 0x200, 1000));
+        assert_eq!(list.class_name(), Some("SysListView32"));
+        assert_eq!(list.title, NameOrOrdinal::Name("items".to_string()));
+        assert_eq!(list.creation_data, [1, 2, 3, 4]);
+
+        // Item counts past the last control, up to the largest one
+        for count in [3u16, 0xffff] {
+            let mut truncated = dialog.clone();
+            truncated[8..10].copy_from_slice(&count.to_le_bytes());
+            assert!(DialogTemplate::from_bytes(&truncated).is_err());
+        }
+        // No control at all is fine, whatever follows the font
+        let mut empty = dialog.clone();
+        empty[8..10].copy_from_slice(&0u16.to_le_bytes());
+        assert!(DialogTemplate::from_bytes(&empty).unwrap().controls
+            .is_empty());
+        // DS_SETFONT without a font, and an ordinal cut after its marker
+        let title_end = 18 + utf16z("MAINMENU").len() + 4
+            + utf16z("Properties").len();
+        assert!(DialogTemplate::from_bytes(&dialog[..title_end]).is_err());
+        assert!(DialogTemplate::from_bytes(&dialog[..title_end - 24])
+            .is_err());
+
+        // &File > (&Open, separator, E&xit), &Help
+        let mut menu = words(&[0, 0, 0x10]);
+        menu.extend(utf16z("&File"));
+        menu.extend(words(&[0, 100]));
+        menu.extend(utf16z("&Open"));
+        menu.extend(words(&[0x800, 0, 0]));
+        menu.extend(words(&[0x80, 101]));
+        menu.extend(utf16z("E&xit"));
+        menu.extend(words(&[0x80, 200]));
+        menu.extend(utf16z("&Help"));
+
+        let template = MenuTemplate::from_bytes(&menu).unwrap();
+        assert!(!template.extended);
+        assert_eq!(template.items.len(), 2);
+        let file = &template.items[0];
+        assert_eq!(file.text, "&File");
+        let children = file.children.as_ref().unwrap();
+        let texts: Vec<_> = children.iter().map(|i| i.text.as_str()).collect();
+        assert_eq!(texts, ["&Open", "", "E&xit"]);
+        assert_eq!(children[2].id, 101);
+        assert_eq!(template.items[1], MenuItem {
+            flags: 0x80, item_type: 0, state: 0, id: 200, help_id: 0,
+            text: "&Help".to_string(), children: None,
+        });
+        assert!(MenuTemplate::from_bytes(&menu[..menu.len() - 4]).is_err());
+
+        // The same menu bar, extended, with a popup holding a single item
+        let mut menu = words(&[1, 4]);
+        menu.extend(5u32.to_le_bytes());
+        menu.extend([0u32, 0, 0].iter().flat_map(|v| v.to_le_bytes()));
+        menu.extend(words(&[0x01]));
+        menu.extend(utf16z("&File"));
+        pad(&mut menu);
+        menu.extend(42u32.to_le_bytes());
+        menu.extend([0u32, 8, 100].iter().flat_map(|v| v.to_le_bytes()));
+        menu.extend(words(&[0x80]));
+        menu.extend(utf16z("&Open"));
+        pad(&mut menu);
+        menu.extend([0u32, 0, 200].iter().flat_map(|v| v.to_le_bytes()));
+        menu.extend(words(&[0x80]));
+        menu.extend(utf16z("&Help"));
+
+        let template = MenuTemplate::from_bytes(&menu).unwrap();
+        assert!(template.extended);
+        assert_eq!(template.help_id, 5);
+        assert_eq!(template.items.len(), 2);
+        assert_eq!(template.items[0].help_id, 42);
+        let open = &template.items[0].children.as_ref().unwrap()[0];
+        assert_eq!((open.id, open.state, open.text.as_str()),
+            (100, 8, "&Open"));
+        assert_eq!(template.items[1].id, 200);
+        assert_eq!(template.items[1].text, "&Help");
+    }
+
+    #[test]
+    fn parse_certificate_table() {
+        // Only the system DLLs of the testdata are signed
+        for path in TESTDATA {
+            let data = fs::read(path).unwrap();
+            let pe = PE::from_bytes(&data).unwrap();
+            let certificates = pe.certificates().unwrap();
+            if path.ends_with("notepad.exe") {
+                assert!(certificates.is_empty());
+                continue;
+            }
+
+            assert_eq!(certificates.len(), 1);
+            let certificate = &certificates[0];
+            let directory = pe.opt_header.data_directories.security().unwrap();
+            assert_eq!(certificate.offset, directory.virtual_address);
+            assert_eq!(certificate.length, directory.size);
+            assert_eq!(certificate.revision(), CertificateRevision::Revision2);
+            assert_eq!(certificate.kind(), CertificateType::PkcsSignedData);
+            // A DER SEQUENCE with a two byte length spanning the whole blob
+            let signed_data = certificate.signed_data().unwrap();
+            assert_eq!(&signed_data[..2], [0x30, 0x82]);
+            let len = u16::from_be_bytes([signed_data[2], signed_data[3]]);
+            assert!(len as usize + 4 <= signed_data.len());
+        }
+
+        let data = fs::read("testdata/64bit/kernel32.dll").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+        let certificate = &pe.certificates().unwrap()[0];
+        assert_eq!(certificate.offset, 0xb8400);
+        assert_eq!(certificate.certificate.len(), 15456 - 8);
+        assert_eq!(&certificate.certificate[..4], [0x30, 0x82, 0x3c, 0x51]);
+
+        // A length shorter than the header is rejected
+        let mut data = data.clone();
+        data[0xb8400..0xb8404].copy_from_slice(&4u32.to_le_bytes());
+        let pe = PE::from_bytes(&data).unwrap();
+        assert!(matches!(pe.certificates(),
+            Err(PeError::InvalidCertificate(0xb8400))));
+    }
+
+    #[test]
+    fn compute_authenticode_digest() {
+        fn hex(bytes: &[u8]) -> String {
+            bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+        }
+
+        assert_eq!(hex(&Sha1::digest(b"abc")),
+            "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(hex(&Sha256::digest(b"")),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        let mut hasher = Sha256::new();
+        let data = b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
+        for chunk in data.chunks(7) {
+            hasher.update(chunk);
+        }
+        assert_eq!(hex(&hasher.finalize()),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1");
+
+        for path in TESTDATA {
+            let data = fs::read(path).unwrap();
+            let pe = PE::from_bytes(&data).unwrap();
+            let ranges = pe.authenticode_ranges().unwrap();
+
+            // Everything is hashed but the checksum, the security entry and
+            // the certificate table
+            let hashed: usize = ranges.iter().map(|range| range.len()).sum();
+            let security = pe.opt_header.data_directories.security()
+                .map_or(0, |directory| directory.size as usize);
+            assert_eq!(hashed, data.len() - 4 - 8 - security);
+            assert!(ranges.windows(2).all(|w| w[0].end <= w[1].start));
+
+            // The signature embeds the digest it was made over
+            let Some(certificate) = pe.certificates().unwrap().pop() else {
+                continue
+            };
+            let signed_data = certificate.signed_data().unwrap();
+            let sha256 = pe.authenticode_digest(DigestAlgorithm::Sha256)
+                .unwrap();
+            assert!(signed_data.windows(32).any(|window| window == sha256),
+                "{}", path);
+            let sha1 = pe.authenticode_digest(DigestAlgorithm::Sha1).unwrap();
+            assert!(!signed_data.windows(20).any(|window| window == sha1));
+        }
+    }
+
+    #[test]
+    fn parse_signed_data() {
+        let data = fs::read("testdata/64bit/kernel32.dll").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+        let signatures = pe.signatures().unwrap();
+        assert_eq!(signatures.len(), 1);
+        let signature = &signatures[0];
+
+        let indirect_data = signature.indirect_data.as_ref().unwrap();
+        assert_eq!(indirect_data.data_type, SPC_PE_IMAGE_DATA);
+        assert_eq!(indirect_data.algorithm(), Some(DigestAlgorithm::Sha256));
+        assert_eq!(indirect_data.digest,
+            pe.authenticode_digest(DigestAlgorithm::Sha256).unwrap());
+
+        assert_eq!(signature.certificates.len(), 2);
+        let leaf = &signature.certificates[0];
+        assert_eq!(leaf.subject.common_name(), Some("Microsoft Windows"));
+        assert_eq!(leaf.issuer.to_string(), "C=US, ST=Washington, \
+            L=Redmond, O=Microsoft Corporation, \
+            CN=Microsoft Windows Production PCA 2011");
+        assert_eq!(leaf.serial_hex(), "33000003127562478161E81F04000000000312");
+        assert_eq!(leaf.not_before.to_string(), "2021-06-10 18:55:35 UTC");
+        assert_eq!(leaf.not_after.to_string(), "2022-06-09 18:55:35 UTC");
+        assert!(!leaf.is_ca());
+        assert!(signature.certificates[1].is_ca());
+
+        assert_eq!(signature.signers.len(), 1);
+        let signer = &signature.signers[0];
+        assert_eq!(signer.find_certificate(&signature.certificates),
+            Some(leaf));
+        assert!(signer.countersignatures.is_empty());
+        assert!(signer.nested_signatures.is_empty());
+        // The message digest covers the content without its SEQUENCE header
+        let (content, _) = Tlv::from_bytes(&signature.content).unwrap();
+        assert_eq!(signer.message_digest.as_deref(),
+            Some(&Sha256::digest(content.value)[..]));
+
+        assert_eq!(signer.timestamps.len(), 1);
+        let timestamp = &signer.timestamps[0];
+        assert_eq!(timestamp.info.gen_time.to_string(),
+            "2021-08-26 10:08:12 UTC");
+        assert_eq!(timestamp.info.hashed_message,
+            Sha256::digest(&signer.signature));
+        assert_eq!(timestamp.signed_data.certificates[0].subject.common_name(),
+            Some("Microsoft Time-Stamp Service"));
+
+        // Nest the signature of ntdll into the one of kernel32
+        fn encode(tag: u8, value: &[u8]) -> Vec<u8> {
+            let mut bytes = vec![tag];
+            match value.len() {
+                len @ 0..=0x7f => bytes.push(len as u8),
+                len => {
+                    let len = (len as u32).to_be_bytes();
+                    let skip = len.iter().take_while(|b| **b == 0).count();
+                    bytes.push(0x80 | (4 - skip) as u8);
+                    bytes.extend(&len[skip..]);
+                },
+            }
+            bytes.extend(value);
+            bytes
+        }
+        let other = fs::read("testdata/32bit/ntdll.dll").unwrap();
+        let other = PE::from_bytes(&other).unwrap();
+        let nested = other.certificates().unwrap()[0].signed_data().unwrap();
+        // Leave out the padding of the certificate table entry
+        let nested = Tlv::from_bytes(nested).unwrap().0.raw;
+
+        let blob = pe.certificates().unwrap()[0].signed_data().unwrap();
+        let (content_info, _) = Tlv::from_bytes(blob).unwrap();
+        let mut fields = content_info.reader();
+        let oid = fields.read(der::TAG_OID).unwrap();
+        let signed_data = fields.read_any().unwrap().reader()
+            .read(der::TAG_SEQUENCE).unwrap();
+        let mut fields = signed_data.reader().read_all().unwrap();
+        let signer = fields.pop().unwrap().reader().read_any().unwrap();
+        let mut signer_fields = signer.reader().read_all().unwrap();
+        let unauthenticated = signer_fields.pop().unwrap();
+
+        let attribute = encode(der::TAG_SEQUENCE, &[
+            encode(der::TAG_OID, &[0x2b, 6, 1, 4, 1, 0x82, 0x37, 2, 4, 1]),
+            encode(der::TAG_SET, nested),
+        ].concat());
+        let unauthenticated = encode(unauthenticated.tag,
+            &[unauthenticated.value, &attribute].concat());
+        let signer: Vec<u8> = signer_fields.iter()
+            .flat_map(|field| field.raw.to_vec())
+            .chain(unauthenticated)
+            .collect();
+        let signers = encode(der::TAG_SET, &encode(der::TAG_SEQUENCE, &signer));
+        let signed_data: Vec<u8> = fields.iter()
+            .flat_map(|field| field.raw.to_vec())
+            .chain(signers)
+            .collect();
+        let blob = encode(der::TAG_SEQUENCE, &[
+            oid.raw.to_vec(),
+            encode(der::context(0), &encode(der::TAG_SEQUENCE, &signed_data)),
+        ].concat());
+
+        let signature = SignedData::from_der(&blob).unwrap();
+        let nested = &signature.signers[0].nested_signatures;
+        assert_eq!(nested.len(), 1);
+        assert_eq!(nested[0], other.signatures().unwrap()[0]);
+        assert_eq!(signature.all_signatures().len(), 2);
+        assert_eq!(NESTED_SIGNATURE, "1.3.6.1.4.1.311.2.4.1");
+
+        assert!(matches!(SignedData::from_der(&blob[..blob.len() - 1]),
+            Err(PeError::InvalidDer)));
+    }
+
+    #[test]
+    fn verify_signatures() {
+        fn encode_base64(bytes: &[u8]) -> String {
+            const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ\
+                abcdefghijklmnopqrstuvwxyz0123456789+/";
+            bytes.chunks(3)
+                .flat_map(|chunk| {
+                    let n = chunk.iter().enumerate()
+                        .fold(0, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+                    (0..4).map(move |i| match i <= chunk.len() {
+                        true => ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize],
+                        false => b'=',
+                    })
+                })
+                .map(char::from)
+                .collect()
+        }
+
+        // Our roots are the CAs that issued the signers, the real roots are
+        // not in the testdata
+        let mut authorities = Vec::new();
+        for path in TESTDATA {
+            let data = fs::read(path).unwrap();
+            let pe = PE::from_bytes(&data).unwrap();
+            for signature in pe.signatures().unwrap() {
+                let timestamps = signature.signers[0].timestamps.iter()
+                    .flat_map(|timestamp| &timestamp.signed_data.certificates);
+                authorities.extend(signature.certificates.iter()
+                    .chain(timestamps)
+                    .filter(|certificate| certificate.is_ca())
+                    .cloned());
+            }
+        }
+        let dir = std::env::temp_dir()
+            .join(format!("pe-parser-roots-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (index, authority) in authorities.iter().enumerate() {
+            match index % 2 {
+                0 => fs::write(dir.join(format!("{}.cer", index)),
+                    &authority.raw).unwrap(),
+                _ => {
+                    let body = encode_base64(&authority.raw).as_bytes()
+                        .chunks(64)
+                        .map(|line| String::from_utf8_lossy(line) + "\n")
+                        .collect::<String>();
+                    fs::write(dir.join(format!("{}.pem", index)), format!(
+                        "-----BEGIN CERTIFICATE-----\n{}\
+                        -----END CERTIFICATE-----\n", body)).unwrap();
+                },
+            }
+        }
+        let mut store = TrustStore::from_dir(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        // None of them is self-issued, so they only help build chains
+        assert!(store.roots.is_empty());
+        assert!(!store.intermediates.is_empty());
+
+        let data = fs::read("testdata/64bit/kernel32.dll").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+        let verification = pe.verify_signatures(&store).unwrap();
+        assert!(!verification.is_valid());
+        let signature = &verification.signatures[0];
+        assert_eq!(signature.digest_algorithm, Some(DigestAlgorithm::Sha256));
+        assert_eq!(signature.chain.len(), 2);
+        // The timestamp is not trusted either, so the chain is checked now,
+        // long after the leaf expired
+        assert!(!signature.timestamps[0].is_valid());
+        assert!(matches!(&signature.failures[..], [
+            VerificationFailure::CertificateNotValidAt { subject, .. },
+            VerificationFailure::IssuerNotFound { issuer, .. },
+        ] if subject.common_name() == Some("Microsoft Windows")
+            && issuer.common_name()
+                == Some("Microsoft Root Certificate Authority 2010")));
+
+        for authority in store.intermediates.clone() {
+            store.add_root(authority);
+        }
+        for path in TESTDATA {
+            let data = fs::read(path).unwrap();
+            let pe = PE::from_bytes(&data).unwrap();
+            let verification = pe.verify_signatures(&store).unwrap();
+            assert_eq!(verification.is_valid(), !path.contains("notepad"),
+                "{}: {:?}", path, verification.signatures.iter()
+                    .flat_map(|signature| &signature.failures)
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>());
+        }
+
+        let verification = pe.verify_signatures(&store).unwrap();
+        let signature = &verification.signatures[0];
+        assert_eq!(signature.subject.as_ref().unwrap().common_name(),
+            Some("Microsoft Windows"));
+        assert_eq!(signature.time.to_string(), "2021-08-26 10:08:12 UTC");
+        assert!(signature.timestamps[0].is_valid());
+        assert_eq!(signature.chain[1].subject.common_name(),
+            Some("Microsoft Windows Production PCA 2011"));
+
+        // Patch a byte of code
+        let text = pe.section_by_name(".text").unwrap();
+        let mut patched = data.clone();
+        patched[text.pointer_to_raw_data as usize + 0x10] ^= 1;
+        let verification = PE::from_bytes(&patched).unwrap()
+            .verify_signatures(&store).unwrap();
+        assert_eq!(verification.signatures[0].failures,
+            [VerificationFailure::ImageDigestMismatch]);
+
+        // Patch a byte of the signer's signature, which the timestamp
+        // covers too
+        let signer_signature = &pe.signatures().unwrap()[0].signers[0]
+            .signature;
+        let offset = data.windows(signer_signature.len())
+            .position(|window| window == &signer_signature[..])
+            .unwrap();
+        let mut patched = data.clone();
+        patched[offset + 0x10] ^= 1;
+        let verification = PE::from_bytes(&patched).unwrap()
+            .verify_signatures(&store).unwrap();
+        let signature = &verification.signatures[0];
+        assert_eq!(signature.timestamps[0].failures,
+            [VerificationFailure::TimestampMismatch]);
+        // Without the timestamp, the leaf is checked now and has expired
+        assert!(matches!(&signature.failures[..], [
+            VerificationFailure::BadSignature { .. },
+            VerificationFailure::CertificateNotValidAt { .. },
+        ]));
+
+        // Exponents are capped like moduli, or a certificate could make
+        // the check spin for ages
+        let verification = pe.verify_signatures(&store).unwrap();
+        let [leaf, issuer] = &verification.signatures[0].chain[..] else {
+            panic!("expected a chain of two certificates")
+        };
+        assert_eq!(leaf.signature_algorithm.oid.as_str(),
+            crate::authenticode::verify::SHA256_WITH_RSA);
+        let digest = DigestAlgorithm::Sha256.digest(&leaf.tbs);
+        let mut key = issuer.rsa_public_key().unwrap();
+        assert!(key.verify_pkcs1v15(DigestAlgorithm::Sha256, &digest,
+            &leaf.signature));
+        // Leading zeroes do not count
+        key.exponent.splice(0..0, [0; 8]);
+        assert!(key.verify_pkcs1v15(DigestAlgorithm::Sha256, &digest,
+            &leaf.signature));
+        key.exponent = vec![0xff; 4 << 20];
+        let start = Instant::now();
+        assert!(!key.verify_pkcs1v15(DigestAlgorithm::Sha256, &digest,
+            &leaf.signature));
+        assert!(start.elapsed().as_secs() < 1);
+
+        // Signers need the codeSigning purpose and timestamp authorities
+        // the timeStamping one, if their certificates restrict purposes
+        let signed_data = &pe.signatures().unwrap()[0];
+        let verification = verify_signature(&pe, signed_data, &store)
+            .unwrap();
+        assert!(verification.is_valid());
+        let leaf = &verification.chain[0];
+        let tsa = &verification.timestamps[0].chain[0];
+        let with_usage = |certificates: &mut Vec<X509Certificate>,
+                raw: &[u8], usage: Option<&[u8]>| {
+            let certificate = certificates.iter_mut()
+                .find(|certificate| certificate.raw == raw)
+                .unwrap();
+            certificate.extensions
+                .retain(|extension| extension.oid != EXTENDED_KEY_USAGE);
+            if let Some(usage) = usage {
+                certificate.extensions.push(Extension {
+                    oid: der::Oid(EXTENDED_KEY_USAGE.to_string()),
+                    critical: false,
+                    value: usage.to_vec(),
+                });
+            }
+        };
+        // SEQUENCE { serverAuth }, then SEQUENCE { codeSigning }
+        let server_auth = [0x30, 0x0a, 0x06, 0x08, 0x2b, 0x06, 0x01, 0x05,
+            0x05, 0x07, 0x03, 0x01];
+        let code_signing = [0x30, 0x0a, 0x06, 0x08, 0x2b, 0x06, 0x01, 0x05,
+            0x05, 0x07, 0x03, 0x03];
+
+        let mut modified = signed_data.clone();
+        with_usage(&mut modified.certificates, &leaf.raw, Some(&server_auth));
+        let verification = verify_signature(&pe, &modified, &store).unwrap();
+        assert_eq!(verification.failures, [
+            VerificationFailure::KeyUsageNotAllowed {
+                subject: leaf.subject.clone(),
+                usage: der::Oid(CODE_SIGNING.to_string()),
+            },
+        ]);
+        with_usage(&mut modified.certificates, &leaf.raw, None);
+        assert!(verify_signature(&pe, &modified, &store).unwrap()
+            .is_valid());
+
+        let mut modified = signed_data.clone();
+        let timestamp = &mut modified.signers[0].timestamps[0];
+        with_usage(&mut timestamp.signed_data.certificates, &tsa.raw,
+            Some(&code_signing));
+        let verification = verify_signature(&pe, &modified, &store).unwrap();
+        assert_eq!(verification.timestamps[0].failures, [
+            VerificationFailure::KeyUsageNotAllowed {
+                subject: tsa.subject.clone(),
+                usage: der::Oid(TIME_STAMPING.to_string()),
+            },
+        ]);
+
+        // Version 3 signers name their certificate by key identifier
+        let mut modified = signed_data.clone();
+        let signer = &mut modified.signers[0];
+        signer.version = 3;
+        signer.issuer = None;
+        signer.serial = None;
+        signer.subject_key_id = leaf.subject_key_id().map(<[u8]>::to_vec);
+        assert!(signer.subject_key_id.is_some());
+        let verification = verify_signature(&pe, &modified, &store).unwrap();
+        assert!(verification.is_valid(), "{:?}", verification.failures);
+        modified.signers[0].subject_key_id = Some(vec![0; 20]);
+        let verification = verify_signature(&pe, &modified, &store).unwrap();
+        assert_eq!(verification.failures,
+            [VerificationFailure::SignerCertificateNotFound]);
+
+        // Content that is not DER, as a nested signature may carry, is
+        // reported rather than failing every signature
+        let mut modified = signed_data.clone();
+        modified.content = vec![0x30];
+        modified.indirect_data = None;
+        let verification = verify_signature(&pe, &modified, &store).unwrap();
+        assert_eq!(verification.failures,
+            [VerificationFailure::NotAuthenticode]);
+    }
+
+    #[test]
+    fn verify_page_hashes() {
+        for path in TESTDATA {
+            let data = fs::read(path).unwrap();
+            let pe = PE::from_bytes(&data).unwrap();
+            let Some(signed) = pe.page_hashes().unwrap() else {
+                assert!(path.contains("notepad"));
+                continue
+            };
+            assert_eq!(signed.algorithm, DigestAlgorithm::Sha256);
+            assert_eq!(signed, pe.compute_page_hashes(signed.algorithm));
+            assert!(signed.modified_pages(&pe).is_empty());
+        }
+
+        let data = fs::read("testdata/64bit/kernel32.dll").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+        let signed = pe.page_hashes().unwrap().unwrap();
+        assert_eq!(signed.pages.len(), 190);
+        assert_eq!(signed.pages[1].offset, 0x400);
+        let last = signed.pages.last().unwrap();
+        assert_eq!(last.offset, 0xb8400);
+        assert_eq!(last.digest, [0; 32]);
+        let ranges = signed.ranges();
+        assert_eq!(ranges[0], 0..0x400);
+        assert_eq!(ranges[1], 0x400..0x1400);
+
+        // A patched byte is found in its page
+        let mut patched = data.clone();
+        patched[0x5123] ^= 1;
+        let patched = PE::from_bytes(&patched).unwrap();
+        assert_eq!(signed.modified_pages(&patched), vec![(0x4400..0x5400)]);
+
+        // The checksum is left out of the header page, the rest is not
+        let mut patched = data.clone();
+        let checksum = pe.dos_header.e_lfanew as usize + 24 + 64;
+        patched[checksum] ^= 1;
+        assert!(signed.modified_pages(&PE::from_bytes(&patched).unwrap())
+            .is_empty());
+        patched[0x80] ^= 1;
+        assert_eq!(signed.modified_pages(&PE::from_bytes(&patched).unwrap()),
+            vec![(0..0x400)]);
+    }
+
+    #[test]
+    fn verify_checksum() {
+        for path in TESTDATA {
+            let data = fs::read(path).unwrap();
+            let pe = PE::from_bytes(&data).unwrap();
+            assert_ne!(pe.opt_header.win_fields.checksum(), 0, "{}", path);
+            assert!(pe.checksum_valid(), "{}", path);
+
+            // The checksum field itself does not count
+            let mut patched = data.clone();
+            let checksum = pe.dos_header.e_lfanew as usize + 24 + 64;
+            patched[checksum..checksum + 4].fill(0);
+            let patched = PE::from_bytes(&patched).unwrap();
+            assert_eq!(patched.compute_checksum(), pe.compute_checksum());
+            assert!(!patched.checksum_valid());
+        }
+
+        let data = fs::read("testdata/64bit/notepad.exe").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+        let mut patched = data.clone();
+        patched[0x80] ^= 1;
+        assert!(!PE::from_bytes(&patched).unwrap().checksum_valid());
+        // An odd trailing byte is a word of its own
+        patched = data.clone();
+        patched.push(0x42);
+        assert_eq!(PE::from_bytes(&patched).unwrap().compute_checksum(),
+            pe.compute_checksum() + 0x42 + 1);
+    }
+
+    #[test]
+    fn decode_rich_header() {
+        for path in TESTDATA {
+            let data = fs::read(path).unwrap();
+            let pe = PE::from_bytes(&data).unwrap();
+            let rich = pe.rich_header().unwrap().unwrap();
+            assert_eq!(rich.offset, 0x80);
+            assert!(rich.checksum_valid(), "{}", path);
+            assert!(rich.data.starts_with(b"DanS\0\0\0\0"));
+        }
+
+        let data = fs::read("testdata/64bit/kernel32.dll").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+        let rich = pe.rich_header().unwrap().unwrap();
+        assert_eq!(rich.key, 0xa6aadaa7);
+        assert_eq!(rich.entries.len(), 9);
+        assert_eq!(rich.data.len(), 16 + 9 * 8);
+
+        let implib = rich.entries[0];
+        assert_eq!((implib.product_id, implib.build, implib.count),
+            (257, 27412, 4));
+        assert_eq!(implib.product_name(), Some("Implib1400"));
+        assert_eq!(implib.visual_studio(), Some("Visual Studio 2017 (14.1)"));
+        assert_eq!(rich.entries[1].product_name(), Some("Implib900"));
+        assert_eq!(rich.entries[1].visual_studio(),
+            Some("Visual Studio 2008 (9.0)"));
+        let imports = rich.entries[2];
+        assert_eq!((imports.product_id, imports.count), (1, 1325));
+        assert_eq!(imports.product_name(), Some("Import0"));
+        assert_eq!(imports.visual_studio(), None);
+
+        // Changing a count breaks the checksum
+        let mut patched = data.clone();
+        patched[0x80 + 16 + 4] ^= 1;
+        let rich = PE::from_bytes(&patched).unwrap().rich_header().unwrap()
+            .unwrap();
+        assert_eq!(rich.entries[0].count, 5);
+        assert!(!rich.checksum_valid());
+
+        // Without `DanS` the header is malformed, without `Rich` it is gone
+        patched = data.clone();
+        patched[0x80] ^= 1;
+        assert!(matches!(PE::from_bytes(&patched).unwrap().rich_header(),
+            Err(PeError::InvalidRichHeader)));
+        patched = data.clone();
+        patched[0xd8] ^= 1;
+        assert_eq!(PE::from_bytes(&patched).unwrap().rich_header().unwrap(),
+            None);
+    }
+
+    #[test]
+    fn compute_imphash_and_rich_hash() {
+        fn hex(bytes: &[u8]) -> String {
+            bytes.iter().map(|b| format!("{:02x}", b)).collect()
+        }
+        assert_eq!(hex(&Md5::digest(b"")), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(hex(&Md5::digest(
+                b"The quick brown fox jumps over the lazy dog")),
+            "9e107d9d372bb6826bd81d3542a419d6");
+        let mut md5 = Md5::new();
+        for chunk in [b"The quick brown fox ".as_slice(), b"jumps over ",
+                b"the lazy dog"] {
+            md5.update(chunk);
+        }
+        assert_eq!(hex(&md5.finalize()), "9e107d9d372bb6826bd81d3542a419d6");
+
+        assert_eq!(ordinal_name("WS2_32.dll", 115), Some("WSAStartup"));
+        assert_eq!(ordinal_name("wsock32.dll", 151), Some("__WSAFDIsSet"));
+        assert_eq!(ordinal_name("OLEAUT32.DLL", 2), Some("SysAllocString"));
+        assert_eq!(ordinal_name("oleaut32.dll", 1), None);
+        assert_eq!(ordinal_name("comctl32.dll", 345), None);
+
+        // Computed with the algorithm of pefile
+        let expected = [
+            ("testdata/32bit/kernel32.dll",
+                Some("5fcb9c1087c59bc90bcb51139d6cc1c2"),
+                "a8dcd6bd156b8beeb369330506dbb5dd"),
+            ("testdata/32bit/notepad.exe",
+                Some("291bf41874edcdb21d447b43ee0e6b1f"),
+                "7c30c87925375ef8ae4048f99c9d645f"),
+            ("testdata/32bit/ntdll.dll", None,
+                "582854a1d88be7095a0c5cddf8de5f91"),
+            ("testdata/32bit/user32.dll",
+                Some("ee570bfe36d4eb273240b9d6487a29dd"),
+                "44e37815909be5e7e7453284e5e3526c"),
+            ("testdata/64bit/kernel32.dll",
+                Some("d4db3fb69e1eaf44e96269f1a467dcb9"),
+                "4549320af6790d410f09ddc3bab86c86"),
+            ("testdata/64bit/notepad.exe",
+                Some("670212bd5fae78855c331eddeffdd4eb"),
+                "f3e263e22b7d607bb2ec5e2b5cf4cf38"),
+            ("testdata/64bit/ntdll.dll", None,
+                "14bd645d53948484742bbe953ae2126d"),
+            ("testdata/64bit/user32.dll",
+                Some("99067ec4d783a275e8470a80ee6a44e7"),
+                "683a724c1a71fb5ae136ecb7c948f97d"),
+        ];
+        for (path, imphash, rich_hash) in expected {
+            let data = fs::read(path).unwrap();
+            let pe = PE::from_bytes(&data).unwrap();
+            assert_eq!(pe.imphash().unwrap().as_deref(), imphash, "{}", path);
+            let rich = pe.rich_header().unwrap().unwrap();
+            assert_eq!(rich.hash().as_deref(), Some(rich_hash), "{}", path);
+        }
+
+        // pefile finds no header when `DanS` is moved off 0x80
+        let data = fs::read("testdata/64bit/notepad.exe").unwrap();
+        let pe = PE::from_bytes(&data).unwrap();
+        let mut shifted = data[..pe.dos_header.e_lfanew as usize].to_vec();
+        shifted.splice(0x80..0x80, [0; 16]);
+        let rich = RichHeader::from_bytes(&shifted).unwrap().unwrap();
+        assert_eq!(rich.offset, 0x90);
+        assert_eq!(rich.hash(), None);
+    }
 }
@@ -5,7 +5,7 @@ use crate::{
 /// Helper function that consumes 1 byte(u8) from `bytes` and returns a u16
 /// as result. In case `bytes` buffer is too small it returns an error.
 pub fn take_u8(bytes: &[u8]) -> Result<(u8, &[u8])> {
-    if bytes.len() < 1 {
+    if bytes.is_empty() {
         return Err(PeError::BufferTooSmall)
     }
 
@@ -17,7 +17,7 @@ pub fn take_u8(bytes: &[u8]) -> Result<(u8, &[u8])> {
 /// Helper function that consumes 1 byte(u8) from `bytes` and returns a u16
 /// as result. In case `bytes` buffer is too small it returns an error.
 pub fn take_u16(bytes: &[u8]) -> Result<(u16, &[u8])> {
-    if bytes.len() < 1 {
+    if bytes.len() < 2 {
         return Err(PeError::BufferTooSmall)
     }
 
@@ -50,3 +50,45 @@ pub fn take_u64(bytes: &[u8]) -> Result<(u64, &[u8])> {
     Ok((u64::from_le_bytes(left.try_into()?), right))
 }
 
+
+/// Helper function that consumes `len` bytes from `bytes` and returns them as
+/// a slice. In case `bytes` buffer is too small it returns an error.
+pub fn take_bytes(bytes: &[u8], len: usize) -> Result<(&[u8], &[u8])> {
+    if bytes.len() < len {
+        return Err(PeError::BufferTooSmall)
+    }
+
+    Ok(bytes.split_at(len))
+}
+
+/// Helper function that consumes `count` UTF-16 code units from `bytes` and
+/// returns them decoded as a String. Invalid surrogates are replaced with
+/// U+FFFD. In case `bytes` buffer is too small it returns an error.
+pub fn take_utf16(bytes: &[u8], count: usize) -> Result<(String, &[u8])> {
+    let len = count.checked_mul(2).ok_or(PeError::BufferTooSmall)?;
+    let (string, bytes) = take_bytes(bytes, len)?;
+
+    Ok((utf16_to_string(string), bytes))
+}
+
+/// Decodes a little endian UTF-16 buffer into a String. A trailing odd byte is
+/// ignored and invalid surrogates are replaced with U+FFFD.
+pub fn utf16_to_string(bytes: &[u8]) -> String {
+    let units = bytes.chunks_exact(2)
+        .map(|unit| u16::from_le_bytes([unit[0], unit[1]]));
+    char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+/// Helper function that consumes a NUL terminated UTF-16 string from `bytes`
+/// and returns it without the terminator. In case `bytes` ends before the
+/// terminator it returns an error.
+pub fn take_utf16_cstr(bytes: &[u8]) -> Result<(String, &[u8])> {
+    let count = bytes.chunks_exact(2)
+        .position(|unit| unit == [0, 0])
+        .ok_or(PeError::BufferTooSmall)?;
+    let (string, bytes) = take_utf16(bytes, count)?;
+
+    Ok((string, &bytes[2..]))
+}
@@ -0,0 +1,117 @@
+use crate::{
+    error::Result,
+    parsing::*,
+    resources::{ResourceId, ResourceTree, ResourceType},
+    PE,
+};
+
+#[derive(Debug, PartialEq)]
+pub enum AcceleratorFlag {
+    /// The key is a virtual-key code rather than an ASCII character.
+    VirtKey = 0x01,
+    /// No top-level menu item is highlighted when the accelerator is used.
+    NoInvert = 0x02,
+    /// The SHIFT key must be held down.
+    Shift = 0x04,
+    /// The CTRL key must be held down.
+    Control = 0x08,
+    /// The ALT key must be held down.
+    Alt = 0x10,
+    /// The entry is the last one of the table.
+    End = 0x80,
+    /// Invalid
+    Invalid = 0x0,
+}
+
+impl AcceleratorFlag {
+    pub fn to_vec(value: u16) -> Vec<Self> {
+        let mut flags = Vec::new();
+
+        for i in 0..8 {
+            let new_flag = match (1 << i) & value {
+                0x01 => Self::VirtKey,
+                0x02 => Self::NoInvert,
+                0x04 => Self::Shift,
+                0x08 => Self::Control,
+                0x10 => Self::Alt,
+                0x80 => Self::End,
+                _ => Self::Invalid,
+            };
+            if new_flag != Self::Invalid {
+                flags.push(new_flag);
+            }
+        }
+        flags
+    }
+}
+
+/// The `ACCELTABLEENTRY` structure, one keystroke of a table
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Accelerator {
+    /// Describes the keystroke, see `AcceleratorFlag`.
+    pub flags: u16,
+    /// An ASCII character or a virtual-key code, depending on the flags.
+    pub key: u16,
+    /// The identifier passed with the `WM_COMMAND` message.
+    pub id: u16,
+    /// Padding, should be 0.
+    pub padding: u16,
+}
+
+impl Accelerator {
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8])> {
+        let (flags, bytes) = take_u16(bytes)?;
+        let (key, bytes) = take_u16(bytes)?;
+        let (id, bytes) = take_u16(bytes)?;
+        let (padding, bytes) = take_u16(bytes)?;
+
+        Ok((Self { flags, key, id, padding }, bytes))
+    }
+
+    pub fn len() -> usize {
+        8usize
+    }
+
+    pub fn flags(&self) -> Vec<AcceleratorFlag> {
+        AcceleratorFlag::to_vec(self.flags)
+    }
+}
+
+/// An `RT_ACCELERATOR` resource
+#[derive(Debug, Clone, PartialEq)]
+pub struct AcceleratorTable {
+    pub name: ResourceId,
+    pub language: ResourceId,
+    pub entries: Vec<Accelerator>,
+}
+
+impl AcceleratorTable {
+    /// Decodes the entries of a table, up to the one flagged as the last or
+    /// the end of `bytes`, whichever comes first
+    pub fn parse_entries(bytes: &[u8]) -> Result<Vec<Accelerator>> {
+        let mut entries = Vec::new();
+        let mut bytes = bytes;
+        while bytes.len() >= Accelerator::len() {
+            let (entry, rest) = Accelerator::from_bytes(bytes)?;
+            entries.push(entry);
+            if entry.flags & AcceleratorFlag::End as u16 != 0 {
+                break;
+            }
+            bytes = rest;
+        }
+        Ok(entries)
+    }
+
+    /// Decodes every `RT_ACCELERATOR` resource of `pe`
+    pub fn from_pe(pe: &PE) -> Result<Vec<Self>> {
+        let tree = ResourceTree::from_pe(pe)?;
+        tree.resources_of_type(ResourceType::Accelerator)
+            .into_iter()
+            .map(|resource| Ok(Self {
+                entries: Self::parse_entries(&resource.data(pe)?)?,
+                name: resource.name,
+                language: resource.language,
+            }))
+            .collect()
+    }
+}
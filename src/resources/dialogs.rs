@@ -0,0 +1,246 @@
+use crate::{
+    error::Result,
+    parsing::*,
+    resources::{ResourceId, ResourceTree, ResourceType},
+    PE,
+};
+
+/// `DS_SETFONT`, the template carries a font. Also part of `DS_SHELLFONT`.
+const DS_SETFONT: u32 = 0x40;
+
+/// A field that holds nothing, a 16-bit ordinal or a name. Ordinals are
+/// marked by a leading 0xFFFF unit.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NameOrOrdinal {
+    None,
+    Ordinal(u16),
+    Name(String),
+}
+
+impl NameOrOrdinal {
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8])> {
+        let (first, rest) = take_u16(bytes)?;
+        match first {
+            0x0000 => Ok((Self::None, rest)),
+            0xffff => {
+                let (ordinal, rest) = take_u16(rest)?;
+                Ok((Self::Ordinal(ordinal), rest))
+            },
+            _ => {
+                let (name, rest) = take_utf16_cstr(bytes)?;
+                Ok((Self::Name(name), rest))
+            },
+        }
+    }
+}
+
+/// The font used for the text of a dialog box and its controls
+#[derive(Debug, Clone, PartialEq)]
+pub struct DialogFont {
+    /// Point size of the font.
+    pub point_size: u16,
+    /// Weight of the font. Extended templates only, 0 otherwise.
+    pub weight: u16,
+    /// Whether the font is italic. Extended templates only.
+    pub italic: bool,
+    /// Character set of the font. Extended templates only, 0 otherwise.
+    pub charset: u8,
+    /// Name of the typeface.
+    pub typeface: String,
+}
+
+/// A control of a dialog box, from either a `DLGITEMTEMPLATE` or a
+/// `DLGITEMTEMPLATEEX`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DialogControl {
+    /// Context help identifier. Extended templates only, 0 otherwise.
+    pub help_id: u32,
+    /// Extended window styles.
+    pub ext_style: u32,
+    /// Window styles.
+    pub style: u32,
+    /// Coordinates and size, in dialog box units.
+    pub x: i16,
+    pub y: i16,
+    pub cx: i16,
+    pub cy: i16,
+    /// Control identifier. Only 16 bits wide in standard templates.
+    pub id: u32,
+    /// The window class, either a predefined ordinal or a registered name.
+    pub class: NameOrOrdinal,
+    /// The initial text, or the ordinal of a resource such as an icon.
+    pub title: NameOrOrdinal,
+    /// Data passed to the control in `WM_CREATE`.
+    pub creation_data: Vec<u8>,
+}
+
+impl DialogControl {
+    /// Returns the name of the window class, resolving the ordinals of the
+    /// predefined classes
+    pub fn class_name(&self) -> Option<&str> {
+        match &self.class {
+            NameOrOrdinal::Ordinal(0x80) => Some("Button"),
+            NameOrOrdinal::Ordinal(0x81) => Some("Edit"),
+            NameOrOrdinal::Ordinal(0x82) => Some("Static"),
+            NameOrOrdinal::Ordinal(0x83) => Some("ListBox"),
+            NameOrOrdinal::Ordinal(0x84) => Some("ScrollBar"),
+            NameOrOrdinal::Ordinal(0x85) => Some("ComboBox"),
+            NameOrOrdinal::Name(name) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+/// A `DLGTEMPLATE` or `DLGTEMPLATEEX` along with its controls
+#[derive(Debug, Clone, PartialEq)]
+pub struct DialogTemplate {
+    /// Set for `DLGTEMPLATEEX`.
+    pub extended: bool,
+    /// Context help identifier. Extended templates only, 0 otherwise.
+    pub help_id: u32,
+    /// Extended window styles.
+    pub ext_style: u32,
+    /// Window styles.
+    pub style: u32,
+    /// Coordinates and size, in dialog box units.
+    pub x: i16,
+    pub y: i16,
+    pub cx: i16,
+    pub cy: i16,
+    /// The menu resource of the dialog box.
+    pub menu: NameOrOrdinal,
+    /// The window class of the dialog box, the predefined one if `None`.
+    pub class: NameOrOrdinal,
+    /// The title of the dialog box.
+    pub title: String,
+    /// The font, present when the style has `DS_SETFONT`.
+    pub font: Option<DialogFont>,
+    pub controls: Vec<DialogControl>,
+}
+
+impl DialogTemplate {
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let (version, bytes) = take_u16(data)?;
+        let (signature, _) = take_u16(bytes)?;
+        let extended = version == 1 && signature == 0xffff;
+
+        let (help_id, ext_style, style, bytes) = if extended {
+            let (_, bytes) = take_u32(data)?;
+            let (help_id, bytes) = take_u32(bytes)?;
+            let (ext_style, bytes) = take_u32(bytes)?;
+            let (style, bytes) = take_u32(bytes)?;
+            (help_id, ext_style, style, bytes)
+        } else {
+            let (style, bytes) = take_u32(data)?;
+            let (ext_style, bytes) = take_u32(bytes)?;
+            (0, ext_style, style, bytes)
+        };
+        let (count, bytes) = take_u16(bytes)?;
+        let (x, bytes) = take_u16(bytes)?;
+        let (y, bytes) = take_u16(bytes)?;
+        let (cx, bytes) = take_u16(bytes)?;
+        let (cy, bytes) = take_u16(bytes)?;
+        let (menu, bytes) = NameOrOrdinal::from_bytes(bytes)?;
+        let (class, bytes) = NameOrOrdinal::from_bytes(bytes)?;
+        let (title, mut bytes) = take_utf16_cstr(bytes)?;
+
+        let mut font = None;
+        if style & DS_SETFONT != 0 {
+            let (point_size, rest) = take_u16(bytes)?;
+            let (weight, italic, charset, rest) = if extended {
+                let (weight, rest) = take_u16(rest)?;
+                let (italic, rest) = take_u8(rest)?;
+                let (charset, rest) = take_u8(rest)?;
+                (weight, italic != 0, charset, rest)
+            } else {
+                (0, false, 0, rest)
+            };
+            let (typeface, rest) = take_utf16_cstr(rest)?;
+            font = Some(DialogFont {
+                point_size, weight, italic, charset, typeface
+            });
+            bytes = rest;
+        }
+
+        let mut controls = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let (control, rest) = parse_control(data, bytes, extended)?;
+            controls.push(control);
+            bytes = rest;
+        }
+
+        Ok(Self {
+            extended, help_id, ext_style, style, x: x as i16, y: y as i16,
+            cx: cx as i16, cy: cy as i16, menu, class, title, font, controls
+        })
+    }
+}
+
+/// Parses the control at `bytes`, which sits inside `data`. Controls are
+/// aligned on 32 bits relative to the start of the template.
+fn parse_control<'a>(data: &[u8], bytes: &'a [u8], extended: bool)
+        -> Result<(DialogControl, &'a [u8])> {
+    let bytes = align_to_dword(data, bytes)?;
+
+    let (help_id, ext_style, style, bytes) = if extended {
+        let (help_id, bytes) = take_u32(bytes)?;
+        let (ext_style, bytes) = take_u32(bytes)?;
+        let (style, bytes) = take_u32(bytes)?;
+        (help_id, ext_style, style, bytes)
+    } else {
+        let (style, bytes) = take_u32(bytes)?;
+        let (ext_style, bytes) = take_u32(bytes)?;
+        (0, ext_style, style, bytes)
+    };
+    let (x, bytes) = take_u16(bytes)?;
+    let (y, bytes) = take_u16(bytes)?;
+    let (cx, bytes) = take_u16(bytes)?;
+    let (cy, bytes) = take_u16(bytes)?;
+    let (id, bytes) = if extended {
+        take_u32(bytes)?
+    } else {
+        let (id, bytes) = take_u16(bytes)?;
+        (id as u32, bytes)
+    };
+    let (class, bytes) = NameOrOrdinal::from_bytes(bytes)?;
+    let (title, bytes) = NameOrOrdinal::from_bytes(bytes)?;
+
+    let (extra, bytes) = take_u16(bytes)?;
+    let (creation_data, bytes) = take_bytes(bytes, extra as usize)?;
+
+    Ok((DialogControl {
+        help_id, ext_style, style, x: x as i16, y: y as i16, cx: cx as i16,
+        cy: cy as i16, id, class, title,
+        creation_data: creation_data.to_vec(),
+    }, bytes))
+}
+
+/// Skips the padding that aligns `bytes` on 32 bits relative to `data`
+fn align_to_dword<'a>(data: &[u8], bytes: &'a [u8]) -> Result<&'a [u8]> {
+    let offset = data.len() - bytes.len();
+    let padding = (4 - offset % 4) % 4;
+    Ok(take_bytes(bytes, padding)?.1)
+}
+
+/// An `RT_DIALOG` resource
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dialog {
+    pub name: ResourceId,
+    pub language: ResourceId,
+    pub template: DialogTemplate,
+}
+
+impl Dialog {
+    /// Decodes every `RT_DIALOG` resource of `pe`
+    pub fn from_pe(pe: &PE) -> Result<Vec<Self>> {
+        let tree = ResourceTree::from_pe(pe)?;
+        tree.resources_of_type(ResourceType::Dialog)
+            .into_iter()
+            .map(|resource| Ok(Self {
+                template: DialogTemplate::from_bytes(&resource.data(pe)?)?,
+                name: resource.name,
+                language: resource.language,
+            }))
+            .collect()
+    }
+}
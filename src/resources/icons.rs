@@ -0,0 +1,209 @@
+use crate::{
+    error::{Result, PeError},
+    parsing::*,
+    resources::{Resource, ResourceId, ResourceTree, ResourceType},
+    PE,
+};
+
+/// Whether a group holds icons or cursors. The discriminant is the `type`
+/// field shared by the group header and the `.ico`/`.cur` file header.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GroupKind {
+    Icon = 1,
+    Cursor = 2,
+}
+
+impl GroupKind {
+    /// The resource type of the group
+    pub fn group_type(&self) -> ResourceType {
+        match self {
+            Self::Icon => ResourceType::GroupIcon,
+            Self::Cursor => ResourceType::GroupCursor,
+        }
+    }
+
+    /// The resource type of the images the group references
+    pub fn image_type(&self) -> ResourceType {
+        match self {
+            Self::Icon => ResourceType::Icon,
+            Self::Cursor => ResourceType::Cursor,
+        }
+    }
+}
+
+/// A `GRPICONDIRENTRY` or the cursor flavour of it, describing one image of
+/// a group
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GroupEntry {
+    /// Width in pixels. For icons, 0 means 256.
+    pub width: u16,
+    /// Height in pixels. For cursors, this counts both the XOR and the AND
+    /// masks and is thus twice the height of the image.
+    pub height: u16,
+    /// Number of colors in the palette, 0 if more than 256. Icons only.
+    pub color_count: u8,
+    /// Reserved, must be 0. Icons only.
+    pub reserved: u8,
+    /// Number of color planes.
+    pub planes: u16,
+    /// Number of bits per pixel.
+    pub bit_count: u16,
+    /// The size, in bytes, of the image resource.
+    pub bytes_in_res: u32,
+    /// The ID of the `RT_ICON` or `RT_CURSOR` resource holding the image.
+    pub id: u16,
+}
+
+impl GroupEntry {
+    pub fn from_bytes(bytes: &[u8], kind: GroupKind) -> Result<(Self, &[u8])> {
+        let (width, height, color_count, reserved, bytes) = match kind {
+            GroupKind::Icon => {
+                let (width, bytes) = take_u8(bytes)?;
+                let (height, bytes) = take_u8(bytes)?;
+                let (color_count, bytes) = take_u8(bytes)?;
+                let (reserved, bytes) = take_u8(bytes)?;
+                (width as u16, height as u16, color_count, reserved, bytes)
+            },
+            GroupKind::Cursor => {
+                let (width, bytes) = take_u16(bytes)?;
+                let (height, bytes) = take_u16(bytes)?;
+                (width, height, 0, 0, bytes)
+            },
+        };
+        let (planes, bytes) = take_u16(bytes)?;
+        let (bit_count, bytes) = take_u16(bytes)?;
+        let (bytes_in_res, bytes) = take_u32(bytes)?;
+        let (id, bytes) = take_u16(bytes)?;
+
+        Ok((Self {
+            width, height, color_count, reserved, planes, bit_count,
+            bytes_in_res, id
+        }, bytes))
+    }
+
+    pub fn len() -> usize {
+        14usize
+    }
+}
+
+/// An `RT_GROUP_ICON` or `RT_GROUP_CURSOR` resource, with the images it
+/// references
+#[derive(Debug, Clone, PartialEq)]
+pub struct IconGroup {
+    pub kind: GroupKind,
+    pub name: ResourceId,
+    pub language: ResourceId,
+    pub entries: Vec<GroupEntry>,
+    /// The image resource of each entry, in the same order
+    pub images: Vec<Resource>,
+    /// The entries whose image resource does not exist, left out of
+    /// `entries`
+    pub missing: Vec<GroupEntry>,
+}
+
+impl IconGroup {
+    /// Parses the content of a group resource into its entries
+    pub fn parse_entries(bytes: &[u8], kind: GroupKind)
+            -> Result<Vec<GroupEntry>> {
+        let (_reserved, bytes) = take_u16(bytes)?;
+        let (group_type, bytes) = take_u16(bytes)?;
+        let (count, mut bytes) = take_u16(bytes)?;
+        if group_type != kind as u16 {
+            return Err(PeError::InvalidIconGroup);
+        }
+
+        let mut entries = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let (entry, rest) = GroupEntry::from_bytes(bytes, kind)?;
+            entries.push(entry);
+            bytes = rest;
+        }
+        Ok(entries)
+    }
+
+    /// Collects the icon groups, then the cursor groups, of `pe`. Images are
+    /// looked up in the language of the group first, then in any language;
+    /// entries whose image is missing are set aside in `missing`.
+    pub fn from_pe(pe: &PE) -> Result<Vec<Self>> {
+        let tree = ResourceTree::from_pe(pe)?;
+        let mut groups = Vec::new();
+        for kind in [GroupKind::Icon, GroupKind::Cursor] {
+            let images = tree.resources_of_type(kind.image_type());
+            for group in tree.resources_of_type(kind.group_type()) {
+                let mut entries = Vec::new();
+                let mut found = Vec::new();
+                let mut missing = Vec::new();
+                for entry in Self::parse_entries(&group.data(pe)?, kind)? {
+                    let name = ResourceId::Id(entry.id);
+                    let image = images.iter()
+                        .find(|image| image.name == name
+                            && image.language == group.language)
+                        .or_else(|| images.iter()
+                            .find(|image| image.name == name));
+                    match image {
+                        Some(image) => {
+                            entries.push(entry);
+                            found.push(image.clone());
+                        },
+                        None => missing.push(entry),
+                    }
+                }
+
+                groups.push(Self {
+                    kind,
+                    name: group.name,
+                    language: group.language,
+                    entries,
+                    images: found,
+                    missing,
+                });
+            }
+        }
+        Ok(groups)
+    }
+
+    /// Builds the `.ico` or `.cur` file of the group. The images are stored
+    /// one after the other, after the directory. Cursor images start with
+    /// their hotspot, which moves into the directory entry.
+    pub fn to_file(&self, pe: &PE) -> Result<Vec<u8>> {
+        let images = self.images.iter()
+            .map(|image| image.data(pe))
+            .collect::<Result<Vec<_>>>()?;
+
+        let count = self.entries.len();
+        let mut directory = Vec::with_capacity(6 + count * 16);
+        directory.extend(0u16.to_le_bytes());
+        directory.extend((self.kind as u16).to_le_bytes());
+        directory.extend((count as u16).to_le_bytes());
+
+        let mut data = Vec::new();
+        let mut offset = 6 + count * 16;
+        for (entry, image) in self.entries.iter().zip(&images) {
+            let (image, width, height, color_count, field1, field2) =
+                    match self.kind {
+                GroupKind::Icon => {
+                    (&image[..], entry.width as u8, entry.height as u8,
+                        entry.color_count, entry.planes, entry.bit_count)
+                },
+                GroupKind::Cursor => {
+                    let (x, rest) = take_u16(image)?;
+                    let (y, rest) = take_u16(rest)?;
+                    (rest, entry.width as u8, (entry.height / 2) as u8, 0, x,
+                        y)
+                },
+            };
+
+            directory.extend([width, height, color_count, 0]);
+            directory.extend(field1.to_le_bytes());
+            directory.extend(field2.to_le_bytes());
+            directory.extend((image.len() as u32).to_le_bytes());
+            directory.extend((offset as u32).to_le_bytes());
+
+            data.extend_from_slice(image);
+            offset += image.len();
+        }
+
+        directory.extend(data);
+        Ok(directory)
+    }
+}
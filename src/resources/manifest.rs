@@ -0,0 +1,348 @@
+use crate::{
+    error::Result,
+    resources::{ResourceId, ResourceTree, ResourceType},
+    PE,
+};
+
+/// How the loader uses a manifest, given by the ID it is stored under
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ManifestKind {
+    /// `CREATEPROCESS_MANIFEST_RESOURCE_ID`, used by executables
+    CreateProcess,
+    /// `ISOLATIONAWARE_MANIFEST_RESOURCE_ID`, used by DLLs when loaded
+    IsolationAware,
+    /// `ISOLATIONAWARE_NOSTATICIMPORT_MANIFEST_RESOURCE_ID`, used by DLLs
+    /// for dynamic loads only
+    IsolationAwareNoStaticImport,
+}
+
+impl ManifestKind {
+    /// Returns the kind of manifest stored under `id`, if it is one of the
+    /// IDs the loader looks at
+    pub fn from_id(id: &ResourceId) -> Option<Self> {
+        match id.id()? {
+            1 => Some(Self::CreateProcess),
+            2 => Some(Self::IsolationAware),
+            3 => Some(Self::IsolationAwareNoStaticImport),
+            _ => None,
+        }
+    }
+}
+
+/// The privileges requested through `requestedExecutionLevel`
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecutionLevel {
+    AsInvoker,
+    HighestAvailable,
+    RequireAdministrator,
+    Other(String),
+}
+
+impl From<&str> for ExecutionLevel {
+    fn from(value: &str) -> ExecutionLevel {
+        match value {
+            "asInvoker" => ExecutionLevel::AsInvoker,
+            "highestAvailable" => ExecutionLevel::HighestAvailable,
+            "requireAdministrator" => ExecutionLevel::RequireAdministrator,
+            value => ExecutionLevel::Other(value.to_string()),
+        }
+    }
+}
+
+/// An operating system declared through `supportedOS`
+#[derive(Debug, Clone, PartialEq)]
+pub enum SupportedOs {
+    WindowsVista,
+    Windows7,
+    Windows8,
+    Windows81,
+    /// Windows 10, Windows 11 and the matching Server releases
+    Windows10,
+    /// A GUID that is not known, as written in the manifest
+    Other(String),
+}
+
+impl From<&str> for SupportedOs {
+    fn from(value: &str) -> SupportedOs {
+        let guid = value.trim_matches(|c| c == '{' || c == '}')
+            .to_ascii_lowercase();
+        match guid.as_str() {
+            "e2011457-1546-43c5-a5fe-008deee3d3f0" => SupportedOs::WindowsVista,
+            "35138b9a-5d96-4fbd-8e2d-a2440225f93a" => SupportedOs::Windows7,
+            "4a2f28e3-53b9-4441-ba9c-d69d4a4a6e38" => SupportedOs::Windows8,
+            "1f676c76-80e1-4239-95bb-83d0f6d0da78" => SupportedOs::Windows81,
+            "8e0f7a12-bfb3-4fe8-b9a5-48fd50a15a9a" => SupportedOs::Windows10,
+            _ => SupportedOs::Other(value.to_string()),
+        }
+    }
+}
+
+/// An application manifest along with the settings audited most often
+#[derive(Debug, Clone, PartialEq)]
+pub struct Manifest {
+    /// The kind of manifest, when it comes from a resource
+    pub kind: Option<ManifestKind>,
+    /// The decoded XML text
+    pub xml: String,
+    /// The `level` attribute of `requestedExecutionLevel`
+    pub execution_level: Option<ExecutionLevel>,
+    /// The `uiAccess` attribute of `requestedExecutionLevel`
+    pub ui_access: Option<bool>,
+    /// The content of `dpiAware`, e.g. `true` or `true/pm`
+    pub dpi_aware: Option<String>,
+    /// The content of `dpiAwareness`, e.g. `PerMonitorV2, PerMonitor`
+    pub dpi_awareness: Option<String>,
+    /// The content of `longPathAware`
+    pub long_path_aware: Option<bool>,
+    /// The operating systems listed under `compatibility`
+    pub supported_os: Vec<SupportedOs>,
+}
+
+impl Manifest {
+    /// Decodes a manifest. The text may be UTF-8 or UTF-16 in either byte
+    /// order, with or without a byte order mark; anything else is decoded
+    /// as UTF-8, replacing invalid sequences.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let xml = decode_text(bytes);
+        let mut manifest = Self {
+            kind: None,
+            xml: String::new(),
+            execution_level: None,
+            ui_access: None,
+            dpi_aware: None,
+            dpi_awareness: None,
+            long_path_aware: None,
+            supported_os: Vec::new(),
+        };
+
+        for element in Scanner::new(&xml) {
+            match element.name {
+                "requestedExecutionLevel" => {
+                    manifest.execution_level = element.attribute("level")
+                        .map(|level| level.as_str().into());
+                    manifest.ui_access = element.attribute("uiAccess")
+                        .and_then(|value| parse_bool(&value));
+                },
+                "supportedOS" => {
+                    if let Some(id) = element.attribute("Id") {
+                        manifest.supported_os.push(id.as_str().into());
+                    }
+                },
+                "dpiAware" => manifest.dpi_aware = element.text,
+                "dpiAwareness" => manifest.dpi_awareness = element.text,
+                "longPathAware" => {
+                    manifest.long_path_aware = element.text.as_deref()
+                        .and_then(parse_bool);
+                },
+                _ => {},
+            }
+        }
+
+        manifest.xml = xml;
+        manifest
+    }
+
+    /// Decodes the manifests of `pe` stored under the IDs used by the loader,
+    /// in the order they appear in the resource tree
+    pub fn from_pe(pe: &PE) -> Result<Vec<Self>> {
+        let tree = ResourceTree::from_pe(pe)?;
+        let mut manifests = Vec::new();
+        for resource in tree.resources_of_type(ResourceType::Manifest) {
+            let Some(kind) = ManifestKind::from_id(&resource.name) else {
+                continue
+            };
+            let mut manifest = Self::from_bytes(&resource.data(pe)?);
+            manifest.kind = Some(kind);
+            manifests.push(manifest);
+        }
+        Ok(manifests)
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+/// Decodes the text of a manifest, guessing the encoding from the byte order
+/// mark or, failing that, from where the zero bytes of the leading `<` are
+fn decode_text(bytes: &[u8]) -> String {
+    let utf16 = |bytes: &[u8], big_endian: bool| {
+        let units: Vec<u16> = bytes.chunks_exact(2)
+            .map(|unit| match big_endian {
+                true => u16::from_be_bytes([unit[0], unit[1]]),
+                false => u16::from_le_bytes([unit[0], unit[1]]),
+            })
+            .collect();
+        String::from_utf16_lossy(&units)
+    };
+
+    let text = match bytes {
+        [0xef, 0xbb, 0xbf, rest @ ..] => String::from_utf8_lossy(rest).into(),
+        [0xff, 0xfe, rest @ ..] => utf16(rest, false),
+        [0xfe, 0xff, rest @ ..] => utf16(rest, true),
+        [b'<', 0, ..] => utf16(bytes, false),
+        [0, b'<', ..] => utf16(bytes, true),
+        _ => String::from_utf8_lossy(bytes).into(),
+    };
+    // Resource compilers often pad the data with zeroes
+    text.trim_end_matches('\0').to_string()
+}
+
+/// An element met by the `Scanner`
+struct Element<'a> {
+    /// The name, without its namespace prefix
+    name: &'a str,
+    /// The attributes, names stripped of their prefix and values still
+    /// escaped
+    attributes: Vec<(&'a str, &'a str)>,
+    /// The text directly following the start tag, trimmed, if not empty
+    text: Option<String>,
+}
+
+impl Element<'_> {
+    fn attribute(&self, name: &str) -> Option<String> {
+        self.attributes.iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| unescape(value))
+    }
+}
+
+/// Yields the start tags of an XML document. This is not a validating
+/// parser: it only understands as much XML as manifests use, skipping
+/// comments, processing instructions and declarations.
+struct Scanner<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(text: &'a str) -> Self {
+        Self { rest: text }
+    }
+
+    /// Skips past `end`, or to the end of the document if it is missing
+    fn skip_past(&mut self, end: &str) {
+        self.rest = match self.rest.find(end) {
+            Some(index) => &self.rest[index + end.len()..],
+            None => "",
+        };
+    }
+}
+
+impl<'a> Iterator for Scanner<'a> {
+    type Item = Element<'a>;
+
+    fn next(&mut self) -> Option<Element<'a>> {
+        loop {
+            let start = self.rest.find('<')?;
+            self.rest = &self.rest[start..];
+
+            if self.rest.starts_with("<!--") {
+                self.skip_past("-->");
+                continue;
+            }
+            if self.rest.starts_with("<![CDATA[") {
+                self.skip_past("]]>");
+                continue;
+            }
+            if self.rest.starts_with("<?") {
+                self.skip_past("?>");
+                continue;
+            }
+            if self.rest.starts_with("<!") || self.rest.starts_with("</") {
+                self.skip_past(">");
+                continue;
+            }
+
+            let tag = self.rest;
+            let (element, rest) = parse_start_tag(&tag[1..])?;
+            self.rest = rest;
+            return Some(element);
+        }
+    }
+}
+
+/// Parses a start tag, `tag` pointing right after the `<`. Returns the
+/// element and what follows the tag.
+fn parse_start_tag(tag: &str) -> Option<(Element<'_>, &str)> {
+    let is_name_end = |c: char| c.is_whitespace() || c == '/' || c == '>';
+    let name_end = tag.find(is_name_end)?;
+    let name = local_name(&tag[..name_end]);
+
+    let mut attributes = Vec::new();
+    let mut rest = &tag[name_end..];
+    let self_closing = loop {
+        rest = rest.trim_start();
+        if let Some(after) = rest.strip_prefix("/>") {
+            rest = after;
+            break true;
+        }
+        if let Some(after) = rest.strip_prefix('>') {
+            rest = after;
+            break false;
+        }
+
+        let equals = rest.find('=')?;
+        let key = local_name(rest[..equals].trim());
+        let value = rest[equals + 1..].trim_start();
+        let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let end = value[1..].find(quote)?;
+        attributes.push((key, &value[1..1 + end]));
+        rest = &value[end + 2..];
+    };
+
+    let text = if self_closing {
+        None
+    } else {
+        let end = rest.find('<').unwrap_or(rest.len());
+        Some(unescape(rest[..end].trim())).filter(|text| !text.is_empty())
+    };
+
+    Some((Element { name, attributes, text }, rest))
+}
+
+/// Strips the namespace prefix of a name, e.g. `ws2:dpiAwareness`
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+/// Replaces the predefined entities and character references of `text`
+fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let Some(end) = rest.find(';') else { break };
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity.strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(|code| code.ok())
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                result.push(c);
+                rest = &rest[end + 1..];
+            },
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            },
+        }
+    }
+    result.push_str(rest);
+    result
+}
@@ -0,0 +1,179 @@
+use crate::{
+    error::{Result, PeError},
+    parsing::*,
+    resources::{ResourceId, ResourceTree, ResourceType},
+    PE,
+};
+
+/// Deepest nesting of popups accepted
+const MAX_DEPTH: usize = 16;
+
+/// `MF_POPUP`, the item opens a submenu
+const MF_POPUP: u16 = 0x10;
+/// `MF_END`, the item is the last of its menu
+const MF_END: u16 = 0x80;
+/// Flag of `MENUEX_TEMPLATE_ITEM` for items that open a submenu
+const EX_POPUP: u16 = 0x01;
+
+/// An item of a menu, from either a `MENUITEMTEMPLATE` or a
+/// `MENUEX_TEMPLATE_ITEM`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MenuItem {
+    /// The `MF_*` flags of a standard item, or the popup and last item bits
+    /// of an extended one.
+    pub flags: u16,
+    /// The `MFT_*` type. Extended templates only, 0 otherwise.
+    pub item_type: u32,
+    /// The `MFS_*` state. Extended templates only, 0 otherwise.
+    pub state: u32,
+    /// The command identifier. Standard popups have none.
+    pub id: u32,
+    /// Context help identifier of a popup. Extended templates only, 0
+    /// otherwise.
+    pub help_id: u32,
+    /// The text of the item, empty for separators.
+    pub text: String,
+    /// The items of the submenu, if the item opens one.
+    pub children: Option<Vec<MenuItem>>,
+}
+
+/// A `MENUITEMTEMPLATEHEADER` or `MENUEX_TEMPLATE_HEADER` along with the
+/// items of the menu
+#[derive(Debug, Clone, PartialEq)]
+pub struct MenuTemplate {
+    /// Set for `MENUEX_TEMPLATE_HEADER`.
+    pub extended: bool,
+    /// Context help identifier of the menu bar. Extended templates only, 0
+    /// otherwise.
+    pub help_id: u32,
+    pub items: Vec<MenuItem>,
+}
+
+impl MenuTemplate {
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let (version, bytes) = take_u16(data)?;
+        let (offset, rest) = take_u16(bytes)?;
+        let extended = match version {
+            0 => false,
+            1 => true,
+            _ => return Err(PeError::InvalidMenu),
+        };
+
+        // The offset of the first item counts from the end of its own field
+        let (_, items) = take_bytes(rest, offset as usize)?;
+        let (help_id, items) = if extended {
+            (take_u32(rest)?.0, items)
+        } else {
+            (0, items)
+        };
+
+        let parser = MenuParser { data, extended };
+        let (items, _) = parser.items(items, 0)?;
+        Ok(Self { extended, help_id, items })
+    }
+}
+
+struct MenuParser<'a> {
+    data: &'a [u8],
+    extended: bool,
+}
+
+impl<'a> MenuParser<'a> {
+    /// Parses the items of a menu, up to the one flagged as the last
+    fn items(&self, mut bytes: &'a [u8], depth: usize)
+            -> Result<(Vec<MenuItem>, &'a [u8])> {
+        if depth > MAX_DEPTH {
+            return Err(PeError::InvalidMenu);
+        }
+
+        let mut items = Vec::new();
+        loop {
+            let (item, last, rest) = match self.extended {
+                true => self.extended_item(bytes, depth)?,
+                false => self.item(bytes, depth)?,
+            };
+            items.push(item);
+            bytes = rest;
+            if last {
+                return Ok((items, bytes));
+            }
+        }
+    }
+
+    fn item(&self, bytes: &'a [u8], depth: usize)
+            -> Result<(MenuItem, bool, &'a [u8])> {
+        let (flags, bytes) = take_u16(bytes)?;
+        let (id, bytes) = match flags & MF_POPUP {
+            0 => take_u16(bytes)?,
+            _ => (0, bytes),
+        };
+        let (text, mut bytes) = take_utf16_cstr(bytes)?;
+
+        let mut children = None;
+        if flags & MF_POPUP != 0 {
+            let (items, rest) = self.items(bytes, depth + 1)?;
+            children = Some(items);
+            bytes = rest;
+        }
+
+        Ok((MenuItem {
+            flags, item_type: 0, state: 0, id: id as u32, help_id: 0, text,
+            children
+        }, flags & MF_END != 0, bytes))
+    }
+
+    /// Parses a `MENUEX_TEMPLATE_ITEM`. Items, and the help identifier of
+    /// popups, are aligned on 32 bits relative to the start of the template.
+    fn extended_item(&self, bytes: &'a [u8], depth: usize)
+            -> Result<(MenuItem, bool, &'a [u8])> {
+        let bytes = self.align(bytes)?;
+        let (item_type, bytes) = take_u32(bytes)?;
+        let (state, bytes) = take_u32(bytes)?;
+        let (id, bytes) = take_u32(bytes)?;
+        let (flags, bytes) = take_u16(bytes)?;
+        let (text, mut bytes) = take_utf16_cstr(bytes)?;
+
+        let mut help_id = 0;
+        let mut children = None;
+        if flags & EX_POPUP != 0 {
+            let (id, rest) = take_u32(self.align(bytes)?)?;
+            let (items, rest) = self.items(rest, depth + 1)?;
+            help_id = id;
+            children = Some(items);
+            bytes = rest;
+        }
+
+        Ok((MenuItem {
+            flags, item_type, state, id, help_id, text, children
+        }, flags & MF_END != 0, bytes))
+    }
+
+    fn align(&self, bytes: &'a [u8]) -> Result<&'a [u8]> {
+        let offset = self.data.len() - bytes.len();
+        let padding = (4 - offset % 4) % 4;
+        Ok(take_bytes(bytes, padding)?.1)
+    }
+}
+
+/// An `RT_MENU` resource
+#[derive(Debug, Clone, PartialEq)]
+pub struct Menu {
+    pub name: ResourceId,
+    pub language: ResourceId,
+    pub template: MenuTemplate,
+}
+
+impl Menu {
+    /// Decodes every `RT_MENU` resource of `pe`
+    pub fn from_pe(pe: &PE) -> Result<Vec<Self>> {
+        let tree = ResourceTree::from_pe(pe)?;
+        tree.resources_of_type(ResourceType::Menu)
+            .into_iter()
+            .map(|resource| Ok(Self {
+                template: MenuTemplate::from_bytes(&resource.data(pe)?)?,
+                name: resource.name,
+                language: resource.language,
+            }))
+            .collect()
+    }
+}
@@ -0,0 +1,314 @@
+pub mod accelerators;
+pub mod dialogs;
+pub mod icons;
+pub mod manifest;
+pub mod menus;
+pub mod strings;
+pub mod version;
+
+use std::{borrow::Cow, fmt};
+
+use crate::{
+    error::{Result, PeError},
+    parsing::*,
+    PE,
+};
+
+/// Deepest directory nesting accepted while walking the tree. Well formed
+/// files only use three levels: type, name and language.
+const MAX_DEPTH: usize = 8;
+
+/// Upper bound on the number of entries visited in the whole tree.
+const MAX_ENTRIES: usize = 0x10000;
+
+/// The predefined resource types
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResourceType {
+    Cursor = 1,
+    Bitmap = 2,
+    Icon = 3,
+    Menu = 4,
+    Dialog = 5,
+    String = 6,
+    FontDir = 7,
+    Font = 8,
+    Accelerator = 9,
+    RcData = 10,
+    MessageTable = 11,
+    GroupCursor = 12,
+    GroupIcon = 14,
+    Version = 16,
+    DlgInclude = 17,
+    PlugPlay = 19,
+    Vxd = 20,
+    AniCursor = 21,
+    AniIcon = 22,
+    Html = 23,
+    Manifest = 24,
+}
+
+/// Identifies a node of the tree, either by number or by name
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ResourceId {
+    Id(u16),
+    Name(String),
+}
+
+impl ResourceId {
+    /// Returns the numeric identifier, if the node is not named
+    pub fn id(&self) -> Option<u16> {
+        match self {
+            Self::Id(id) => Some(*id),
+            Self::Name(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for ResourceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Id(id) => write!(f, "#{}", id),
+            Self::Name(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl From<ResourceType> for ResourceId {
+    fn from(kind: ResourceType) -> ResourceId {
+        ResourceId::Id(kind as u16)
+    }
+}
+
+/// The `IMAGE_RESOURCE_DIRECTORY` table, with its entries
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResourceDirectory {
+    /// Resource flags, reserved for future use.
+    pub characteristics: u32,
+    /// The time that the resource data was created by the resource compiler.
+    pub time_date_stamp: u32,
+    /// The major version number, set by the user.
+    pub major_version: u16,
+    /// The minor version number, set by the user.
+    pub minor_version: u16,
+    /// The number of entries that use strings to identify them.
+    pub number_of_named_entries: u16,
+    /// The number of entries that use numeric IDs to identify them.
+    pub number_of_id_entries: u16,
+    /// The entries of the directory, named ones first
+    pub entries: Vec<ResourceEntry>,
+}
+
+impl ResourceDirectory {
+    pub fn len() -> usize {
+        16usize
+    }
+}
+
+/// A child of a resource directory
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceEntry {
+    pub id: ResourceId,
+    pub node: ResourceNode,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResourceNode {
+    Directory(ResourceDirectory),
+    Data(ResourceDataEntry),
+}
+
+/// The `IMAGE_RESOURCE_DATA_ENTRY` structure, describing a leaf of the tree
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResourceDataEntry {
+    /// The RVA of the resource data.
+    pub rva: u32,
+    /// The size, in bytes, of the resource data.
+    pub size: u32,
+    /// The code page that is used to decode code point values within the
+    /// resource data. Typically, the code page would be the Unicode code
+    /// page.
+    pub code_page: u32,
+    /// Reserved, must be 0.
+    pub reserved: u32,
+}
+
+impl ResourceDataEntry {
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8])> {
+        let (rva, bytes) = take_u32(bytes)?;
+        let (size, bytes) = take_u32(bytes)?;
+        let (code_page, bytes) = take_u32(bytes)?;
+        let (reserved, bytes) = take_u32(bytes)?;
+
+        Ok((Self { rva, size, code_page, reserved }, bytes))
+    }
+
+    pub fn len() -> usize {
+        16usize
+    }
+}
+
+/// A leaf of the tree along with the type, name and language it is filed
+/// under
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resource {
+    pub kind: ResourceId,
+    pub name: ResourceId,
+    pub language: ResourceId,
+    pub data: ResourceDataEntry,
+}
+
+/// The resource tree of an image
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResourceTree {
+    pub root: ResourceDirectory,
+}
+
+impl ResourceTree {
+    /// Walks the resource directory of `pe`. An image without resources gives
+    /// an empty tree.
+    pub fn from_pe(pe: &PE) -> Result<Self> {
+        let directory = match pe.opt_header.data_directories.resource() {
+            Some(directory) => *directory,
+            None => return Ok(Self::default()),
+        };
+
+        let mut walker = Walker {
+            pe,
+            base: directory.virtual_address,
+            path: Vec::new(),
+            visited: 0,
+        };
+        let root = walker.directory(0)?;
+
+        Ok(Self { root })
+    }
+
+    /// Returns every leaf sitting at the usual type/name/language depth
+    pub fn resources(&self) -> Vec<Resource> {
+        let mut resources = Vec::new();
+        for kind in &self.root.entries {
+            let ResourceNode::Directory(names) = &kind.node else { continue };
+            for name in &names.entries {
+                let ResourceNode::Directory(languages) = &name.node else {
+                    continue
+                };
+                for language in &languages.entries {
+                    if let ResourceNode::Data(data) = &language.node {
+                        resources.push(Resource {
+                            kind: kind.id.clone(),
+                            name: name.id.clone(),
+                            language: language.id.clone(),
+                            data: *data,
+                        });
+                    }
+                }
+            }
+        }
+        resources
+    }
+
+    /// Returns the leaves of the given type
+    pub fn resources_of_type(&self, kind: impl Into<ResourceId>)
+            -> Vec<Resource> {
+        let kind = kind.into();
+        self.resources()
+            .into_iter()
+            .filter(|resource| resource.kind == kind)
+            .collect()
+    }
+
+    /// Returns the first leaf filed under `kind` and `name`, in any language
+    pub fn find(&self, kind: impl Into<ResourceId>, name: &ResourceId)
+            -> Option<Resource> {
+        let kind = kind.into();
+        self.resources()
+            .into_iter()
+            .find(|resource| resource.kind == kind && &resource.name == name)
+    }
+}
+
+impl Resource {
+    /// Returns the content of the resource
+    pub fn data<'pe>(&self, pe: &PE<'pe>) -> Result<Cow<'pe, [u8]>> {
+        pe.read_at_rva(self.data.rva, self.data.size as usize)
+    }
+}
+
+/// Walks the directories of the tree. Offsets are relative to the start of
+/// the resource directory.
+struct Walker<'a, 'pe> {
+    pe: &'a PE<'pe>,
+    base: u32,
+    /// Offsets of the directories between the root and the current one
+    path: Vec<u32>,
+    visited: usize,
+}
+
+impl Walker<'_, '_> {
+    fn read(&self, offset: u32, len: usize) -> Result<Cow<'_, [u8]>> {
+        self.pe.read_at_rva(self.base.wrapping_add(offset), len)
+    }
+
+    fn directory(&mut self, offset: u32) -> Result<ResourceDirectory> {
+        if self.path.contains(&offset) {
+            return Err(PeError::ResourceCycle(offset));
+        }
+        if self.path.len() >= MAX_DEPTH {
+            return Err(PeError::ResourceTooDeep(offset));
+        }
+        self.path.push(offset);
+
+        let data = self.read(offset, ResourceDirectory::len())?;
+        let (characteristics, bytes) = take_u32(&data)?;
+        let (time_date_stamp, bytes) = take_u32(bytes)?;
+        let (major_version, bytes) = take_u16(bytes)?;
+        let (minor_version, bytes) = take_u16(bytes)?;
+        let (number_of_named_entries, bytes) = take_u16(bytes)?;
+        let (number_of_id_entries, _) = take_u16(bytes)?;
+
+        let count = number_of_named_entries as usize
+            + number_of_id_entries as usize;
+        let mut entries = Vec::with_capacity(count);
+        for index in 0..count {
+            self.visited += 1;
+            if self.visited > MAX_ENTRIES {
+                return Err(PeError::ResourceTooLarge);
+            }
+
+            let entry_offset = offset
+                .wrapping_add(ResourceDirectory::len() as u32)
+                .wrapping_add(index as u32 * 8);
+            let data = self.read(entry_offset, 8)?;
+            let (name, bytes) = take_u32(&data)?;
+            let (target, _) = take_u32(bytes)?;
+
+            // The high bit selects a name string over a numeric ID
+            let id = if name & 0x8000_0000 != 0 {
+                let name_offset = name & 0x7fff_ffff;
+                let (len, _) = take_u16(&self.read(name_offset, 2)?)?;
+                let name = self.pe.read_utf16_at_rva(
+                    self.base.wrapping_add(name_offset).wrapping_add(2),
+                    len as usize)?;
+                ResourceId::Name(name)
+            } else {
+                ResourceId::Id(name as u16)
+            };
+
+            // The high bit selects a subdirectory over a data entry
+            let node = if target & 0x8000_0000 != 0 {
+                ResourceNode::Directory(self.directory(target & 0x7fff_ffff)?)
+            } else {
+                let data = self.read(target, ResourceDataEntry::len())?;
+                ResourceNode::Data(ResourceDataEntry::from_bytes(&data)?.0)
+            };
+
+            entries.push(ResourceEntry { id, node });
+        }
+
+        self.path.pop();
+        Ok(ResourceDirectory {
+            characteristics, time_date_stamp, major_version, minor_version,
+            number_of_named_entries, number_of_id_entries, entries
+        })
+    }
+}
@@ -0,0 +1,148 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    error::{Result, PeError},
+    parsing::*,
+    resources::{ResourceId, ResourceTree, ResourceType},
+    PE,
+};
+
+/// Number of strings in each `RT_STRING` block
+const STRINGS_PER_BLOCK: u16 = 16;
+
+/// The strings of a language, gathered from all the resources of one type
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalizedStrings {
+    pub language: ResourceId,
+    pub strings: BTreeMap<u32, String>,
+}
+
+impl LocalizedStrings {
+    pub fn get(&self, id: u32) -> Option<&str> {
+        self.strings.get(&id).map(String::as_str)
+    }
+}
+
+/// Decodes an `RT_STRING` block. Block `n` holds the strings with IDs
+/// `(n - 1) * 16` through `(n - 1) * 16 + 15`, each stored as a length
+/// followed by that many UTF-16 code units. Empty strings are left out.
+pub fn parse_string_block(bytes: &[u8], block_id: u16)
+        -> Result<Vec<(u16, String)>> {
+    let first_id = block_id.wrapping_sub(1).wrapping_mul(STRINGS_PER_BLOCK);
+
+    let mut strings = Vec::new();
+    let mut bytes = bytes;
+    for index in 0..STRINGS_PER_BLOCK {
+        let (len, rest) = take_u16(bytes)?;
+        let (string, rest) = take_utf16(rest, len as usize)?;
+        if len != 0 {
+            strings.push((first_id.wrapping_add(index), string));
+        }
+        bytes = rest;
+    }
+    Ok(strings)
+}
+
+/// Decodes the `RT_STRING` resources of `pe`, one map per language
+pub fn string_tables(pe: &PE) -> Result<Vec<LocalizedStrings>> {
+    let tree = ResourceTree::from_pe(pe)?;
+    let mut tables: Vec<LocalizedStrings> = Vec::new();
+    for resource in tree.resources_of_type(ResourceType::String) {
+        // Blocks are always numbered, a named block cannot be located
+        let Some(block_id) = resource.name.id() else { continue };
+        let strings = parse_string_block(&resource.data(pe)?, block_id)?;
+        let table = localized(&mut tables, &resource.language);
+        table.strings.extend(strings.into_iter()
+            .map(|(id, string)| (id as u32, string)));
+    }
+    Ok(tables)
+}
+
+/// A `MESSAGE_RESOURCE_BLOCK`, covering a range of message IDs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MessageBlock {
+    /// The lowest message ID of the block.
+    pub low_id: u32,
+    /// The highest message ID of the block.
+    pub high_id: u32,
+    /// The offset of the entries, from the start of the resource.
+    pub offset_to_entries: u32,
+}
+
+impl MessageBlock {
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8])> {
+        let (low_id, bytes) = take_u32(bytes)?;
+        let (high_id, bytes) = take_u32(bytes)?;
+        let (offset_to_entries, bytes) = take_u32(bytes)?;
+
+        Ok((Self { low_id, high_id, offset_to_entries }, bytes))
+    }
+
+    pub fn len() -> usize {
+        12usize
+    }
+}
+
+/// Decodes an `RT_MESSAGETABLE` resource. Each entry is flagged as either
+/// ANSI, decoded as Latin-1, or UTF-16. Trailing NULs are dropped but the
+/// line breaks ending most messages are kept.
+pub fn parse_message_table(bytes: &[u8]) -> Result<Vec<(u32, String)>> {
+    let (number_of_blocks, mut rest) = take_u32(bytes)?;
+
+    let mut messages = Vec::new();
+    for _ in 0..number_of_blocks {
+        let (block, remaining) = MessageBlock::from_bytes(rest)?;
+        rest = remaining;
+        if block.low_id > block.high_id {
+            return Err(PeError::InvalidMessageTable);
+        }
+
+        let mut offset = block.offset_to_entries as usize;
+        for id in block.low_id..=block.high_id {
+            let entry = bytes.get(offset..).ok_or(PeError::BufferTooSmall)?;
+            let (length, entry) = take_u16(entry)?;
+            let (flags, entry) = take_u16(entry)?;
+            // The length counts the header, an empty entry would not move on
+            if length < 4 {
+                return Err(PeError::InvalidMessageTable);
+            }
+            let (text, _) = take_bytes(entry, length as usize - 4)?;
+
+            let text = match flags {
+                // MESSAGE_RESOURCE_UNICODE
+                0x0001 => utf16_to_string(text),
+                _ => text.iter().map(|&byte| byte as char).collect(),
+            };
+            messages.push((id, text.trim_end_matches('\0').to_string()));
+            offset += length as usize;
+        }
+    }
+    Ok(messages)
+}
+
+/// Decodes the `RT_MESSAGETABLE` resources of `pe`, one map per language
+pub fn message_tables(pe: &PE) -> Result<Vec<LocalizedStrings>> {
+    let tree = ResourceTree::from_pe(pe)?;
+    let mut tables: Vec<LocalizedStrings> = Vec::new();
+    for resource in tree.resources_of_type(ResourceType::MessageTable) {
+        let messages = parse_message_table(&resource.data(pe)?)?;
+        localized(&mut tables, &resource.language).strings.extend(messages);
+    }
+    Ok(tables)
+}
+
+/// Returns the map of `language`, adding it if it is not there yet
+fn localized<'a>(tables: &'a mut Vec<LocalizedStrings>, language: &ResourceId)
+        -> &'a mut LocalizedStrings {
+    let index = match tables.iter().position(|t| &t.language == language) {
+        Some(index) => index,
+        None => {
+            tables.push(LocalizedStrings {
+                language: language.clone(),
+                strings: BTreeMap::new(),
+            });
+            tables.len() - 1
+        },
+    };
+    &mut tables[index]
+}
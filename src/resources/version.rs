@@ -0,0 +1,390 @@
+use crate::{
+    error::{Result, PeError},
+    parsing::*,
+    resources::{ResourceTree, ResourceType},
+    PE,
+};
+
+/// Signature of the `VS_FIXEDFILEINFO` structure
+const FIXED_FILE_INFO_SIGNATURE: u32 = 0xfeef_04bd;
+
+/// Deepest nesting of blocks accepted. `VS_VERSIONINFO` only uses four
+/// levels: the root, `StringFileInfo`, string tables and strings.
+const MAX_DEPTH: usize = 8;
+
+#[derive(Debug, PartialEq)]
+pub enum FileFlag {
+    /// The file contains debugging information or is compiled with debugging
+    /// features enabled.
+    Debug = 0x01,
+    /// The file is a development version, not a commercially released
+    /// product.
+    Prerelease = 0x02,
+    /// The file has been modified and is not identical to the original
+    /// shipping file of the same version number.
+    Patched = 0x04,
+    /// The file was not built using standard release procedures.
+    PrivateBuild = 0x08,
+    /// The file's version structure was created dynamically.
+    InfoInferred = 0x10,
+    /// The file was built by the original company using standard release
+    /// procedures but is a variation of the normal file of the same version
+    /// number.
+    SpecialBuild = 0x20,
+    /// Invalid
+    Invalid = 0x0,
+}
+
+impl FileFlag {
+    pub fn to_vec(value: u32) -> Vec<Self> {
+        let mut flags = Vec::new();
+
+        for i in 0..6 {
+            let new_flag = match (1 << i) & value {
+                0x01 => Self::Debug,
+                0x02 => Self::Prerelease,
+                0x04 => Self::Patched,
+                0x08 => Self::PrivateBuild,
+                0x10 => Self::InfoInferred,
+                0x20 => Self::SpecialBuild,
+                _ => Self::Invalid,
+            };
+            if new_flag != Self::Invalid {
+                flags.push(new_flag);
+            }
+        }
+        flags
+    }
+}
+
+/// The operating system for which the file was designed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileOs {
+    Unknown,
+    Dos,
+    Os216,
+    Os232,
+    Nt,
+    WinCe,
+    Windows16,
+    Pm16,
+    Pm32,
+    Windows32,
+    DosWindows16,
+    DosWindows32,
+    Os216Pm16,
+    Os232Pm32,
+    NtWindows32,
+    Other(u32),
+}
+
+impl From<u32> for FileOs {
+    fn from(value: u32) -> FileOs {
+        match value {
+            0x0000_0000 => FileOs::Unknown,
+            0x0001_0000 => FileOs::Dos,
+            0x0002_0000 => FileOs::Os216,
+            0x0003_0000 => FileOs::Os232,
+            0x0004_0000 => FileOs::Nt,
+            0x0005_0000 => FileOs::WinCe,
+            0x0000_0001 => FileOs::Windows16,
+            0x0000_0002 => FileOs::Pm16,
+            0x0000_0003 => FileOs::Pm32,
+            0x0000_0004 => FileOs::Windows32,
+            0x0001_0001 => FileOs::DosWindows16,
+            0x0001_0004 => FileOs::DosWindows32,
+            0x0002_0002 => FileOs::Os216Pm16,
+            0x0003_0003 => FileOs::Os232Pm32,
+            0x0004_0004 => FileOs::NtWindows32,
+            value => FileOs::Other(value),
+        }
+    }
+}
+
+/// The general type of file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileType {
+    Unknown,
+    App,
+    Dll,
+    /// A device driver. `file_subtype` holds the kind of driver.
+    Driver,
+    /// A font. `file_subtype` holds the kind of font.
+    Font,
+    /// A virtual device. `file_subtype` holds the virtual device identifier.
+    Vxd,
+    StaticLib,
+    Other(u32),
+}
+
+impl From<u32> for FileType {
+    fn from(value: u32) -> FileType {
+        match value {
+            0 => FileType::Unknown,
+            1 => FileType::App,
+            2 => FileType::Dll,
+            3 => FileType::Driver,
+            4 => FileType::Font,
+            5 => FileType::Vxd,
+            7 => FileType::StaticLib,
+            value => FileType::Other(value),
+        }
+    }
+}
+
+/// The `VS_FIXEDFILEINFO` structure
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FixedFileInfo {
+    /// Contains the value 0xFEEF04BD.
+    pub signature: u32,
+    /// The binary version number of this structure.
+    pub struc_version: u32,
+    /// The most significant 32 bits of the file's binary version number.
+    pub file_version_ms: u32,
+    /// The least significant 32 bits of the file's binary version number.
+    pub file_version_ls: u32,
+    /// The most significant 32 bits of the binary version number of the
+    /// product with which this file was distributed.
+    pub product_version_ms: u32,
+    /// The least significant 32 bits of the binary version number of the
+    /// product with which this file was distributed.
+    pub product_version_ls: u32,
+    /// Contains a bitmask that specifies the valid bits in `file_flags`.
+    pub file_flags_mask: u32,
+    /// Contains a bitmask that specifies the Boolean attributes of the file.
+    pub file_flags: u32,
+    /// The operating system for which this file was designed.
+    pub file_os: FileOs,
+    /// The general type of file.
+    pub file_type: FileType,
+    /// The function of the file, for drivers, fonts and virtual devices.
+    pub file_subtype: u32,
+    /// The most significant 32 bits of the file's binary creation date and
+    /// time stamp.
+    pub file_date_ms: u32,
+    /// The least significant 32 bits of the file's binary creation date and
+    /// time stamp.
+    pub file_date_ls: u32,
+}
+
+impl FixedFileInfo {
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8])> {
+        let (signature, bytes) = take_u32(bytes)?;
+        let (struc_version, bytes) = take_u32(bytes)?;
+        let (file_version_ms, bytes) = take_u32(bytes)?;
+        let (file_version_ls, bytes) = take_u32(bytes)?;
+        let (product_version_ms, bytes) = take_u32(bytes)?;
+        let (product_version_ls, bytes) = take_u32(bytes)?;
+        let (file_flags_mask, bytes) = take_u32(bytes)?;
+        let (file_flags, bytes) = take_u32(bytes)?;
+        let (file_os, bytes) = take_u32(bytes)?;
+        let (file_type, bytes) = take_u32(bytes)?;
+        let (file_subtype, bytes) = take_u32(bytes)?;
+        let (file_date_ms, bytes) = take_u32(bytes)?;
+        let (file_date_ls, bytes) = take_u32(bytes)?;
+
+        Ok((Self {
+            signature, struc_version, file_version_ms, file_version_ls,
+            product_version_ms, product_version_ls, file_flags_mask,
+            file_flags, file_os: file_os.into(), file_type: file_type.into(),
+            file_subtype, file_date_ms, file_date_ls
+        }, bytes))
+    }
+
+    pub fn len() -> usize {
+        52usize
+    }
+
+    /// Returns the file version as major, minor, build and revision
+    pub fn file_version(&self) -> (u16, u16, u16, u16) {
+        split_version(self.file_version_ms, self.file_version_ls)
+    }
+
+    /// Returns the product version as major, minor, build and revision
+    pub fn product_version(&self) -> (u16, u16, u16, u16) {
+        split_version(self.product_version_ms, self.product_version_ls)
+    }
+
+    /// Returns the flags that are both set and declared valid by the mask
+    pub fn flags(&self) -> Vec<FileFlag> {
+        FileFlag::to_vec(self.file_flags & self.file_flags_mask)
+    }
+}
+
+fn split_version(ms: u32, ls: u32) -> (u16, u16, u16, u16) {
+    ((ms >> 16) as u16, ms as u16, (ls >> 16) as u16, ls as u16)
+}
+
+/// A `StringTable` block, holding the strings for a language and code page
+#[derive(Debug, Clone, PartialEq)]
+pub struct StringTable {
+    /// The language and code page as 8 hex digits, e.g. `040904B0`
+    pub key: String,
+    pub language: u16,
+    pub code_page: u16,
+    /// The strings of the table, in file order
+    pub strings: Vec<(String, String)>,
+}
+
+impl StringTable {
+    /// Returns the value of the string named `key`
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.strings.iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// The decoded `VS_VERSIONINFO` structure of an `RT_VERSION` resource
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VersionInfo {
+    /// The fixed, language independent information, if present
+    pub fixed: Option<FixedFileInfo>,
+    /// The `StringFileInfo` tables, one per language
+    pub string_tables: Vec<StringTable>,
+    /// The language and code page pairs listed in `VarFileInfo`
+    pub translations: Vec<(u16, u16)>,
+}
+
+impl VersionInfo {
+    /// Decodes the content of an `RT_VERSION` resource
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let root = Block::parse(bytes, 0, 0)?;
+        if root.key != "VS_VERSION_INFO" {
+            return Err(PeError::InvalidVersionInfo);
+        }
+
+        let fixed = match FixedFileInfo::from_bytes(root.value) {
+            Ok((fixed, _)) if fixed.signature == FIXED_FILE_INFO_SIGNATURE => {
+                Some(fixed)
+            },
+            _ => None,
+        };
+
+        let mut info = Self { fixed, ..Self::default() };
+        for child in &root.children {
+            match child.key.as_str() {
+                "StringFileInfo" => {
+                    for table in &child.children {
+                        let id = u32::from_str_radix(&table.key, 16)
+                            .unwrap_or(0);
+                        let strings = table.children.iter()
+                            .map(|string| (string.key.clone(), string.text()))
+                            .collect();
+                        info.string_tables.push(StringTable {
+                            key: table.key.clone(),
+                            language: (id >> 16) as u16,
+                            code_page: id as u16,
+                            strings,
+                        });
+                    }
+                },
+                "VarFileInfo" => {
+                    for var in &child.children {
+                        if var.key != "Translation" {
+                            continue;
+                        }
+                        for pair in var.value.chunks_exact(4) {
+                            info.translations.push((
+                                u16::from_le_bytes([pair[0], pair[1]]),
+                                u16::from_le_bytes([pair[2], pair[3]]),
+                            ));
+                        }
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        Ok(info)
+    }
+
+    /// Decodes the first `RT_VERSION` resource of `pe`, if any
+    pub fn from_pe(pe: &PE) -> Result<Option<Self>> {
+        let tree = ResourceTree::from_pe(pe)?;
+        match tree.resources_of_type(ResourceType::Version).first() {
+            Some(resource) => Self::from_bytes(&resource.data(pe)?).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the value of the string named `key`, e.g. `FileVersion` or
+    /// `CompanyName`, from the first string table that defines it
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.string_tables.iter().find_map(|table| table.get(key))
+    }
+
+    /// Returns the value of the string named `key` from the table of the
+    /// given language
+    pub fn get_for_language(&self, language: u16, key: &str) -> Option<&str> {
+        self.string_tables.iter()
+            .filter(|table| table.language == language)
+            .find_map(|table| table.get(key))
+    }
+}
+
+/// The generic layout shared by every structure of `VS_VERSIONINFO`
+struct Block<'a> {
+    key: String,
+    value: &'a [u8],
+    children: Vec<Block<'a>>,
+    /// Declared length of the block, including its children
+    len: usize,
+}
+
+impl<'a> Block<'a> {
+    /// Parses the block at `offset`. Every field is aligned on 32 bits
+    /// relative to the start of `data`.
+    fn parse(data: &'a [u8], offset: usize, depth: usize) -> Result<Self> {
+        if depth > MAX_DEPTH {
+            return Err(PeError::InvalidVersionInfo);
+        }
+        let bytes = data.get(offset..).ok_or(PeError::BufferTooSmall)?;
+        let (len, bytes) = take_u16(bytes)?;
+        let (value_len, bytes) = take_u16(bytes)?;
+        let (value_type, bytes) = take_u16(bytes)?;
+        let len = len as usize;
+        let end = offset.saturating_add(len).min(data.len());
+
+        let key_units = bytes.chunks_exact(2)
+            .take_while(|unit| unit != &[0, 0])
+            .count();
+        let key = utf16_to_string(&bytes[..key_units * 2]);
+        let key_end = offset + 6 + key_units * 2 + 2;
+
+        // Text values count their length in UTF-16 code units
+        let value_size = if value_type == 1 {
+            value_len as usize * 2
+        } else {
+            value_len as usize
+        };
+        let value_start = align4(key_end).min(end);
+        let value_end = value_start.saturating_add(value_size).min(end);
+        let value = &data[value_start..value_end];
+
+        let mut children = Vec::new();
+        let mut child = align4(value_end);
+        while child + 6 <= end {
+            let block = Block::parse(&data[..end], child, depth + 1)?;
+            if block.len == 0 {
+                break;
+            }
+            child = align4(child + block.len);
+            children.push(block);
+        }
+
+        Ok(Self { key, value, children, len })
+    }
+
+    /// Decodes the value as a null terminated UTF-16 string
+    fn text(&self) -> String {
+        let units = self.value.chunks_exact(2)
+            .take_while(|unit| unit != &[0, 0])
+            .count();
+        utf16_to_string(&self.value[..units * 2])
+    }
+}
+
+fn align4(value: usize) -> usize {
+    (value + 3) & !3
+}
@@ -0,0 +1,103 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    directories::debug::{CodeView, DebugPayload},
+    error::Result,
+    PE,
+};
+
+/// The keys under which an image and its PDB are stored in a symbol store,
+/// which is laid out as `name/KEY/name`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolKeys {
+    /// The key of the image: its timestamp as 8 uppercase hex digits followed
+    /// by its size of image in lowercase hex
+    pub image_key: String,
+    /// The file name of the PDB, without the directories recorded by the
+    /// linker
+    pub pdb_name: Option<String>,
+    /// The key of the PDB: the GUID followed by the age in lowercase hex for
+    /// PDB 7.0, or the signature followed by the age for PDB 2.0
+    pub pdb_key: Option<String>,
+}
+
+impl SymbolKeys {
+    /// Computes the symbol store keys of `pe`. The PDB key is taken from the
+    /// first CodeView entry of the debug directory.
+    pub fn from_pe(pe: &PE) -> Result<Self> {
+        let image_key = image_key(pe.file_header.time_date_stamp,
+            pe.opt_header.win_fields.size_of_image());
+
+        let codeview = pe.debug_entries()?
+            .into_iter()
+            .find_map(|entry| match entry.payload {
+                Some(DebugPayload::CodeView(codeview)) => Some(codeview),
+                _ => None,
+            });
+        let (pdb_name, pdb_key) = match codeview {
+            Some(CodeView::Rsds { guid, age, path }) => {
+                (Some(file_name(&path)),
+                    Some(format!("{}{:x}", guid.to_simple_string(), age)))
+            },
+            Some(CodeView::Nb10 { signature, age, path, .. }) => {
+                (Some(file_name(&path)),
+                    Some(format!("{:08X}{:x}", signature, age)))
+            },
+            None => (None, None),
+        };
+
+        Ok(Self { image_key, pdb_name, pdb_key })
+    }
+
+    /// Returns where the image named `image_name` lives in the symbol store
+    /// at `store`
+    pub fn image_path(&self, store: &Path, image_name: &str) -> PathBuf {
+        store.join(image_name).join(&self.image_key).join(image_name)
+    }
+
+    /// Returns where the PDB of the image lives in the symbol store at
+    /// `store`, if the image references a PDB
+    pub fn pdb_path(&self, store: &Path) -> Option<PathBuf> {
+        let name = self.pdb_name.as_ref()?;
+        let key = self.pdb_key.as_ref()?;
+        Some(store.join(name).join(key).join(name))
+    }
+
+    /// Looks the image up in the symbol store at `store`, accepting the
+    /// compressed form where the last character of the name is replaced by
+    /// an underscore
+    pub fn find_image(&self, store: &Path, image_name: &str)
+            -> Option<PathBuf> {
+        find_file(self.image_path(store, image_name))
+    }
+
+    /// Looks the PDB up in the symbol store at `store`, accepting the
+    /// compressed form
+    pub fn find_pdb(&self, store: &Path) -> Option<PathBuf> {
+        find_file(self.pdb_path(store)?)
+    }
+}
+
+/// Returns the symbol store key of an image
+pub fn image_key(time_date_stamp: u32, size_of_image: u32) -> String {
+    format!("{:08X}{:x}", time_date_stamp, size_of_image)
+}
+
+/// Strips the directories from a path recorded by the linker, which may use
+/// either kind of separator
+fn file_name(path: &str) -> String {
+    path.rsplit(['\\', '/']).next().unwrap_or(path).to_string()
+}
+
+fn find_file(path: PathBuf) -> Option<PathBuf> {
+    if path.is_file() {
+        return Some(path);
+    }
+
+    let name = path.file_name()?.to_str()?;
+    let mut compressed = name.to_string();
+    compressed.pop();
+    compressed.push('_');
+    let compressed = path.with_file_name(compressed);
+    compressed.is_file().then_some(compressed)
+}